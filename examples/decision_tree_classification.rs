@@ -47,10 +47,12 @@ fn main() {
                 TrainingData {
                     x: x.clone().into(),
                     y: y.clone().into(),
+                    weights: None,
                 },
                 TrainingData {
                     x: x.clone().into(),
                     y: y.clone().into(),
+                    weights: None,
                 },
             ].to_stream(root_scope);
 
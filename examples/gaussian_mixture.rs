@@ -0,0 +1,58 @@
+extern crate flexi_logger;
+extern crate log;
+extern crate ml_dataflow;
+extern crate ndarray;
+extern crate timely;
+extern crate timely_communication;
+
+use flexi_logger::Logger;
+use ml_dataflow::data::serialization::*;
+use ml_dataflow::models::gmm::{CovarianceType, EmConvergenceCriteria, GaussianMixture};
+use ml_dataflow::models::kmeans::initializers::RandomSample;
+use ml_dataflow::models::{Predict, Train};
+use ndarray::prelude::*;
+use timely::dataflow::operators::*;
+use timely_communication::initialize::Configuration;
+
+fn main() {
+    Logger::with_env_or_str("ml_dataflow=debug")
+        .start()
+        .unwrap();
+    ::timely::execute(Configuration::Process(2), move |root| {
+        let n_components = 2;
+        let some_data: AbomonableArray<_, _> = arr2(&[
+            [0., 0.2, 0.],
+            [0.5, 0., 0.1],
+            [0., -0.4, 0.],
+            [-0.3, 0., 0.2],
+            [5., 5.1, 5.],
+            [4.6, 5., 5.],
+            [5., 5., 5.3],
+            [5.2, 4.8, 5.],
+        ]).into();
+
+        let end_criteria = <EmConvergenceCriteria<f64>>::default()
+            .limit_iterations(20)
+            .likelihood_change(1e-4);
+        let model = GaussianMixture::<f64, RandomSample>::new(
+            n_components,
+            some_data.view().cols(),
+            CovarianceType::Diagonal,
+            end_criteria,
+        );
+
+        root.dataflow::<usize, _, _>(|scope| {
+            let training_results = vec![some_data.clone()]
+                .to_stream(scope)
+                .train(&model)
+                .inspect(|result| println!("{:?}", result.means.view()));
+
+            vec![some_data]
+                .to_stream(scope)
+                .predict(&model, training_results.broadcast())
+                .inspect(|result| println!("{:?}", result.as_ref().unwrap().view()));
+        });
+
+        while root.step() {}
+    }).expect("Execute dataflow");
+}
@@ -0,0 +1,73 @@
+extern crate ml_dataflow;
+extern crate ndarray;
+extern crate timely;
+extern crate timely_communication;
+
+use ml_dataflow::data::dataflow::error_measures::Rmse;
+use ml_dataflow::data::dataflow::{ExchangeEvenly, SegmentTrainingData};
+use ml_dataflow::data::TrainingData;
+use ml_dataflow::models::decision_tree::regression::StreamingRegressionTree;
+use ml_dataflow::models::gradient_boost::GradientBoostingRegression;
+use ml_dataflow::models::model_selection::{ParameterGrid, SearchParameters};
+use ndarray::prelude::*;
+use timely::dataflow::operators::*;
+use timely::dataflow::Scope;
+use timely_communication::initialize::Configuration;
+
+fn main() {
+    ::timely::execute(Configuration::Process(2), move |root| {
+        let x = Array2::from_shape_fn((200, 1), |(row, _)| (row % 20) as i64);
+        let y = x.column(0).mapv(|v| (v as f64 * 0.5).sin() * 10.);
+
+        let points_per_worker = 500_000;
+        let candidates = ParameterGrid::new()
+            .axis("levels", vec![1., 2., 3.])
+            .axis("bins", vec![5., 10.])
+            .axis("learning_rate", vec![0.1, 0.3])
+            .candidates();
+
+        root.dataflow::<u64, _, _>(|root_scope| {
+            let training_stream = vec![TrainingData {
+                x: x.clone().into(),
+                y: y.clone().into(),
+                weights: None,
+            }].to_stream(root_scope);
+
+            root_scope.scoped::<u64, _, _>(|segment_scope| {
+                training_stream
+                    .enter(segment_scope)
+                    .segment_training_data(points_per_worker * segment_scope.peers() as u64)
+                    .exchange_evenly()
+                    .search_parameters(
+                        &candidates,
+                        |params| {
+                            let tree = StreamingRegressionTree::new(
+                                params.get("levels").unwrap() as u64,
+                                points_per_worker,
+                                params.get("bins").unwrap() as usize,
+                                1.0,
+                            );
+                            GradientBoostingRegression::new(
+                                20,
+                                tree,
+                                params.get("learning_rate").unwrap(),
+                            )
+                        },
+                        4,
+                        0,
+                        Rmse,
+                    )
+                    .inspect(|result| match result {
+                        Ok(result) => {
+                            for (params, scores) in &result.ranking {
+                                println!("{:.4}\t{}", scores.mean_error, params);
+                            }
+                        }
+                        Err(err) => println!("Search failed: {}", err),
+                    })
+                    .leave()
+            });
+        });
+        while root.step() {}
+    }).expect("Execute dataflow");
+}
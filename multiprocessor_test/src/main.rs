@@ -111,6 +111,7 @@ fn train(process_id: usize, config: Config) {
 
                 let training_stream: Stream<_, TrainingData<i64, f64>> = root_scope
                     .training_data_from_csv(path, samples_per_worker as usize)
+                    .expect("Read training data")
                     .map(move |training_data| {
                         let mut x_mapped =
                             unsafe { Array2::<i64>::uninitialized(training_data.x().dim()) };
@@ -126,6 +127,7 @@ fn train(process_id: usize, config: Config) {
                         TrainingData {
                             x: x_mapped.into(),
                             y: training_data.y,
+                            weights: None,
                         }
                     });
 
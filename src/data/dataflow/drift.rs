@@ -0,0 +1,461 @@
+//! Detection of concept drift in streams of prediction errors.
+//!
+//! The detectors observe one error value at a time, e.g. `0` or `1` for misclassified
+//! samples or the error rate of each timestamp computed with `MeasurePredictionError`.
+//! Once the errors get significantly worse than before, they report a drift, which can be
+//! used to trigger retraining.
+
+use fnv::FnvHashMap;
+use num_traits::ToPrimitive;
+use std::collections::VecDeque;
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::{operators::Operator, Scope, Stream};
+use timely::ExchangeData;
+
+/// State of a monitored stream after observing an error value
+#[derive(Abomonation, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DriftState {
+    Stable,
+    /// The errors increased, but not yet significantly
+    Warning,
+    /// The errors increased significantly. The detector only considers the errors after
+    /// the drift from now on.
+    Drift,
+}
+
+/// A detector that monitors a sequence of error values
+pub trait DriftDetector {
+    /// Adds the next error value to the monitored sequence
+    fn add(&mut self, value: f64) -> DriftState;
+}
+
+/// Drift Detection Method (Gama et al., 2004) for error values in `[0, 1]`.
+/// Reports a drift when the error rate rises a number of standard deviations above
+/// its minimum.
+#[derive(Abomonation, Clone, Debug)]
+pub struct Ddm {
+    min_samples: u64,
+    warning_level: f64,
+    drift_level: f64,
+    count: u64,
+    error_rate: f64,
+    min_error_rate: f64,
+    min_std: f64,
+}
+
+impl Ddm {
+    pub fn new() -> Self {
+        Ddm {
+            min_samples: 30,
+            warning_level: 2.,
+            drift_level: 3.,
+            count: 0,
+            error_rate: 0.,
+            min_error_rate: ::std::f64::INFINITY,
+            min_std: ::std::f64::INFINITY,
+        }
+    }
+
+    /// Sets the number of values that are observed before drifts are reported
+    pub fn min_samples(mut self, min_samples: u64) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+
+    /// Sets the number of standard deviations above the minimum error rate at which a
+    /// warning and a drift are reported
+    pub fn levels(mut self, warning_level: f64, drift_level: f64) -> Self {
+        assert!(
+            warning_level <= drift_level,
+            "The warning level must not exceed the drift level"
+        );
+        self.warning_level = warning_level;
+        self.drift_level = drift_level;
+        self
+    }
+
+    fn reset(&mut self) {
+        self.count = 0;
+        self.error_rate = 0.;
+        self.min_error_rate = ::std::f64::INFINITY;
+        self.min_std = ::std::f64::INFINITY;
+    }
+}
+
+impl Default for Ddm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DriftDetector for Ddm {
+    fn add(&mut self, value: f64) -> DriftState {
+        self.count += 1;
+        self.error_rate += (value - self.error_rate) / self.count as f64;
+        let std = (self.error_rate * (1. - self.error_rate) / self.count as f64).sqrt();
+        if self.count < self.min_samples {
+            return DriftState::Stable;
+        }
+
+        if self.error_rate + std <= self.min_error_rate + self.min_std {
+            self.min_error_rate = self.error_rate;
+            self.min_std = std;
+        }
+
+        let level = self.error_rate + std;
+        if level > self.min_error_rate + self.drift_level * self.min_std {
+            self.reset();
+            DriftState::Drift
+        } else if level > self.min_error_rate + self.warning_level * self.min_std {
+            DriftState::Warning
+        } else {
+            DriftState::Stable
+        }
+    }
+}
+
+/// Page-Hinkley test (Page, 1954), which reports a drift when the cumulative deviation
+/// of the values above their mean exceeds a threshold
+#[derive(Abomonation, Clone, Debug)]
+pub struct PageHinkley {
+    min_samples: u64,
+    delta: f64,
+    threshold: f64,
+    count: u64,
+    mean: f64,
+    deviation: f64,
+}
+
+impl PageHinkley {
+    pub fn new() -> Self {
+        PageHinkley {
+            min_samples: 30,
+            delta: 0.005,
+            threshold: 50.,
+            count: 0,
+            mean: 0.,
+            deviation: 0.,
+        }
+    }
+
+    /// Sets the number of values that are observed before drifts are reported
+    pub fn min_samples(mut self, min_samples: u64) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+
+    /// Sets the magnitude of changes that are tolerated
+    pub fn delta(mut self, delta: f64) -> Self {
+        self.delta = delta;
+        self
+    }
+
+    /// Sets the cumulative deviation at which a drift is reported
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+}
+
+impl Default for PageHinkley {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DriftDetector for PageHinkley {
+    fn add(&mut self, value: f64) -> DriftState {
+        self.count += 1;
+        self.mean += (value - self.mean) / self.count as f64;
+        self.deviation = (self.deviation + value - self.mean - self.delta).max(0.);
+
+        if self.count >= self.min_samples && self.deviation > self.threshold {
+            self.count = 0;
+            self.mean = 0.;
+            self.deviation = 0.;
+            DriftState::Drift
+        } else {
+            DriftState::Stable
+        }
+    }
+}
+
+/// Adaptive windowing (Bifet & Gavaldà, 2007) for values in `[0, 1]`. Keeps a window of
+/// recent values and drops its older part whenever the means of the two parts differ
+/// significantly. Checking the window takes linear time in its length, which is limited
+/// by `max_window`.
+#[derive(Clone, Debug)]
+pub struct Adwin {
+    delta: f64,
+    min_window: usize,
+    max_window: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Adwin {
+    pub fn new() -> Self {
+        Adwin {
+            delta: 0.002,
+            min_window: 5,
+            max_window: 10_000,
+            window: VecDeque::new(),
+            sum: 0.,
+        }
+    }
+
+    /// Sets the confidence of the detected drifts
+    pub fn delta(mut self, delta: f64) -> Self {
+        assert!(delta > 0. && delta < 1., "Delta must be between 0 and 1");
+        self.delta = delta;
+        self
+    }
+
+    /// Sets the minimum number of values in each part of the window
+    pub fn min_window(mut self, min_window: usize) -> Self {
+        assert!(min_window > 0, "The parts of the window can not be empty");
+        self.min_window = min_window;
+        self
+    }
+
+    /// Sets the maximum number of values in the window
+    pub fn max_window(mut self, max_window: usize) -> Self {
+        self.max_window = max_window;
+        self
+    }
+
+    /// Number of values in the current window
+    pub fn width(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Mean of the values in the current window
+    pub fn mean(&self) -> f64 {
+        self.sum / self.window.len().max(1) as f64
+    }
+
+    /// Returns the number of older values to drop if the window can be cut
+    fn find_cut(&self) -> Option<usize> {
+        let n = self.window.len();
+        if n < 2 * self.min_window {
+            return None;
+        }
+        let log_term = (4. * n as f64 / self.delta).ln();
+
+        let mut head_sum = 0.;
+        for (i, value) in self.window.iter().take(n - self.min_window).enumerate() {
+            head_sum += value;
+            let head_len = i + 1;
+            if head_len < self.min_window {
+                continue;
+            }
+            let n0 = head_len as f64;
+            let n1 = (n - head_len) as f64;
+            let harmonic_mean = 1. / (1. / n0 + 1. / n1);
+            let epsilon = (log_term / (2. * harmonic_mean)).sqrt();
+            if (head_sum / n0 - (self.sum - head_sum) / n1).abs() > epsilon {
+                return Some(head_len);
+            }
+        }
+        None
+    }
+
+    fn drop_oldest(&mut self, count: usize) {
+        for value in self.window.drain(..count) {
+            self.sum -= value;
+        }
+    }
+}
+
+impl Default for Adwin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DriftDetector for Adwin {
+    fn add(&mut self, value: f64) -> DriftState {
+        self.window.push_back(value);
+        self.sum += value;
+        if self.window.len() > self.max_window {
+            self.drop_oldest(1);
+        }
+
+        let mut state = DriftState::Stable;
+        while let Some(cut) = self.find_cut() {
+            self.drop_oldest(cut);
+            state = DriftState::Drift;
+        }
+        state
+    }
+}
+
+/// Extension trait for streams of error values
+pub trait DetectDrift<S: Scope> {
+    /// Sends the errors to the first worker and adds them to the detector in timestamp
+    /// order. For each timestamp in which a warning or a drift was detected, the most
+    /// severe state is emitted.
+    fn detect_drift<D: DriftDetector + 'static>(&self, detector: D) -> Stream<S, DriftState>;
+}
+
+impl<S: Scope, E: ExchangeData + ToPrimitive> DetectDrift<S> for Stream<S, E> {
+    fn detect_drift<D: DriftDetector + 'static>(&self, detector: D) -> Stream<S, DriftState> {
+        let mut detector = detector;
+        self.unary_frontier(Exchange::new(|_| 0u64), "DetectDrift", |_, _| {
+            let mut stash = FnvHashMap::default();
+
+            move |input, output| {
+                input.for_each(|time, data| {
+                    stash
+                        .entry(time.retain())
+                        .or_insert_with(Vec::new)
+                        .extend(data.drain(..));
+                });
+
+                // errors have to be observed in order, so wait for all earlier timestamps
+                let mut complete = stash
+                    .keys()
+                    .filter(|time| !input.frontier().less_equal(time.time()))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                complete.sort_by(|a, b| a.time().cmp(b.time()));
+                for time in complete {
+                    let errors = stash.remove(&time).expect("Errors of timestamp");
+                    let state = errors
+                        .into_iter()
+                        .map(|error| detector.add(error.to_f64().expect("Error value")))
+                        .max()
+                        .unwrap_or(DriftState::Stable);
+                    if state != DriftState::Stable {
+                        output.session(&time).give(state);
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+    use timely::progress::timestamp::RootTimestamp;
+
+    /// Misclassifications that occur for every tenth sample until `shift`, and for every
+    /// second sample afterwards
+    fn errors(shift: usize, len: usize) -> Vec<f64> {
+        (0..len)
+            .map(|i| {
+                let period = if i < shift { 10 } else { 2 };
+                if i % period == 0 {
+                    1.
+                } else {
+                    0.
+                }
+            })
+            .collect()
+    }
+
+    fn states<D: DriftDetector>(detector: &mut D, values: &[f64]) -> Vec<DriftState> {
+        values.iter().map(|&value| detector.add(value)).collect()
+    }
+
+    fn first(states: &[DriftState], state: DriftState) -> Option<usize> {
+        states.iter().position(|&s| s == state)
+    }
+
+    #[test]
+    fn ddm() {
+        let states = states(&mut Ddm::new(), &errors(500, 1000));
+        let warning = first(&states, DriftState::Warning).unwrap();
+        let drift = first(&states, DriftState::Drift).unwrap();
+        assert!(500 < warning && warning < drift && drift < 600);
+    }
+
+    #[test]
+    fn page_hinkley() {
+        let states = states(&mut PageHinkley::new(), &errors(500, 1000));
+        let drift = first(&states, DriftState::Drift).unwrap();
+        assert!(500 < drift && drift < 700);
+        assert_eq!(first(&states, DriftState::Warning), None);
+    }
+
+    #[test]
+    fn adwin() {
+        let mut adwin = Adwin::new();
+        let states = states(&mut adwin, &errors(500, 1000));
+        let drift = first(&states, DriftState::Drift).unwrap();
+        assert!(500 < drift && drift < 600);
+
+        // the old values were dropped from the window
+        assert!(adwin.width() <= 500);
+        assert!(adwin.mean() > 0.4);
+    }
+
+    #[test]
+    fn stable_stream() {
+        let values = errors(1000, 1000);
+        let drifts = states(&mut Ddm::new(), &values)
+            .into_iter()
+            .chain(states(&mut PageHinkley::new(), &values))
+            .chain(states(&mut Adwin::new(), &values))
+            .filter(|&state| state != DriftState::Stable)
+            .count();
+        assert_eq!(drifts, 0);
+    }
+
+    #[test]
+    fn detect_drift_in_stream() {
+        let result = ::timely::example(|scope| {
+            // ten values per timestamp, the shift happens in timestamp 50
+            errors(500, 1000)
+                .into_iter()
+                .enumerate()
+                .map(|(i, error)| ((i / 10) as u64, error))
+                .to_stream(scope)
+                .delay(|&(time, _), _| RootTimestamp::new(time))
+                .map(|(_, error)| error)
+                .detect_drift(Adwin::new())
+                .capture()
+        })
+        .extract();
+
+        let (first_time, first_states) = &result[0];
+        assert!(first_time.inner >= 50 && first_time.inner < 60);
+        assert_eq!(first_states, &vec![DriftState::Drift]);
+    }
+
+    /// Reports the state that corresponds to the value
+    struct Threshold;
+
+    impl DriftDetector for Threshold {
+        fn add(&mut self, value: f64) -> DriftState {
+            if value >= 1. {
+                DriftState::Drift
+            } else if value >= 0.5 {
+                DriftState::Warning
+            } else {
+                DriftState::Stable
+            }
+        }
+    }
+
+    #[test]
+    fn one_state_per_timestamp() {
+        let result = ::timely::example(|scope| {
+            // two batches of errors with the same timestamp
+            vec![1.]
+                .to_stream(scope)
+                .concat(&vec![0.5, 0.].to_stream(scope))
+                .detect_drift(Threshold)
+                .capture()
+        })
+        .extract();
+
+        assert_eq!(
+            result,
+            vec![(RootTimestamp::new(0), vec![DriftState::Drift])]
+        );
+    }
+}
@@ -0,0 +1,552 @@
+//! Error measures for class predictions.
+
+use super::{
+    weighted_sum, AggregateMeasure, ErrorMeasure, WeightedAggregateMeasure, WeightedErrorMeasure,
+};
+use ndarray::prelude::*;
+use num_traits::{cast::cast, Float, ToPrimitive};
+
+#[derive(Copy, Clone, Debug)]
+pub struct IncorrectRatio;
+
+impl<F: PartialEq> AggregateMeasure<F, f64> for IncorrectRatio {
+    /// Number of incorrect predictions and number of samples
+    type Statistics = (u64, u64);
+
+    fn statistics<D: Dimension>(
+        prediction: &ArrayView<F, D>,
+        original: &ArrayView<F, D>,
+    ) -> (u64, u64) {
+        assert_eq!(prediction.shape(), original.shape());
+        let incorrect = original
+            .iter()
+            .zip(prediction.iter())
+            .filter(|(expected, actual)| expected != actual)
+            .count();
+        (incorrect as u64, prediction.len() as u64)
+    }
+
+    fn merge(first: (u64, u64), second: (u64, u64)) -> (u64, u64) {
+        (first.0 + second.0, first.1 + second.1)
+    }
+
+    fn finish(&(incorrect, count): &(u64, u64)) -> f64 {
+        incorrect as f64 / count as f64
+    }
+}
+
+impl<F: PartialEq, D: Dimension> ErrorMeasure<F, f64, D> for IncorrectRatio {
+    fn error(prediction: &ArrayView<F, D>, original: &ArrayView<F, D>) -> f64 {
+        <Self as AggregateMeasure<F, f64>>::finish(&Self::statistics(prediction, original))
+    }
+}
+
+impl<F: PartialEq> WeightedAggregateMeasure<F, f64> for IncorrectRatio {
+    /// Total weight of the incorrect predictions and total weight of all samples
+    type WeightedStatistics = (f64, f64);
+
+    fn weighted_statistics(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> (f64, f64) {
+        assert_eq!(prediction.shape(), original.shape());
+        assert_eq!(weights.shape(), original.shape());
+        original
+            .iter()
+            .zip(prediction.iter())
+            .zip(weights.iter())
+            .fold((0., 0.), |(incorrect, total), ((expected, actual), &weight)| {
+                if expected != actual {
+                    (incorrect + weight, total + weight)
+                } else {
+                    (incorrect, total + weight)
+                }
+            })
+    }
+
+    fn merge_weighted(first: (f64, f64), second: (f64, f64)) -> (f64, f64) {
+        (first.0 + second.0, first.1 + second.1)
+    }
+
+    fn finish_weighted(&(incorrect, total): &(f64, f64)) -> f64 {
+        incorrect / total
+    }
+}
+
+impl<F: PartialEq> WeightedErrorMeasure<F, f64> for IncorrectRatio {
+    fn weighted_error(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> f64 {
+        let statistics = Self::weighted_statistics(prediction, original, weights);
+        <Self as WeightedAggregateMeasure<F, f64>>::finish_weighted(&statistics)
+    }
+}
+
+/// Binary cross-entropy. The predictions are the probabilities of the positive class,
+/// the original labels are `0` or `1`. Probabilities are clipped to `[1e-15, 1 - 1e-15]`
+/// to keep the error finite.
+#[derive(Copy, Clone, Debug)]
+pub struct LogLoss;
+
+impl<F: Float> AggregateMeasure<F, F> for LogLoss {
+    /// Sum of the losses and number of samples
+    type Statistics = (F, u64);
+
+    fn statistics<D: Dimension>(
+        prediction: &ArrayView<F, D>,
+        original: &ArrayView<F, D>,
+    ) -> (F, u64) {
+        assert_eq!(prediction.shape(), original.shape());
+        let sum = original
+            .iter()
+            .zip(prediction.iter())
+            .fold(F::zero(), |sum, (&expected, &actual)| {
+                sum + log_loss(expected, actual)
+            });
+        (sum, prediction.len() as u64)
+    }
+
+    fn merge(first: (F, u64), second: (F, u64)) -> (F, u64) {
+        (first.0 + second.0, first.1 + second.1)
+    }
+
+    fn finish(&(sum, count): &(F, u64)) -> F {
+        sum / cast::<_, F>(count).unwrap()
+    }
+}
+
+impl<F: Float, D: Dimension> ErrorMeasure<F, F, D> for LogLoss {
+    fn error(prediction: &ArrayView<F, D>, original: &ArrayView<F, D>) -> F {
+        Self::finish(&Self::statistics(prediction, original))
+    }
+}
+
+impl<F: Float> WeightedAggregateMeasure<F, F> for LogLoss {
+    /// Weighted sum of the losses and total weight of the samples
+    type WeightedStatistics = (F, F);
+
+    fn weighted_statistics(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> (F, F) {
+        weighted_sum(prediction, original, weights, log_loss)
+    }
+
+    fn merge_weighted(first: (F, F), second: (F, F)) -> (F, F) {
+        (first.0 + second.0, first.1 + second.1)
+    }
+
+    fn finish_weighted(&(sum, total_weight): &(F, F)) -> F {
+        sum / total_weight
+    }
+}
+
+impl<F: Float> WeightedErrorMeasure<F, F> for LogLoss {
+    fn weighted_error(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> F {
+        Self::finish_weighted(&Self::weighted_statistics(prediction, original, weights))
+    }
+}
+
+/// Cross-entropy of a single prediction
+fn log_loss<F: Float>(expected: F, actual: F) -> F {
+    let eps = cast::<_, F>(1e-15).unwrap();
+    let p = actual.max(eps).min(F::one() - eps);
+    -(expected * p.ln() + (F::one() - expected) * (F::one() - p).ln())
+}
+
+/// Number of samples for each combination of original and predicted class.
+/// Classes are the indices `0..classes()`; the matrix grows as new classes are added.
+#[derive(Abomonation, Clone, Debug, PartialEq, Eq, Default)]
+pub struct ConfusionMatrix {
+    classes: usize,
+    /// Row-major counts, rows are the original classes and columns the predicted classes
+    counts: Vec<u64>,
+}
+
+impl ConfusionMatrix {
+    pub fn new(classes: usize) -> Self {
+        ConfusionMatrix {
+            classes,
+            counts: vec![0; classes * classes],
+        }
+    }
+
+    /// Counts the pairs of predicted and original labels. Labels must be non-negative integers.
+    pub fn from_predictions<'a, 'b, F, I, J>(predictions: I, originals: J) -> Self
+    where
+        F: ToPrimitive + 'a + 'b,
+        I: IntoIterator<Item = &'a F>,
+        J: IntoIterator<Item = &'b F>,
+    {
+        let mut matrix = ConfusionMatrix::default();
+        for (predicted, original) in predictions.into_iter().zip(originals) {
+            matrix.add(
+                original
+                    .to_usize()
+                    .expect("Class label must be a valid index"),
+                predicted
+                    .to_usize()
+                    .expect("Class label must be a valid index"),
+            );
+        }
+        matrix
+    }
+
+    fn grow(&mut self, classes: usize) {
+        if classes > self.classes {
+            let mut grown = ConfusionMatrix::new(classes);
+            for original in 0..self.classes {
+                for predicted in 0..self.classes {
+                    grown.counts[original * classes + predicted] = self.count(original, predicted);
+                }
+            }
+            *self = grown;
+        }
+    }
+
+    /// Adds a single sample
+    pub fn add(&mut self, original: usize, predicted: usize) {
+        self.grow(original.max(predicted) + 1);
+        self.counts[original * self.classes + predicted] += 1;
+    }
+
+    /// Adds the counts of another matrix to this one
+    pub fn merge(&mut self, other: &ConfusionMatrix) {
+        self.grow(other.classes);
+        for original in 0..other.classes {
+            for predicted in 0..other.classes {
+                self.counts[original * self.classes + predicted] +=
+                    other.count(original, predicted);
+            }
+        }
+    }
+
+    pub fn classes(&self) -> usize {
+        self.classes
+    }
+
+    /// Number of samples of class `original` that were predicted as `predicted`
+    pub fn count(&self, original: usize, predicted: usize) -> u64 {
+        if original < self.classes && predicted < self.classes {
+            self.counts[original * self.classes + predicted]
+        } else {
+            0
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    fn true_positives(&self, class: usize) -> u64 {
+        self.count(class, class)
+    }
+
+    fn predicted_count(&self, class: usize) -> u64 {
+        (0..self.classes)
+            .map(|original| self.count(original, class))
+            .sum()
+    }
+
+    fn original_count(&self, class: usize) -> u64 {
+        (0..self.classes)
+            .map(|predicted| self.count(class, predicted))
+            .sum()
+    }
+
+    /// Ratio of correctly predicted samples
+    pub fn accuracy(&self) -> f64 {
+        let correct: u64 = (0..self.classes).map(|c| self.true_positives(c)).sum();
+        ratio(correct, self.total())
+    }
+
+    /// Ratio of samples predicted as `class` that actually belong to it
+    pub fn precision(&self, class: usize) -> f64 {
+        ratio(self.true_positives(class), self.predicted_count(class))
+    }
+
+    /// Ratio of samples of `class` that were predicted as such
+    pub fn recall(&self, class: usize) -> f64 {
+        ratio(self.true_positives(class), self.original_count(class))
+    }
+
+    /// Harmonic mean of precision and recall of `class`
+    pub fn f1(&self, class: usize) -> f64 {
+        f1(self.precision(class), self.recall(class))
+    }
+
+    /// Unweighted mean of the precision of all classes
+    pub fn macro_precision(&self) -> f64 {
+        self.class_mean(|c| self.precision(c))
+    }
+
+    /// Unweighted mean of the recall of all classes
+    pub fn macro_recall(&self) -> f64 {
+        self.class_mean(|c| self.recall(c))
+    }
+
+    /// Unweighted mean of the F1 score of all classes
+    pub fn macro_f1(&self) -> f64 {
+        self.class_mean(|c| self.f1(c))
+    }
+
+    /// Precision computed from the summed counts of all classes
+    pub fn micro_precision(&self) -> f64 {
+        let true_positives: u64 = (0..self.classes).map(|c| self.true_positives(c)).sum();
+        let predicted: u64 = (0..self.classes).map(|c| self.predicted_count(c)).sum();
+        ratio(true_positives, predicted)
+    }
+
+    /// Recall computed from the summed counts of all classes
+    pub fn micro_recall(&self) -> f64 {
+        let true_positives: u64 = (0..self.classes).map(|c| self.true_positives(c)).sum();
+        let original: u64 = (0..self.classes).map(|c| self.original_count(c)).sum();
+        ratio(true_positives, original)
+    }
+
+    /// F1 score computed from the micro-averaged precision and recall
+    pub fn micro_f1(&self) -> f64 {
+        f1(self.micro_precision(), self.micro_recall())
+    }
+
+    fn class_mean<M: Fn(usize) -> f64>(&self, measure: M) -> f64 {
+        if self.classes == 0 {
+            return 0.;
+        }
+        (0..self.classes).map(measure).sum::<f64>() / self.classes as f64
+    }
+}
+
+fn ratio(numerator: u64, denominator: u64) -> f64 {
+    if denominator == 0 {
+        0.
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+fn f1(precision: f64, recall: f64) -> f64 {
+    if precision + recall == 0. {
+        0.
+    } else {
+        2. * precision * recall / (precision + recall)
+    }
+}
+
+/// Computes the `ConfusionMatrix` of the predictions
+#[derive(Copy, Clone, Debug)]
+pub struct Confusion;
+
+impl<F: ToPrimitive> AggregateMeasure<F, ConfusionMatrix> for Confusion {
+    type Statistics = ConfusionMatrix;
+
+    fn statistics<D: Dimension>(
+        prediction: &ArrayView<F, D>,
+        original: &ArrayView<F, D>,
+    ) -> ConfusionMatrix {
+        assert_eq!(prediction.shape(), original.shape());
+        ConfusionMatrix::from_predictions(prediction.iter(), original.iter())
+    }
+
+    fn merge(mut first: ConfusionMatrix, second: ConfusionMatrix) -> ConfusionMatrix {
+        first.merge(&second);
+        first
+    }
+
+    fn finish(statistics: &ConfusionMatrix) -> ConfusionMatrix {
+        statistics.clone()
+    }
+}
+
+impl<F: ToPrimitive, D: Dimension> ErrorMeasure<F, ConfusionMatrix, D> for Confusion {
+    fn error(prediction: &ArrayView<F, D>, original: &ArrayView<F, D>) -> ConfusionMatrix {
+        let statistics = Self::statistics(prediction, original);
+        <Self as AggregateMeasure<F, ConfusionMatrix>>::finish(&statistics)
+    }
+}
+
+/// Defines measures that compute a value from the confusion matrix of the predictions.
+/// Unlike error measures, higher values are better for all of them.
+macro_rules! confusion_measures {
+    ($($(#[$attr:meta])* $name:ident -> $result:ty = $compute:expr;)*) => {
+        $(
+            $(#[$attr])*
+            #[derive(Copy, Clone, Debug)]
+            pub struct $name;
+
+            impl<F: ToPrimitive> AggregateMeasure<F, $result> for $name {
+                type Statistics = ConfusionMatrix;
+
+                fn statistics<D: Dimension>(
+                    prediction: &ArrayView<F, D>,
+                    original: &ArrayView<F, D>,
+                ) -> ConfusionMatrix {
+                    Confusion::statistics(prediction, original)
+                }
+
+                fn merge(first: ConfusionMatrix, second: ConfusionMatrix) -> ConfusionMatrix {
+                    <Confusion as AggregateMeasure<F, ConfusionMatrix>>::merge(first, second)
+                }
+
+                fn finish(statistics: &ConfusionMatrix) -> $result {
+                    let compute: fn(&ConfusionMatrix) -> $result = $compute;
+                    compute(statistics)
+                }
+            }
+
+            impl<F: ToPrimitive, D: Dimension> ErrorMeasure<F, $result, D> for $name {
+                fn error(prediction: &ArrayView<F, D>, original: &ArrayView<F, D>) -> $result {
+                    let statistics = Self::statistics(prediction, original);
+                    <Self as AggregateMeasure<F, $result>>::finish(&statistics)
+                }
+            }
+        )*
+    };
+}
+
+confusion_measures! {
+    /// Precision of each class
+    ClassPrecision -> Vec<f64> = |m| (0..m.classes()).map(|c| m.precision(c)).collect();
+    /// Recall of each class
+    ClassRecall -> Vec<f64> = |m| (0..m.classes()).map(|c| m.recall(c)).collect();
+    /// F1 score of each class
+    ClassF1 -> Vec<f64> = |m| (0..m.classes()).map(|c| m.f1(c)).collect();
+    /// Unweighted mean of the precision of all classes
+    MacroPrecision -> f64 = ConfusionMatrix::macro_precision;
+    /// Unweighted mean of the recall of all classes
+    MacroRecall -> f64 = ConfusionMatrix::macro_recall;
+    /// Unweighted mean of the F1 score of all classes
+    MacroF1 -> f64 = ConfusionMatrix::macro_f1;
+    /// Precision computed from the summed counts of all classes
+    MicroPrecision -> f64 = ConfusionMatrix::micro_precision;
+    /// Recall computed from the summed counts of all classes
+    MicroRecall -> f64 = ConfusionMatrix::micro_recall;
+    /// F1 score computed from the micro-averaged precision and recall
+    MicroF1 -> f64 = ConfusionMatrix::micro_f1;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn example() -> (Array1<usize>, Array1<usize>) {
+        (arr1(&[0, 2, 1, 0, 0, 1]), arr1(&[0, 1, 2, 0, 1, 2]))
+    }
+
+    #[test]
+    fn incorrect_ratio() {
+        let (prediction, original) = example();
+        let error = IncorrectRatio::error(&prediction.view(), &original.view());
+        assert!(abs_diff_eq!(error, &(4. / 6.), epsilon = 1e-10));
+    }
+
+    #[test]
+    fn weighted_incorrect_ratio() {
+        let (prediction, original) = example();
+        let weights = arr1(&[1., 1., 1., 3., 2., 0.]);
+        let error =
+            IncorrectRatio::weighted_error(&prediction.view(), &original.view(), &weights.view());
+        assert!(abs_diff_eq!(error, &(4. / 8.), epsilon = 1e-10));
+
+        let (first, second) = (s![..2], s![2..]);
+        let merged = <IncorrectRatio as WeightedAggregateMeasure<usize, f64>>::merge_weighted(
+            IncorrectRatio::weighted_statistics(
+                &prediction.slice(first),
+                &original.slice(first),
+                &weights.slice(first),
+            ),
+            IncorrectRatio::weighted_statistics(
+                &prediction.slice(second),
+                &original.slice(second),
+                &weights.slice(second),
+            ),
+        );
+        let error =
+            <IncorrectRatio as WeightedAggregateMeasure<usize, f64>>::finish_weighted(&merged);
+        assert!(abs_diff_eq!(error, &(4. / 8.), epsilon = 1e-10));
+    }
+
+    #[test]
+    fn log_loss() {
+        let original = arr1(&[0., 1., 1., 0.]);
+        let prediction = arr1(&[0.1, 0.9, 0.8, 0.35]);
+        let error = LogLoss::error(&prediction.view(), &original.view());
+        assert!(abs_diff_eq!(
+            error,
+            &0.216_161_874_680_579_1,
+            epsilon = 1e-10
+        ));
+
+        let error = LogLoss::error(&arr1(&[0.]).view(), &arr1(&[1.]).view());
+        assert!(error.is_finite());
+    }
+
+    #[test]
+    fn confusion_matrix() {
+        let (prediction, original) = example();
+        let matrix = Confusion::error(&prediction.view(), &original.view());
+
+        assert_eq!(matrix.classes(), 3);
+        assert_eq!(matrix.total(), 6);
+        assert_eq!(matrix.count(0, 0), 2);
+        assert_eq!(matrix.count(1, 0), 1);
+        assert_eq!(matrix.count(1, 2), 1);
+        assert_eq!(matrix.count(2, 1), 2);
+        assert_eq!(matrix.count(1, 1), 0);
+
+        let mut merged = ConfusionMatrix::new(1);
+        merged.add(0, 0);
+        merged.merge(&matrix);
+        assert_eq!(merged.classes(), 3);
+        assert_eq!(merged.count(0, 0), 3);
+        assert_eq!(merged.total(), 7);
+    }
+
+    #[test]
+    fn precision_recall_f1() {
+        let (prediction, original) = example();
+        let (prediction, original) = (prediction.view(), original.view());
+
+        assert_eq!(
+            ClassPrecision::error(&prediction, &original),
+            vec![2. / 3., 0., 0.]
+        );
+        assert_eq!(ClassRecall::error(&prediction, &original), vec![1., 0., 0.]);
+        let f1 = ClassF1::error(&prediction, &original);
+        assert!(abs_diff_eq!(f1[0], &0.8, epsilon = 1e-10));
+        assert_eq!(&f1[1..], &[0., 0.]);
+
+        assert!(abs_diff_eq!(
+            MacroPrecision::error(&prediction, &original),
+            &(2. / 9.),
+            epsilon = 1e-10
+        ));
+        assert!(abs_diff_eq!(
+            MacroRecall::error(&prediction, &original),
+            &(1. / 3.),
+            epsilon = 1e-10
+        ));
+        assert!(abs_diff_eq!(
+            MacroF1::error(&prediction, &original),
+            &(0.8 / 3.),
+            epsilon = 1e-10
+        ));
+
+        // every sample has exactly one class, so all micro averages equal the accuracy
+        for &micro in &[
+            MicroPrecision::error(&prediction, &original),
+            MicroRecall::error(&prediction, &original),
+            MicroF1::error(&prediction, &original),
+        ] {
+            assert!(abs_diff_eq!(micro, &(1. / 3.), epsilon = 1e-10));
+        }
+    }
+}
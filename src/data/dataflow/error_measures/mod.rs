@@ -0,0 +1,245 @@
+//! Measures for the quality of predictions, and an operator that applies them to streams.
+
+use data::dataflow::{CombineEachTime, ReduceEachTime};
+use data::serialization::AbomonableArray1;
+use data::serialization::AsView;
+use fnv::FnvHashMap;
+use ndarray::prelude::*;
+use num_traits::{cast::cast, Float};
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::{operators::{Map, Operator}, Scope, Stream};
+use timely::{Data, ExchangeData};
+
+mod classification;
+mod ranking;
+mod regression;
+
+pub use self::classification::{
+    ClassF1, ClassPrecision, ClassRecall, Confusion, ConfusionMatrix, IncorrectRatio, LogLoss,
+    MacroF1, MacroPrecision, MacroRecall, MicroF1, MicroPrecision, MicroRecall,
+};
+pub use self::ranking::{MeasureRanking, ScoreHistogram};
+pub use self::regression::{Mae, Mape, RSquared, RSquaredStatistics, Rmse};
+
+/// A measure that compares predictions to the original values
+pub trait ErrorMeasure<F, E, D: Dimension> {
+    fn error(prediction: &ArrayView<F, D>, original: &ArrayView<F, D>) -> E;
+}
+
+/// A measure that can be computed from statistics which are merged across chunks of predictions,
+/// e.g. sums and counts. This allows computing the exact measure for predictions that are
+/// spread across workers.
+pub trait AggregateMeasure<F, E> {
+    type Statistics;
+
+    /// Statistics of a single chunk of predictions
+    fn statistics<D: Dimension>(
+        prediction: &ArrayView<F, D>,
+        original: &ArrayView<F, D>,
+    ) -> Self::Statistics;
+
+    /// Combines the statistics of two chunks
+    fn merge(first: Self::Statistics, second: Self::Statistics) -> Self::Statistics;
+
+    /// Computes the measure from the statistics
+    fn finish(statistics: &Self::Statistics) -> E;
+}
+
+/// A measure that weights the error of each prediction, e.g. by the weights of the samples
+pub trait WeightedErrorMeasure<F, E> {
+    fn weighted_error(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> E;
+}
+
+/// A weighted measure that can be computed from statistics which are merged across chunks of
+/// predictions, e.g. weighted sums and the total weight. This allows computing the exact
+/// weighted measure for predictions that are spread across workers.
+pub trait WeightedAggregateMeasure<F, E> {
+    type WeightedStatistics;
+
+    /// Statistics of a single chunk of predictions and the weights of their samples
+    fn weighted_statistics(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> Self::WeightedStatistics;
+
+    /// Combines the statistics of two chunks
+    fn merge_weighted(
+        first: Self::WeightedStatistics,
+        second: Self::WeightedStatistics,
+    ) -> Self::WeightedStatistics;
+
+    /// Computes the measure from the statistics
+    fn finish_weighted(statistics: &Self::WeightedStatistics) -> E;
+}
+
+/// Weighted sum of a value computed from each prediction and original value, and the total
+/// weight
+fn weighted_sum<F: Float>(
+    prediction: &ArrayView1<F>,
+    original: &ArrayView1<F>,
+    weights: &ArrayView1<f64>,
+    value: impl Fn(F, F) -> F,
+) -> (F, F) {
+    assert_eq!(prediction.shape(), original.shape());
+    assert_eq!(weights.shape(), original.shape());
+    original
+        .iter()
+        .zip(prediction.iter())
+        .zip(weights.iter())
+        .fold(
+            (F::zero(), F::zero()),
+            |(sum, total_weight), ((&expected, &actual), &weight)| {
+                let weight = cast::<_, F>(weight).unwrap();
+                (
+                    sum + weight * value(expected, actual),
+                    total_weight + weight,
+                )
+            },
+        )
+}
+
+pub trait MeasurePredictionError<S: Scope, T, E> {
+    fn prediction_error<M: ErrorMeasure<T, E, Ix1>>(
+        &self,
+        original: &Stream<S, AbomonableArray1<T>>,
+        error_measure: M,
+    ) -> Stream<S, E>;
+
+    /// Computes the error of each chunk of predictions, weighting each prediction. The
+    /// original values are paired with the weights of their samples.
+    fn weighted_prediction_error<M: WeightedErrorMeasure<T, E>>(
+        &self,
+        original: &Stream<S, (AbomonableArray1<T>, AbomonableArray1<f64>)>,
+        error_measure: M,
+    ) -> Stream<S, E>;
+
+    /// Computes a single measure for all predictions of each timestamp on the first worker,
+    /// by merging the statistics of the individual chunks
+    fn aggregate_prediction_error<M: AggregateMeasure<T, E> + 'static>(
+        &self,
+        original: &Stream<S, AbomonableArray1<T>>,
+        error_measure: M,
+    ) -> Stream<S, E>
+    where
+        M::Statistics: ExchangeData;
+
+    /// Computes a single weighted measure for all predictions of each timestamp on the first
+    /// worker. The original values are paired with the weights of their samples.
+    fn weighted_aggregate_prediction_error<M: WeightedAggregateMeasure<T, E> + 'static>(
+        &self,
+        original: &Stream<S, (AbomonableArray1<T>, AbomonableArray1<f64>)>,
+        error_measure: M,
+    ) -> Stream<S, E>
+    where
+        M::WeightedStatistics: ExchangeData;
+}
+
+impl<S: Scope, T: Data, E: Data> MeasurePredictionError<S, T, E> for Stream<S, AbomonableArray1<T>> {
+    fn prediction_error<M: ErrorMeasure<T, E, Ix1>>(
+        &self,
+        original: &Stream<S, AbomonableArray1<T>>,
+        _error_measure: M,
+    ) -> Stream<S, E> {
+        zip_each_time(self, original, "PredictionError", |prediction, original| {
+            M::error(&prediction.view(), &original.view())
+        })
+    }
+
+    fn weighted_prediction_error<M: WeightedErrorMeasure<T, E>>(
+        &self,
+        original: &Stream<S, (AbomonableArray1<T>, AbomonableArray1<f64>)>,
+        _error_measure: M,
+    ) -> Stream<S, E> {
+        zip_each_time(
+            self,
+            original,
+            "WeightedPredictionError",
+            |prediction, (original, weights)| {
+                M::weighted_error(&prediction.view(), &original.view(), &weights.view())
+            },
+        )
+    }
+
+    fn aggregate_prediction_error<M: AggregateMeasure<T, E> + 'static>(
+        &self,
+        original: &Stream<S, AbomonableArray1<T>>,
+        _error_measure: M,
+    ) -> Stream<S, E>
+    where
+        M::Statistics: ExchangeData,
+    {
+        self.combine_each_time(original, |predictions, originals| {
+            predictions
+                .drain(..)
+                .zip(originals.drain(..))
+                .map(|(prediction, original)| M::statistics(&prediction.view(), &original.view()))
+                .collect()
+        }).reduce_each_time(M::merge)
+            .map(|statistics| M::finish(&statistics))
+    }
+
+    fn weighted_aggregate_prediction_error<M: WeightedAggregateMeasure<T, E> + 'static>(
+        &self,
+        original: &Stream<S, (AbomonableArray1<T>, AbomonableArray1<f64>)>,
+        _error_measure: M,
+    ) -> Stream<S, E>
+    where
+        M::WeightedStatistics: ExchangeData,
+    {
+        self.combine_each_time(original, |predictions, originals| {
+            predictions
+                .drain(..)
+                .zip(originals.drain(..))
+                .map(|(prediction, (original, weights))| {
+                    M::weighted_statistics(&prediction.view(), &original.view(), &weights.view())
+                })
+                .collect()
+        }).reduce_each_time(M::merge_weighted)
+            .map(|statistics| M::finish_weighted(&statistics))
+    }
+}
+
+/// Pairs up the items of both streams in the order they arrive on each worker and applies
+/// the closure to each pair, once all items of a timestamp were received
+fn zip_each_time<S: Scope, D1: Data, D2: Data, E: Data>(
+    first: &Stream<S, D1>,
+    second: &Stream<S, D2>,
+    name: &str,
+    apply: impl Fn(D1, D2) -> E + 'static,
+) -> Stream<S, E> {
+    first.binary_frontier(second, Pipeline, Pipeline, name, |_a, _b| {
+        let mut stash = FnvHashMap::default();
+        move |first_input, second_input, output| {
+            first_input.for_each(|time, data| {
+                let entry = stash
+                    .entry(time.retain())
+                    .or_insert_with(|| (vec![], vec![]));
+                entry.0.extend(data.drain(..));
+            });
+
+            second_input.for_each(|time, data| {
+                let entry = stash
+                    .entry(time.retain())
+                    .or_insert_with(|| (vec![], vec![]));
+                entry.1.extend(data.drain(..));
+            });
+
+            let frontiers = &[first_input.frontier(), second_input.frontier()];
+            for (time, (firsts, seconds)) in &mut stash {
+                if frontiers.iter().all(|f| !f.less_equal(time)) {
+                    let mut session = output.session(time);
+                    for (first, second) in firsts.drain(..).zip(seconds.drain(..)) {
+                        session.give(apply(first, second));
+                    }
+                }
+            }
+
+            stash.retain(|_, (firsts, seconds)| !firsts.is_empty() || !seconds.is_empty());
+        }
+    })
+}
@@ -0,0 +1,280 @@
+//! ROC and precision-recall curves for classifier scores.
+//!
+//! Scores are counted in a histogram with fixed bins, which can be built separately on each
+//! worker and merged, instead of sorting all scores on a single worker. Scores that fall into
+//! the same bin are treated as ties, so the results are exact if no bin contains scores of
+//! both classes and an approximation otherwise.
+
+use data::dataflow::ReduceEachTime;
+use fnv::FnvHashMap;
+use models::decision_tree::histogram_generics::HistogramSetItem;
+use num_traits::Float;
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::Operator;
+use timely::dataflow::{Scope, Stream};
+use timely::Data;
+
+/// Number of positive and negative samples per range of scores
+#[derive(Abomonation, Clone, Debug, PartialEq)]
+pub struct ScoreHistogram {
+    low: f64,
+    high: f64,
+    positives: Vec<u64>,
+    negatives: Vec<u64>,
+}
+
+impl ScoreHistogram {
+    /// Creates an empty histogram with `bins` bins of equal width between `low` and `high`.
+    /// Scores outside of this range are counted in the first or last bin.
+    pub fn new(bins: usize, low: f64, high: f64) -> Self {
+        assert!(bins > 0, "At least one bin is required");
+        assert!(low < high, "Lower bound must be less than upper bound");
+        ScoreHistogram {
+            low,
+            high,
+            positives: vec![0; bins],
+            negatives: vec![0; bins],
+        }
+    }
+
+    /// Creates an empty histogram for probabilities between 0 and 1
+    pub fn probabilities(bins: usize) -> Self {
+        ScoreHistogram::new(bins, 0., 1.)
+    }
+
+    fn bin(&self, score: f64) -> usize {
+        let bins = self.positives.len();
+        let position = (score - self.low) / (self.high - self.low) * bins as f64;
+        (position.max(0.) as usize).min(bins - 1)
+    }
+
+    /// Counts a sample with the given score and label
+    pub fn insert<F: Float>(&mut self, score: F, positive: bool) {
+        let bin = self.bin(score.to_f64().expect("Score must be representable as f64"));
+        if positive {
+            self.positives[bin] += 1;
+        } else {
+            self.negatives[bin] += 1;
+        }
+    }
+
+    /// Cumulative number of true and false positives when classifying all samples
+    /// with a score in or above each bin as positive, from the highest bin down.
+    fn cumulative_counts<'a>(&'a self) -> impl Iterator<Item = (u64, u64)> + 'a {
+        self.positives
+            .iter()
+            .zip(self.negatives.iter())
+            .rev()
+            .filter(|&(&p, &n)| p + n > 0)
+            .scan((0, 0), |counts, (&p, &n)| {
+                *counts = (counts.0 + p, counts.1 + n);
+                Some(*counts)
+            })
+    }
+
+    /// Points `(false positive rate, true positive rate)` of the ROC curve,
+    /// starting at `(0, 0)` and ending at `(1, 1)`
+    pub fn roc_curve(&self) -> Vec<(f64, f64)> {
+        let positives = self.positives.iter().sum::<u64>() as f64;
+        let negatives = self.negatives.iter().sum::<u64>() as f64;
+        let mut curve = vec![(0., 0.)];
+        curve.extend(
+            self.cumulative_counts()
+                .map(|(tp, fp)| (fp as f64 / negatives, tp as f64 / positives)),
+        );
+        curve
+    }
+
+    /// Area under the ROC curve. `None` if there are no positive or no negative samples.
+    pub fn roc_auc(&self) -> Option<f64> {
+        if self.positives.iter().all(|&p| p == 0) || self.negatives.iter().all(|&n| n == 0) {
+            return None;
+        }
+        let curve = self.roc_curve();
+        Some(
+            curve
+                .windows(2)
+                .map(|w| (w[1].0 - w[0].0) * (w[1].1 + w[0].1) / 2.)
+                .sum(),
+        )
+    }
+
+    /// Points `(recall, precision)` of the precision-recall curve, from the highest threshold
+    /// to the lowest
+    pub fn pr_curve(&self) -> Vec<(f64, f64)> {
+        let positives = self.positives.iter().sum::<u64>() as f64;
+        self.cumulative_counts()
+            .map(|(tp, fp)| (tp as f64 / positives, tp as f64 / (tp + fp) as f64))
+            .collect()
+    }
+
+    /// Area under the precision-recall curve, computed as the average precision
+    /// (the sum of the precision at each threshold, weighted by the increase in recall).
+    /// `None` if there are no positive samples.
+    pub fn pr_auc(&self) -> Option<f64> {
+        if self.positives.iter().all(|&p| p == 0) {
+            return None;
+        }
+        let mut previous_recall = 0.;
+        Some(
+            self.pr_curve()
+                .into_iter()
+                .map(|(recall, precision)| {
+                    let area = (recall - previous_recall) * precision;
+                    previous_recall = recall;
+                    area
+                })
+                .sum(),
+        )
+    }
+}
+
+impl HistogramSetItem for ScoreHistogram {
+    type Serializable = Self;
+
+    fn merge(&mut self, other: Self) {
+        self.merge_borrowed(&other);
+    }
+
+    fn merge_borrowed(&mut self, other: &Self) {
+        assert_eq!(self.low, other.low, "Histograms must have the same range");
+        assert_eq!(self.high, other.high, "Histograms must have the same range");
+        assert_eq!(
+            self.positives.len(),
+            other.positives.len(),
+            "Histograms must have the same number of bins"
+        );
+        for (count, other_count) in self.positives.iter_mut().zip(other.positives.iter()) {
+            *count += other_count;
+        }
+        for (count, other_count) in self.negatives.iter_mut().zip(other.negatives.iter()) {
+            *count += other_count;
+        }
+    }
+
+    fn empty_clone(&self) -> Self {
+        ScoreHistogram::new(self.positives.len(), self.low, self.high)
+    }
+}
+
+/// Extension trait for a stream of `(score, is_positive)` pairs
+pub trait MeasureRanking<S: Scope, F: Data> {
+    /// Counts the scores of each timestamp in a copy of the given empty histogram. The
+    /// histograms of all workers are merged and emitted on the first worker.
+    fn score_histogram(&self, histogram: ScoreHistogram) -> Stream<S, ScoreHistogram>;
+}
+
+impl<S: Scope, F: Float + Data> MeasureRanking<S, F> for Stream<S, (F, bool)> {
+    fn score_histogram(&self, histogram: ScoreHistogram) -> Stream<S, ScoreHistogram> {
+        let mut stash = FnvHashMap::default();
+        self.unary_notify(
+            Pipeline,
+            "ScoreHistogram",
+            vec![],
+            move |input, output, notificator| {
+                input.for_each(|time, data| {
+                    let time_histogram = stash
+                        .entry(time.time().clone())
+                        .or_insert_with(|| histogram.empty_clone());
+                    for (score, positive) in data.drain(..) {
+                        time_histogram.insert(score, positive);
+                    }
+                    notificator.notify_at(time.retain());
+                });
+
+                notificator.for_each(|time, _, _| {
+                    if let Some(time_histogram) = stash.remove(time.time()) {
+                        output.session(&time).give(time_histogram);
+                    }
+                });
+            },
+        )
+        .reduce_each_time(|mut first, second| {
+            first.merge(second);
+            first
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+    use timely::progress::timestamp::RootTimestamp;
+
+    fn example() -> Vec<(f64, bool)> {
+        vec![(0.1, false), (0.4, false), (0.35, true), (0.8, true)]
+    }
+
+    fn histogram(samples: &[(f64, bool)]) -> ScoreHistogram {
+        let mut histogram = ScoreHistogram::probabilities(100);
+        for &(score, positive) in samples {
+            histogram.insert(score, positive);
+        }
+        histogram
+    }
+
+    #[test]
+    fn roc() {
+        let histogram = histogram(&example());
+        assert_eq!(
+            histogram.roc_curve(),
+            vec![(0., 0.), (0., 0.5), (0.5, 0.5), (0.5, 1.), (1., 1.)]
+        );
+        assert!(abs_diff_eq!(
+            histogram.roc_auc().unwrap(),
+            &0.75,
+            epsilon = 1e-10
+        ));
+    }
+
+    #[test]
+    fn precision_recall() {
+        let scores = histogram(&example());
+        assert!(abs_diff_eq!(
+            scores.pr_auc().unwrap(),
+            &(0.5 + 0.5 * 2. / 3.),
+            epsilon = 1e-10
+        ));
+
+        let negatives_only = histogram(&[(0.3, false)]);
+        assert_eq!(negatives_only.pr_auc(), None);
+        assert_eq!(negatives_only.roc_auc(), None);
+    }
+
+    #[test]
+    fn ties_in_bins() {
+        // all samples in one bin are equivalent to a random classifier
+        let histogram = histogram(&[(0.501, true), (0.502, false)]);
+        assert!(abs_diff_eq!(
+            histogram.roc_auc().unwrap(),
+            &0.5,
+            epsilon = 1e-10
+        ));
+    }
+
+    #[test]
+    fn merge_streams() {
+        let result = ::timely::example(|scope| {
+            example()
+                .to_stream(scope)
+                .score_histogram(ScoreHistogram::probabilities(100))
+                .map(|histogram| (histogram.positives, histogram.negatives))
+                .capture()
+        })
+        .extract();
+
+        let mut merged = histogram(&example()[..2]);
+        merged.merge(histogram(&example()[2..]));
+
+        assert_eq!(
+            result,
+            vec![(
+                RootTimestamp::new(0),
+                vec![(merged.positives.clone(), merged.negatives.clone())]
+            )]
+        );
+        assert_eq!(merged, histogram(&example()));
+    }
+}
@@ -0,0 +1,372 @@
+//! Error measures for continuous predictions.
+
+use super::{
+    weighted_sum, AggregateMeasure, ErrorMeasure, WeightedAggregateMeasure, WeightedErrorMeasure,
+};
+use ndarray::prelude::*;
+use num_traits::{cast::cast, Float};
+use std::iter::Sum;
+
+/// Sum of the values and number of samples
+fn sum_and_count<F, D, M>(
+    prediction: &ArrayView<F, D>,
+    original: &ArrayView<F, D>,
+    value: M,
+) -> (F, u64)
+where
+    F: Float,
+    D: Dimension,
+    M: Fn(F, F) -> Option<F>,
+{
+    assert_eq!(prediction.shape(), original.shape());
+    original
+        .iter()
+        .zip(prediction.iter())
+        .filter_map(|(&expected, &actual)| value(expected, actual))
+        .fold((F::zero(), 0), |(sum, count), value| {
+            (sum + value, count + 1)
+        })
+}
+
+fn merge_sums<F: Float>(first: (F, u64), second: (F, u64)) -> (F, u64) {
+    (first.0 + second.0, first.1 + second.1)
+}
+
+fn mean<F: Float>(&(sum, count): &(F, u64)) -> F {
+    sum / cast::<_, F>(count).unwrap()
+}
+
+fn merge_weighted_sums<F: Float>(first: (F, F), second: (F, F)) -> (F, F) {
+    (first.0 + second.0, first.1 + second.1)
+}
+
+fn weighted_mean<F: Float>(&(sum, total_weight): &(F, F)) -> F {
+    sum / total_weight
+}
+
+/// Root mean squared error
+#[derive(Copy, Clone, Debug)]
+pub struct Rmse;
+
+impl<F: Float> AggregateMeasure<F, F> for Rmse {
+    type Statistics = (F, u64);
+
+    fn statistics<D: Dimension>(
+        prediction: &ArrayView<F, D>,
+        original: &ArrayView<F, D>,
+    ) -> (F, u64) {
+        sum_and_count(prediction, original, |expected, actual| {
+            Some((expected - actual) * (expected - actual))
+        })
+    }
+
+    fn merge(first: (F, u64), second: (F, u64)) -> (F, u64) {
+        merge_sums(first, second)
+    }
+
+    fn finish(statistics: &(F, u64)) -> F {
+        mean(statistics).sqrt()
+    }
+}
+
+impl<F: Float, D: Dimension> ErrorMeasure<F, F, D> for Rmse {
+    fn error(prediction: &ArrayView<F, D>, original: &ArrayView<F, D>) -> F {
+        Self::finish(&Self::statistics(prediction, original))
+    }
+}
+
+impl<F: Float> WeightedAggregateMeasure<F, F> for Rmse {
+    type WeightedStatistics = (F, F);
+
+    fn weighted_statistics(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> (F, F) {
+        weighted_sum(prediction, original, weights, |expected, actual| {
+            (expected - actual) * (expected - actual)
+        })
+    }
+
+    fn merge_weighted(first: (F, F), second: (F, F)) -> (F, F) {
+        merge_weighted_sums(first, second)
+    }
+
+    fn finish_weighted(statistics: &(F, F)) -> F {
+        weighted_mean(statistics).sqrt()
+    }
+}
+
+impl<F: Float> WeightedErrorMeasure<F, F> for Rmse {
+    fn weighted_error(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> F {
+        Self::finish_weighted(&Self::weighted_statistics(prediction, original, weights))
+    }
+}
+
+/// Mean absolute error
+#[derive(Copy, Clone, Debug)]
+pub struct Mae;
+
+impl<F: Float> AggregateMeasure<F, F> for Mae {
+    type Statistics = (F, u64);
+
+    fn statistics<D: Dimension>(
+        prediction: &ArrayView<F, D>,
+        original: &ArrayView<F, D>,
+    ) -> (F, u64) {
+        sum_and_count(prediction, original, |expected, actual| {
+            Some((expected - actual).abs())
+        })
+    }
+
+    fn merge(first: (F, u64), second: (F, u64)) -> (F, u64) {
+        merge_sums(first, second)
+    }
+
+    fn finish(statistics: &(F, u64)) -> F {
+        mean(statistics)
+    }
+}
+
+impl<F: Float, D: Dimension> ErrorMeasure<F, F, D> for Mae {
+    fn error(prediction: &ArrayView<F, D>, original: &ArrayView<F, D>) -> F {
+        Self::finish(&Self::statistics(prediction, original))
+    }
+}
+
+impl<F: Float> WeightedAggregateMeasure<F, F> for Mae {
+    type WeightedStatistics = (F, F);
+
+    fn weighted_statistics(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> (F, F) {
+        weighted_sum(prediction, original, weights, |expected, actual| {
+            (expected - actual).abs()
+        })
+    }
+
+    fn merge_weighted(first: (F, F), second: (F, F)) -> (F, F) {
+        merge_weighted_sums(first, second)
+    }
+
+    fn finish_weighted(statistics: &(F, F)) -> F {
+        weighted_mean(statistics)
+    }
+}
+
+impl<F: Float> WeightedErrorMeasure<F, F> for Mae {
+    fn weighted_error(
+        prediction: &ArrayView1<F>,
+        original: &ArrayView1<F>,
+        weights: &ArrayView1<f64>,
+    ) -> F {
+        Self::finish_weighted(&Self::weighted_statistics(prediction, original, weights))
+    }
+}
+
+/// Mean absolute percentage error, as a fraction (`0.1` is 10%). Samples whose
+/// original value is zero are skipped, since their percentage error is undefined.
+#[derive(Copy, Clone, Debug)]
+pub struct Mape;
+
+impl<F: Float> AggregateMeasure<F, F> for Mape {
+    type Statistics = (F, u64);
+
+    fn statistics<D: Dimension>(
+        prediction: &ArrayView<F, D>,
+        original: &ArrayView<F, D>,
+    ) -> (F, u64) {
+        sum_and_count(prediction, original, |expected, actual| {
+            if expected.is_zero() {
+                None
+            } else {
+                Some(((expected - actual) / expected).abs())
+            }
+        })
+    }
+
+    fn merge(first: (F, u64), second: (F, u64)) -> (F, u64) {
+        merge_sums(first, second)
+    }
+
+    fn finish(statistics: &(F, u64)) -> F {
+        mean(statistics)
+    }
+}
+
+impl<F: Float, D: Dimension> ErrorMeasure<F, F, D> for Mape {
+    fn error(prediction: &ArrayView<F, D>, original: &ArrayView<F, D>) -> F {
+        Self::finish(&Self::statistics(prediction, original))
+    }
+}
+
+/// Coefficient of determination. Unlike the other measures, higher values are better:
+/// `1` is a perfect fit and `0` is as good as always predicting the mean.
+#[derive(Copy, Clone, Debug)]
+pub struct RSquared;
+
+/// Sums from which the coefficient of determination is computed
+#[derive(Abomonation, Copy, Clone, Debug, PartialEq)]
+pub struct RSquaredStatistics<F> {
+    count: u64,
+    sum: F,
+    sum_of_squares: F,
+    residual_sum_of_squares: F,
+}
+
+impl<F: Float + Sum> AggregateMeasure<F, F> for RSquared {
+    type Statistics = RSquaredStatistics<F>;
+
+    fn statistics<D: Dimension>(
+        prediction: &ArrayView<F, D>,
+        original: &ArrayView<F, D>,
+    ) -> RSquaredStatistics<F> {
+        assert_eq!(prediction.shape(), original.shape());
+        RSquaredStatistics {
+            count: original.len() as u64,
+            sum: original.iter().cloned().sum(),
+            sum_of_squares: original.iter().map(|&expected| expected * expected).sum(),
+            residual_sum_of_squares: original
+                .iter()
+                .zip(prediction.iter())
+                .map(|(&expected, &actual)| (expected - actual) * (expected - actual))
+                .sum(),
+        }
+    }
+
+    fn merge(first: RSquaredStatistics<F>, second: RSquaredStatistics<F>) -> RSquaredStatistics<F> {
+        RSquaredStatistics {
+            count: first.count + second.count,
+            sum: first.sum + second.sum,
+            sum_of_squares: first.sum_of_squares + second.sum_of_squares,
+            residual_sum_of_squares: first.residual_sum_of_squares + second.residual_sum_of_squares,
+        }
+    }
+
+    fn finish(statistics: &RSquaredStatistics<F>) -> F {
+        let count = cast::<_, F>(statistics.count).unwrap();
+        let total_sum_of_squares =
+            statistics.sum_of_squares - statistics.sum * statistics.sum / count;
+        F::one() - statistics.residual_sum_of_squares / total_sum_of_squares
+    }
+}
+
+impl<F: Float + Sum, D: Dimension> ErrorMeasure<F, F, D> for RSquared {
+    fn error(prediction: &ArrayView<F, D>, original: &ArrayView<F, D>) -> F {
+        Self::finish(&Self::statistics(prediction, original))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn example() -> (Array1<f64>, Array1<f64>) {
+        (arr1(&[2.5, 0.0, 2.0, 8.0]), arr1(&[3.0, -0.5, 2.0, 7.0]))
+    }
+
+    #[test]
+    fn rmse() {
+        let (prediction, original) = example();
+        let error = Rmse::error(&prediction.view(), &original.view());
+        assert!(abs_diff_eq!(error, &0.375_f64.sqrt(), epsilon = 1e-10));
+    }
+
+    #[test]
+    fn mae() {
+        let (prediction, original) = example();
+        let error = Mae::error(&prediction.view(), &original.view());
+        assert!(abs_diff_eq!(error, &0.5, epsilon = 1e-10));
+    }
+
+    #[test]
+    fn weighted_mae() {
+        let (prediction, original) = example();
+        let weights = arr1(&[1., 0., 2., 1.]);
+        let error = Mae::weighted_error(&prediction.view(), &original.view(), &weights.view());
+        assert!(abs_diff_eq!(error, &0.375, epsilon = 1e-10));
+    }
+
+    #[test]
+    fn mape() {
+        let (prediction, original) = example();
+        let error = Mape::error(&prediction.view(), &original.view());
+        let expected = (0.5 / 3.0 + 0.5 / 0.5 + 0.0 + 1.0 / 7.0) / 4.0;
+        assert!(abs_diff_eq!(error, &expected, epsilon = 1e-10));
+
+        let original = arr1(&[0.0, 2.0]);
+        let prediction = arr1(&[1.0, 1.0]);
+        let error = Mape::error(&prediction.view(), &original.view());
+        assert!(abs_diff_eq!(error, &0.5, epsilon = 1e-10));
+    }
+
+    #[test]
+    fn r_squared() {
+        let (prediction, original) = example();
+        let error = RSquared::error(&prediction.view(), &original.view());
+        assert!(abs_diff_eq!(error, &0.948_608_137_044_968, epsilon = 1e-10));
+    }
+
+    #[test]
+    fn merge_statistics() {
+        let (prediction, original) = example();
+        let (first, second) = (s![..1], s![1..]);
+
+        let merged = <RSquared as AggregateMeasure<f64, f64>>::merge(
+            RSquared::statistics(&prediction.slice(first), &original.slice(first)),
+            RSquared::statistics(&prediction.slice(second), &original.slice(second)),
+        );
+        let error: f64 = RSquared::finish(&merged);
+        assert!(abs_diff_eq!(error, &0.948_608_137_044_968, epsilon = 1e-10));
+
+        let merged = <Rmse as AggregateMeasure<f64, f64>>::merge(
+            Rmse::statistics(&prediction.slice(first), &original.slice(first)),
+            Rmse::statistics(&prediction.slice(second), &original.slice(second)),
+        );
+        let error: f64 = Rmse::finish(&merged);
+        assert!(abs_diff_eq!(error, &0.375_f64.sqrt(), epsilon = 1e-10));
+    }
+
+    #[test]
+    fn merge_weighted_statistics() {
+        let (prediction, original) = example();
+        let weights = arr1(&[1., 0., 2., 1.]);
+        let (first, second) = (s![..2], s![2..]);
+
+        let merged = <Mae as WeightedAggregateMeasure<f64, f64>>::merge_weighted(
+            Mae::weighted_statistics(
+                &prediction.slice(first),
+                &original.slice(first),
+                &weights.slice(first),
+            ),
+            Mae::weighted_statistics(
+                &prediction.slice(second),
+                &original.slice(second),
+                &weights.slice(second),
+            ),
+        );
+        let error: f64 = Mae::finish_weighted(&merged);
+        assert!(abs_diff_eq!(error, &0.375, epsilon = 1e-10));
+
+        let merged = <Rmse as WeightedAggregateMeasure<f64, f64>>::merge_weighted(
+            Rmse::weighted_statistics(
+                &prediction.slice(first),
+                &original.slice(first),
+                &weights.slice(first),
+            ),
+            Rmse::weighted_statistics(
+                &prediction.slice(second),
+                &original.slice(second),
+                &weights.slice(second),
+            ),
+        );
+        let error: f64 = Rmse::finish_weighted(&merged);
+        assert!(abs_diff_eq!(error, &0.3125_f64.sqrt(), epsilon = 1e-10));
+    }
+}
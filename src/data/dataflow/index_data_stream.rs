@@ -1,31 +1,49 @@
 use data::providers::IntSliceIndex;
 use data::serialization::AbomonableArray2;
 use data::serialization::AsView;
+use data::TrainingData;
 use ndarray::prelude::*;
 use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::{operators::Operator, Scope, Stream};
 use timely::Data;
 
-pub trait IndexDataStream<S: Scope, T: Data> {
-    fn index_data(&self) -> Stream<S, (IntSliceIndex<usize>, AbomonableArray2<T>)>;
+/// Chunks of data that consist of a number of samples
+pub trait SampleCount {
+    fn sample_count(&self) -> usize;
 }
 
-impl<S, T> IndexDataStream<S, T> for Stream<S, AbomonableArray2<T>>
+impl<T> SampleCount for AbomonableArray2<T> {
+    fn sample_count(&self) -> usize {
+        self.view().len_of(Axis(0))
+    }
+}
+
+impl<T, L> SampleCount for TrainingData<T, L> {
+    fn sample_count(&self) -> usize {
+        self.x().rows()
+    }
+}
+
+pub trait IndexDataStream<S: Scope, D: Data> {
+    fn index_data(&self) -> Stream<S, (IntSliceIndex<usize>, D)>;
+}
+
+impl<S, D> IndexDataStream<S, D> for Stream<S, D>
 where
     S: Scope,
-    T: Data,
+    D: Data + SampleCount,
 {
-    fn index_data(&self) -> Stream<S, (IntSliceIndex<usize>, AbomonableArray2<T>)> {
+    fn index_data(&self) -> Stream<S, (IntSliceIndex<usize>, D)> {
         self.unary(Pipeline, "IndexData", |_, _| {
             let mut count = 0;
             move |input, output| {
                 input.for_each(|time, data| {
                     let mut session = output.session(&time);
-                    for array in data.drain(..) {
+                    for chunk in data.drain(..) {
                         let start = count;
-                        let length = array.view().len_of(Axis(0));
+                        let length = chunk.sample_count();
                         count += length;
-                        session.give((IntSliceIndex { start, length }, array));
+                        session.give((IntSliceIndex { start, length }, chunk));
                     }
                 });
             }
@@ -13,19 +13,27 @@ use timely::Data;
 
 mod apply_latest;
 mod combine_each_time;
+pub mod drift;
 pub mod error_measures;
 mod exchange_evenly;
 mod index_data_stream;
 mod init_each_time;
+mod reduce_each_time;
 pub mod timer;
 pub mod random;
+pub mod split;
+pub mod window;
 
 pub use self::apply_latest::ApplyLatest;
 pub use self::combine_each_time::CombineEachTime;
+pub use self::drift::DetectDrift;
 pub use self::exchange_evenly::ExchangeEvenly;
-pub use self::index_data_stream::IndexDataStream;
+pub use self::index_data_stream::{IndexDataStream, SampleCount};
 pub use self::init_each_time::InitEachTime;
+pub use self::reduce_each_time::ReduceEachTime;
+pub use self::split::SplitTrainingData;
 pub use self::timer::Timer;
+pub use self::window::{Window, WindowEpochs};
 
 /// A container for result data coming in asynchronously from somewhere. Internally uses the `std::sync::mpsc`
 /// channels for receiving the data.
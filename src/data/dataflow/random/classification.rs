@@ -12,6 +12,7 @@ pub struct RandClassificationTrainingSource<T1, T2, D1, D2> {
     chunks_per_round: usize,
     x_dist: Array2<D1>,
     y_dist: Array1<D2>,
+    seed: u64,
     phantom: PhantomData<(T1, T2)>,
 }
 
@@ -23,6 +24,7 @@ impl<T, L, D1: Clone, D2> Default for RandClassificationTrainingSource<T, L, D1,
             chunks_per_round: 1,
             x_dist: Array2::from_shape_vec((0, 0), vec![]).unwrap(),
             y_dist: Array1::from_shape_vec(0, vec![]).unwrap(),
+            seed: 0,
             phantom: PhantomData,
         }
     }
@@ -57,6 +59,13 @@ where
         }
     }
 
+    /// Configure the seed from which the random number generators of each worker
+    /// and round are derived
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
     /// Create a stream that produces random data
     /// using the configuration in this instance
     pub fn to_stream<'s, S: Scope>(
@@ -69,6 +78,8 @@ where
         let y_dist = self.y_dist.map(|to_dist| to_dist.to_distribution());
         let chunks_per_round = self.chunks_per_round;
         let samples_per_chunk = self.samples_per_chunk;
+        let seed = self.seed;
+        let worker = scope.index();
 
         //let params = self.clone();
         source(scope, "RandomSource", move |capability| {
@@ -79,6 +90,7 @@ where
                     {
                         let mut session = output.session(&cap);
                         let cluster_distribution = Uniform::new(0, x_dist.rows());
+                        let mut rng = seeded_rng(seed, &(worker, cap.time()));
 
                         for _ in 0..chunks_per_round {
                             let mut x = unsafe {
@@ -87,8 +99,7 @@ where
                             let mut y = unsafe { Array1::uninitialized(samples_per_chunk) };
 
                             for (mut x_row, y) in x.outer_iter_mut().zip(y.iter_mut()) {
-                                let mut rng = thread_rng();
-                                let cluster = thread_rng().sample(cluster_distribution);
+                                let cluster = rng.sample(cluster_distribution);
                                 for (x, dist) in x_row.iter_mut().zip(x_dist.row(cluster).iter()) {
                                     *x = rng.sample(dist);
                                 }
@@ -98,6 +109,7 @@ where
                             session.give(TrainingData {
                                 x: x.into(),
                                 y: y.into(),
+                                weights: None,
                             });
                         }
                     }
@@ -13,9 +13,11 @@ use timely::Data;
 
 mod classification;
 mod regression;
+mod seed;
 
 pub use self::classification::RandClassificationTrainingSource;
 pub use self::regression::RandRegressionTrainingSource;
+pub use self::seed::{seeded_rng, SeededRng};
 
 /// Configuration for random distributions
 pub mod params {
@@ -173,4 +175,31 @@ mod test {
         }).extract();
         assert_eq!(result.len(), 3);
     }
+
+    #[test]
+    fn random_source_seeded() {
+        let generate = |seed| {
+            let source = RandClassificationTrainingSource::<f64, usize, _, _>::default()
+                .samples(50, 1, 1)
+                .seed(seed)
+                .x_distributions(arr2(&[
+                    [NormalParams::new(0., 1.), NormalParams::new(0., 1.)],
+                    [NormalParams::new(5., 1.), NormalParams::new(5., 1.)],
+                ]))
+                .y_distributions(arr1(&[DummyDistribution(0), DummyDistribution(1)]));
+
+            ::timely::example(move |scope| {
+                source
+                    .to_stream(Summary::Local(1), RootTimestamp::new(1), scope)
+                    .map(|data: TrainingData<f64, usize>| {
+                        data.x().iter().map(|x| x.to_bits()).collect::<Vec<_>>()
+                    })
+                    .capture()
+            }).extract()
+        };
+
+        let first = generate(42);
+        assert_eq!(first, generate(42));
+        assert_ne!(first, generate(43));
+    }
 }
@@ -14,6 +14,7 @@ pub struct RandRegressionTrainingSource<T1, T2, L, Dt, F> {
     chunks_per_round: usize,
     x_base_dist: Array1<Dt>,
     x_mapper: F,
+    seed: u64,
     phantom: PhantomData<(T1, T2, L)>,
 }
 
@@ -28,6 +29,7 @@ where
             chunks_per_round: 1,
             x_base_dist: Array1::from_shape_vec(0, vec![]).unwrap(),
             x_mapper,
+            seed: 0,
             phantom: PhantomData,
         }
     }
@@ -56,6 +58,13 @@ where
         }
     }
 
+    /// Configure the seed from which the random number generators of each worker
+    /// and round are derived
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
     /// Create a stream that produces random data
     /// using the configuration in this instance
     pub fn to_stream<S: Scope>(
@@ -67,6 +76,8 @@ where
         let base_dist = self.x_base_dist.map(|to_dist| to_dist.to_distribution());
         let chunks_per_round = self.chunks_per_round;
         let samples_per_chunk = self.samples_per_chunk;
+        let seed = self.seed;
+        let worker = scope.index();
 
         source(scope, "RandomSource", move |capability| {
             let mut cap = Some(capability);
@@ -77,6 +88,7 @@ where
                 if let Some(cap) = cap.as_mut() {
                     {
                         let mut session = output.session(&cap);
+                        let mut rng = seeded_rng(seed, &(worker, cap.time()));
 
                         for _ in 0..chunks_per_round {
                             // 2D Array to hold the final input values
@@ -90,8 +102,6 @@ where
                             let mut x_row_temp = unsafe { Array1::uninitialized(base_dist.len()) };
 
                             for (mut x_row, y) in x.outer_iter_mut().zip(y.iter_mut()) {
-                                let mut rng = thread_rng();
-
                                 for (x, dist) in x_row_temp.iter_mut().zip(base_dist.iter()) {
                                     *x = rng.sample(dist);
                                 }
@@ -103,6 +113,7 @@ where
                             session.give(TrainingData {
                                 x: x.into(),
                                 y: y.into(),
+                                weights: None,
                             });
                         }
                     }
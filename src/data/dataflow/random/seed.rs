@@ -0,0 +1,28 @@
+use fnv::FnvHasher;
+use rand::prng::Hc128Rng;
+use rand::SeedableRng;
+use std::hash::{Hash, Hasher};
+
+/// The random number generator used by all seeded models and sources.
+/// A fixed algorithm is used instead of `StdRng`, so results stay reproducible
+/// across versions of the `rand` crate.
+pub type SeededRng = Hc128Rng;
+
+/// Deterministically derives a random number generator from a base seed and some context,
+/// usually the worker index and the current timestamp. The same seed and context
+/// always result in the same sequence of random numbers.
+pub fn seeded_rng<C: Hash>(seed: u64, context: &C) -> SeededRng {
+    let mut rng_seed = [0u8; 32];
+    for (round, chunk) in rng_seed.chunks_mut(8).enumerate() {
+        let mut hasher = FnvHasher::default();
+        seed.hash(&mut hasher);
+        round.hash(&mut hasher);
+        context.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        for (i, byte) in chunk.iter_mut().enumerate() {
+            *byte = (hash >> (8 * i)) as u8;
+        }
+    }
+    SeededRng::from_seed(rng_seed)
+}
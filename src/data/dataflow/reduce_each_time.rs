@@ -0,0 +1,71 @@
+use fnv::FnvHashMap;
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::{operators::Operator, Scope, Stream};
+use timely::ExchangeData;
+
+/// Extension trait for `Stream`.
+pub trait ReduceEachTime<S: Scope, D: ExchangeData> {
+    /// Sends all items to the first worker and reduces the items of each timestamp
+    /// into a single item using the closure passed to the operator. The result is
+    /// emitted once the timestamp is complete.
+    fn reduce_each_time(&self, reduce: impl Fn(D, D) -> D + 'static) -> Stream<S, D>;
+}
+
+impl<S: Scope, D: ExchangeData> ReduceEachTime<S, D> for Stream<S, D> {
+    fn reduce_each_time(&self, reduce: impl Fn(D, D) -> D + 'static) -> Stream<S, D> {
+        let mut stash = FnvHashMap::default();
+        self.unary_notify(
+            Exchange::new(|_| 0u64),
+            "ReduceEachTime",
+            vec![],
+            move |input, output, notificator| {
+                input.for_each(|time, data| {
+                    let entry = stash.entry(time.time().clone()).or_insert(None);
+                    for item in data.drain(..) {
+                        *entry = Some(match entry.take() {
+                            Some(reduced) => reduce(reduced, item),
+                            None => item,
+                        });
+                    }
+                    notificator.notify_at(time.retain());
+                });
+
+                notificator.for_each(|time, _, _| {
+                    if let Some(Some(reduced)) = stash.remove(time.time()) {
+                        output.session(&time).give(reduced);
+                    }
+                });
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use timely::dataflow::operators::{Inspect, ToStream};
+    use timely_communication::initialize::Configuration;
+
+    #[test]
+    fn reduce_on_first_worker() {
+        let reduced = Arc::new(Mutex::new(Vec::new()));
+        let reduced_by_workers = reduced.clone();
+
+        ::timely::execute(Configuration::Process(2), move |root| {
+            let worker = root.index();
+            let reduced = reduced_by_workers.clone();
+            root.dataflow::<u64, _, _>(|scope| {
+                (0..10)
+                    .map(move |i| i * 2 + worker)
+                    .to_stream(scope)
+                    .reduce_each_time(|a, b| a + b)
+                    .inspect(move |sum| reduced.lock().unwrap().push((worker, *sum)));
+            });
+        })
+        .expect("Execute dataflow");
+
+        let reduced = reduced.lock().unwrap().clone();
+        assert_eq!(reduced, vec![(0, (0..20).sum())]);
+    }
+}
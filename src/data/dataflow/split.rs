@@ -0,0 +1,268 @@
+//! Operators that split a stream of training data into disjoint parts, e.g. for holding out
+//! test data or for cross-validation.
+//!
+//! Each sample is assigned to a part using a hash of the seed and the global index of the
+//! sample, i.e. its position in the data of all workers for a timestamp, ordered by worker
+//! index and then by arrival. The same seed and input therefore always result in the same
+//! split, and data that is partitioned into contiguous ranges across workers, like the chunks
+//! loaded from a data source, is split the same way for any number of workers.
+
+use data::TrainingData;
+use fnv::{FnvHashMap, FnvHasher};
+use ndarray::prelude::*;
+use std::hash::{Hash, Hasher};
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Broadcast, Capability, Map, Operator, Partition};
+use timely::dataflow::{Scope, Stream};
+use timely::Data;
+
+/// Hashes the global index of a sample into a pseudo-random number
+fn sample_hash(seed: u64, sample_index: usize) -> u64 {
+    let mut hasher = FnvHasher::default();
+    (seed, sample_index).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Extension trait for a stream of `TrainingData<T, L>`
+pub trait SplitTrainingData<S: Scope, T: Data, L: Data> {
+    /// Splits the samples into a training stream (first) and a test stream (second).
+    /// Approximately `test_ratio` of the samples end up in the test stream.
+    fn train_test_split(
+        &self,
+        test_ratio: f64,
+        seed: u64,
+    ) -> (Stream<S, TrainingData<T, L>>, Stream<S, TrainingData<T, L>>);
+
+    /// Assigns each sample to one of `folds` folds of approximately equal size. Emits the
+    /// samples of each incoming chunk grouped by fold, together with the fold number.
+    fn assign_folds(&self, folds: usize, seed: u64) -> Stream<S, (usize, TrainingData<T, L>)>;
+}
+
+impl<S: Scope, T: Data + Copy, L: Data + Copy> SplitTrainingData<S, T, L>
+    for Stream<S, TrainingData<T, L>>
+{
+    fn train_test_split(
+        &self,
+        test_ratio: f64,
+        seed: u64,
+    ) -> (Stream<S, TrainingData<T, L>>, Stream<S, TrainingData<T, L>>) {
+        let threshold = (test_ratio.max(0.).min(1.) * u64::max_value() as f64) as u64;
+        let mut parts = self
+            .split_samples(2, seed, move |hash| if hash < threshold { 1 } else { 0 })
+            .partition(2, |(part, data)| (part as u64, data));
+
+        let test = parts.pop().unwrap();
+        let train = parts.pop().unwrap();
+        (train, test)
+    }
+
+    fn assign_folds(&self, folds: usize, seed: u64) -> Stream<S, (usize, TrainingData<T, L>)> {
+        assert!(folds > 0, "At least one fold is required");
+        self.split_samples(folds, seed, move |hash| (hash % folds as u64) as usize)
+    }
+}
+
+trait SplitSamples<S: Scope, T: Data, L: Data> {
+    /// Assigns each sample to one of `parts` parts, based on its hash
+    fn split_samples<F: Fn(u64) -> usize + 'static>(
+        &self,
+        parts: usize,
+        seed: u64,
+        assign: F,
+    ) -> Stream<S, (usize, TrainingData<T, L>)>;
+}
+
+impl<S: Scope, T: Data + Copy, L: Data + Copy> SplitSamples<S, T, L>
+    for Stream<S, TrainingData<T, L>>
+{
+    fn split_samples<F: Fn(u64) -> usize + 'static>(
+        &self,
+        parts: usize,
+        seed: u64,
+        assign: F,
+    ) -> Stream<S, (usize, TrainingData<T, L>)> {
+        let worker = self.scope().index();
+        // every worker needs the number of samples on the workers before it to find the
+        // global index of its first sample
+        let counts = self
+            .map(move |chunk| (worker, chunk.x().rows()))
+            .broadcast();
+
+        self.binary_frontier(&counts, Pipeline, Pipeline, "SplitSamples", |_, _| {
+            let mut stash: FnvHashMap<Capability<_>, Vec<TrainingData<T, L>>> =
+                FnvHashMap::default();
+            // number of samples on workers with a lower index, for each time
+            let mut offsets = FnvHashMap::default();
+
+            move |data_input, count_input, output| {
+                data_input.for_each(|time, data| {
+                    stash
+                        .entry(time.retain())
+                        .or_insert_with(Vec::new)
+                        .extend(data.drain(..));
+                });
+                count_input.for_each(|time, data| {
+                    let offset = offsets.entry(time.time().clone()).or_insert(0);
+                    for (other, count) in data.drain(..) {
+                        if other < worker {
+                            *offset += count;
+                        }
+                    }
+                });
+
+                // the offset of a time is known once all counts of the time have arrived
+                let frontiers = [data_input.frontier(), count_input.frontier()];
+                for (cap, chunks) in &mut stash {
+                    if frontiers.iter().any(|f| f.less_equal(cap.time())) {
+                        continue;
+                    }
+
+                    let mut sample_index = offsets.remove(cap.time()).unwrap_or(0);
+                    let mut session = output.session(cap);
+                    for chunk in chunks.drain(..) {
+                        let mut part_indices = vec![Vec::new(); parts];
+                        for row in 0..chunk.x().rows() {
+                            let part = assign(sample_hash(seed, sample_index + row));
+                            part_indices[part].push(row);
+                        }
+                        sample_index += chunk.x().rows();
+
+                        for (part, indices) in part_indices.into_iter().enumerate() {
+                            if !indices.is_empty() {
+                                session.give((
+                                    part,
+                                    TrainingData {
+                                        x: chunk.x().select(Axis(0), &indices).into(),
+                                        y: chunk.y().select(Axis(0), &indices).into(),
+                                        weights: chunk
+                                            .weights()
+                                            .map(|w| w.select(Axis(0), &indices).into()),
+                                    },
+                                ));
+                            }
+                        }
+                    }
+                }
+                stash.retain(|_, chunks| !chunks.is_empty());
+                offsets.retain(|time, _| frontiers.iter().any(|f| f.less_equal(time)));
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+    use timely_communication::initialize::Configuration;
+
+    fn test_data() -> Vec<TrainingData<usize, usize>> {
+        (0..4)
+            .map(|chunk| TrainingData {
+                x: Array2::from_shape_fn((50, 1), |(row, _)| chunk * 50 + row).into(),
+                y: Array1::from_shape_fn(50, |row| chunk * 50 + row).into(),
+                weights: None,
+            })
+            .collect()
+    }
+
+    fn sample_ids(data: &TrainingData<usize, usize>) -> Vec<usize> {
+        data.y().to_vec()
+    }
+
+    #[test]
+    fn train_test_split() {
+        let split = || {
+            ::timely::example(|scope| {
+                let (train, test) = test_data().to_stream(scope).train_test_split(0.2, 7);
+                train
+                    .map(|data| (0, sample_ids(&data)))
+                    .concat(&test.map(|data| (1, sample_ids(&data))))
+                    .capture()
+            }).extract()
+        };
+
+        let result = split();
+        assert_eq!(result, split());
+
+        let mut train = Vec::new();
+        let mut test = Vec::new();
+        for (part, ids) in result.into_iter().flat_map(|(_, data)| data) {
+            if part == 0 {
+                train.extend(ids);
+            } else {
+                test.extend(ids);
+            }
+        }
+
+        assert_eq!(train.len() + test.len(), 200);
+        assert!(test.len() > 20 && test.len() < 60);
+        assert!(test.iter().all(|id| !train.contains(id)));
+    }
+
+    #[test]
+    fn assign_folds() {
+        let result = ::timely::example(|scope| {
+            test_data()
+                .to_stream(scope)
+                .assign_folds(5, 3)
+                .map(|(fold, data)| (fold, sample_ids(&data)))
+                .capture()
+        }).extract();
+
+        let mut fold_sizes = vec![0; 5];
+        let mut ids = Vec::new();
+        for (fold, fold_ids) in result.into_iter().flat_map(|(_, data)| data) {
+            fold_sizes[fold] += fold_ids.len();
+            ids.extend(fold_ids);
+        }
+        ids.sort();
+
+        assert_eq!(ids, (0..200).collect::<Vec<_>>());
+        assert!(fold_sizes.iter().all(|&size| size > 20 && size < 60));
+    }
+
+    #[test]
+    fn same_folds_for_any_number_of_workers() {
+        let single_worker = ::timely::example(|scope| {
+            test_data()
+                .to_stream(scope)
+                .assign_folds(5, 3)
+                .map(|(fold, data)| (fold, sample_ids(&data)))
+                .capture()
+        }).extract();
+        let mut expected = single_worker
+            .into_iter()
+            .flat_map(|(_, data)| data)
+            .flat_map(|(fold, ids)| ids.into_iter().map(move |id| (fold, id)))
+            .collect::<Vec<_>>();
+        expected.sort();
+
+        // each worker holds a contiguous range of the chunks
+        let assigned = Arc::new(Mutex::new(Vec::new()));
+        let assigned_by_workers = assigned.clone();
+        ::timely::execute(Configuration::Process(2), move |root| {
+            let worker = root.index();
+            let assigned = assigned_by_workers.clone();
+            root.dataflow::<u64, _, _>(|scope| {
+                test_data()
+                    .into_iter()
+                    .skip(worker * 2)
+                    .take(2)
+                    .to_stream(scope)
+                    .assign_folds(5, 3)
+                    .inspect(move |(fold, data)| {
+                        let mut assigned = assigned.lock().unwrap();
+                        assigned.extend(sample_ids(data).into_iter().map(|id| (*fold, id)));
+                    });
+            });
+        })
+        .expect("Execute dataflow");
+
+        let mut assigned = assigned.lock().unwrap().clone();
+        assigned.sort();
+        assert_eq!(assigned, expected);
+    }
+}
@@ -0,0 +1,189 @@
+//! Windows over the epochs of a dataflow, to train models on recent history.
+//!
+//! The models are trained separately for each timestamp of their training data. By
+//! re-emitting the data of several epochs at a timestamp, a model is periodically
+//! retrained on the data of that window, e.g. `data.sliding_window(Window::Timestamps(5))
+//! .train(&model)`. Data that falls out of the window is dropped once all earlier
+//! timestamps are complete. Note that tree models additionally limit the number of
+//! samples per worker they use for each timestamp.
+
+use fnv::FnvHashMap;
+use std::collections::{BTreeMap, VecDeque};
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::*;
+use timely::dataflow::{Scope, Stream};
+use timely::progress::nested::product::Product;
+use timely::progress::timestamp::RootTimestamp;
+use timely::Data;
+
+/// Extent of a sliding window, ending at the current epoch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Window {
+    /// The last `n` epochs that contained data
+    Timestamps(usize),
+    /// The epochs that are less than `duration` before the current one
+    Duration(u64),
+}
+
+/// Extension trait for streams in a dataflow with `u64` epochs
+pub trait WindowEpochs<S: Scope, D: Data> {
+    /// For each epoch that contains data on any worker, emits the data of all epochs
+    /// in the window ending at that epoch
+    fn sliding_window(&self, window: Window) -> Stream<S, D>;
+
+    /// Collects the data of non-overlapping windows of `size` epochs, starting at epoch 0,
+    /// and emits it at the last epoch of each window
+    fn tumbling_window(&self, size: u64) -> Stream<S, D>;
+}
+
+impl<S, D> WindowEpochs<S, D> for Stream<S, D>
+where
+    S: Scope<Timestamp = Product<RootTimestamp, u64>>,
+    D: Data,
+{
+    fn sliding_window(&self, window: Window) -> Stream<S, D> {
+        match window {
+            Window::Timestamps(n) => assert!(n > 0, "The window must contain an epoch"),
+            Window::Duration(d) => assert!(d > 0, "The window must contain an epoch"),
+        }
+
+        // every worker emits its windowed data for each epoch with any data
+        let triggers = self.map(|_| ()).broadcast();
+        self.binary_frontier(&triggers, Pipeline, Pipeline, "SlidingWindow", |_, _| {
+            let mut stash: BTreeMap<u64, Vec<D>> = BTreeMap::new();
+            let mut pending: Vec<Capability<Product<RootTimestamp, u64>>> = Vec::new();
+            let mut recent_epochs = VecDeque::new();
+
+            move |data_input, trigger_input, output| {
+                data_input.for_each(|time, data| {
+                    stash
+                        .entry(time.time().inner)
+                        .or_insert_with(Vec::new)
+                        .extend(data.drain(..));
+                });
+                trigger_input.for_each(|time, _| {
+                    if !pending.iter().any(|cap| cap.time() == time.time()) {
+                        pending.push(time.retain());
+                    }
+                });
+
+                // windows have to be emitted in order to evict old data
+                pending.sort_by(|a, b| a.time().cmp(b.time()));
+                let frontiers = [data_input.frontier(), trigger_input.frontier()];
+                while !pending.is_empty()
+                    && frontiers.iter().all(|f| !f.less_equal(pending[0].time()))
+                {
+                    let cap = pending.remove(0);
+                    let epoch = cap.time().inner;
+                    let start = match window {
+                        Window::Timestamps(n) => {
+                            recent_epochs.push_back(epoch);
+                            if recent_epochs.len() > n {
+                                recent_epochs.pop_front();
+                            }
+                            recent_epochs[0]
+                        }
+                        Window::Duration(duration) => (epoch + 1).saturating_sub(duration),
+                    };
+
+                    stash = stash.split_off(&start);
+                    let mut session = output.session(&cap);
+                    for (_, data) in stash.range(..=epoch) {
+                        session.give_iterator(data.iter().cloned());
+                    }
+                }
+            }
+        })
+    }
+
+    fn tumbling_window(&self, size: u64) -> Stream<S, D> {
+        assert!(size > 0, "The window must contain an epoch");
+
+        self.unary_frontier(Pipeline, "TumblingWindow", |_, _| {
+            let mut stash: FnvHashMap<Capability<_>, Vec<D>> = FnvHashMap::default();
+
+            move |input, output| {
+                input.for_each(|time, data| {
+                    let end = RootTimestamp::new(time.time().inner / size * size + size - 1);
+                    stash
+                        .entry(time.delayed(&end))
+                        .or_insert_with(Vec::new)
+                        .extend(data.drain(..));
+                });
+
+                for (cap, data) in &mut stash {
+                    if !input.frontier().less_equal(cap.time()) {
+                        output.session(cap).give_iterator(data.drain(..));
+                    }
+                }
+                stash.retain(|_, data| !data.is_empty());
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc::Receiver;
+    use timely::dataflow::operators::capture::{Event, Extract};
+
+    /// Stream with one item at each of the epochs
+    fn epochs<G>(scope: &mut G, epochs: Vec<u64>) -> Stream<G, u64>
+    where
+        G: Scope<Timestamp = Product<RootTimestamp, u64>>,
+    {
+        epochs
+            .to_stream(scope)
+            .delay(|&epoch, _| RootTimestamp::new(epoch))
+    }
+
+    fn extract_epochs(
+        captured: Receiver<Event<Product<RootTimestamp, u64>, u64>>,
+    ) -> Vec<(u64, Vec<u64>)> {
+        captured
+            .extract()
+            .into_iter()
+            .map(|(time, data)| (time.inner, data))
+            .collect()
+    }
+
+    #[test]
+    fn sliding_timestamps() {
+        let result = ::timely::example(|scope| {
+            epochs(scope, vec![0, 1, 1, 4])
+                .sliding_window(Window::Timestamps(2))
+                .capture()
+        });
+        assert_eq!(
+            extract_epochs(result),
+            vec![(0, vec![0]), (1, vec![0, 1, 1]), (4, vec![1, 1, 4])]
+        );
+    }
+
+    #[test]
+    fn sliding_duration() {
+        let result = ::timely::example(|scope| {
+            epochs(scope, vec![0, 1, 1, 4])
+                .sliding_window(Window::Duration(2))
+                .capture()
+        });
+        assert_eq!(
+            extract_epochs(result),
+            vec![(0, vec![0]), (1, vec![0, 1, 1]), (4, vec![4])]
+        );
+    }
+
+    #[test]
+    fn tumbling() {
+        let result = ::timely::example(|scope| {
+            epochs(scope, vec![0, 1, 1, 4, 7])
+                .tumbling_window(3)
+                .capture()
+        });
+        assert_eq!(
+            extract_epochs(result),
+            vec![(2, vec![0, 1, 1]), (5, vec![4]), (8, vec![7])]
+        );
+    }
+}
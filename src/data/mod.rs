@@ -1,4 +1,5 @@
 pub mod dataflow;
+pub mod preprocessing;
 pub mod providers;
 pub mod quantize;
 pub mod serialization;
@@ -8,12 +9,14 @@ use ndarray::prelude::*;
 use serde::ser::{Serialize, SerializeSeq, SerializeTuple, Serializer};
 
 /// Data structure to hold training data for supervised models.
-/// Holds a Matrix with input data and an Array with the
-/// associated outputs
+/// Holds a Matrix with input data, an Array with the
+/// associated outputs and optionally a weight for each sample
 #[derive(Clone, Abomonation, Debug)]
 pub struct TrainingData<T, L> {
     pub x: AbomonableArray2<T>,
     pub y: AbomonableArray1<L>,
+    /// Weight of each sample, all samples have a weight of 1 if not set
+    pub weights: Option<AbomonableArray1<f64>>,
 }
 
 impl<T, L> TrainingData<T, L> {
@@ -29,11 +32,36 @@ impl<T, L> TrainingData<T, L> {
     pub fn y_mut<'a, 'b: 'a>(&'b mut self) -> ArrayViewMut1<'a, L> {
         self.y.view_mut()
     }
+    pub fn weights<'a, 'b: 'a>(&'b self) -> Option<ArrayView1<'a, f64>> {
+        self.weights.as_ref().map(|weights| weights.view())
+    }
+
+    /// Weight of the sample in the given row
+    pub fn weight(&self, row: usize) -> f64 {
+        self.weights.as_ref().map_or(1., |weights| weights.view()[row])
+    }
+
+    /// Sets the weight of each sample. Weights must be finite and not negative, samples
+    /// with a weight of 0 are ignored in training.
+    pub fn with_weights(mut self, weights: Array1<f64>) -> Self {
+        assert_eq!(
+            weights.len(),
+            self.x.view().rows(),
+            "There must be one weight for each sample"
+        );
+        assert!(
+            weights.iter().all(|w| w.is_finite() && *w >= 0.),
+            "Weights must be finite and not negative"
+        );
+        self.weights = Some(weights.into());
+        self
+    }
 }
 
 pub struct TrainingDataSample<'a, T: 'a, L: 'a> {
     x: ArrayView1<'a, T>,
     y: &'a L,
+    weight: Option<f64>,
 }
 
 impl<'a, T: 'a, L: 'a> From<(ArrayView1<'a, T>, &'a L)> for TrainingDataSample<'a, T, L> {
@@ -41,6 +69,7 @@ impl<'a, T: 'a, L: 'a> From<(ArrayView1<'a, T>, &'a L)> for TrainingDataSample<'
         TrainingDataSample {
             x: from.0,
             y: from.1,
+            weight: None,
         }
     }
 }
@@ -54,15 +83,21 @@ where
     where
         S: Serializer,
     {
-        let mut tuple = serializer.serialize_tuple(self.x.len() + 1)?;
+        let len = self.x.len() + 1 + self.weight.map_or(0, |_| 1);
+        let mut tuple = serializer.serialize_tuple(len)?;
         for x_el in self.x {
             tuple.serialize_element(x_el)?;
         }
         tuple.serialize_element(self.y)?;
+        if let Some(ref weight) = self.weight {
+            tuple.serialize_element(weight)?;
+        }
         tuple.end()
     }
 }
 
+/// Serializes each sample as a tuple of its features and its label,
+/// followed by the weight of the sample if the data is weighted
 impl<T, L> Serialize for TrainingData<T, L>
 where
     T: Serialize,
@@ -75,10 +110,55 @@ where
         let x = self.x();
         let y = self.y();
         let mut seq = serializer.serialize_seq(Some(x.rows()))?;
-        for (row, y) in x.outer_iter().zip(y.iter()) {
-            let sample = TrainingDataSample { x: row, y };
+        for (i, (row, y)) in x.outer_iter().zip(y.iter()).enumerate() {
+            let weight = self.weights.as_ref().map(|_| self.weight(i));
+            let sample = TrainingDataSample { x: row, y, weight };
             seq.serialize_element(&sample)?;
         }
         seq.end()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn data() -> TrainingData<f64, i64> {
+        TrainingData {
+            x: arr2(&[[1.], [2.]]).into(),
+            y: arr1(&[0, 1]).into(),
+            weights: None,
+        }
+    }
+
+    #[test]
+    fn weights() {
+        let data = data().with_weights(arr1(&[0.5, 0.]));
+        assert_eq!(data.weight(0), 0.5);
+        assert_eq!(data.weight(1), 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn negative_weights() {
+        data().with_weights(arr1(&[1., -1.]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn infinite_weights() {
+        data().with_weights(arr1(&[1., ::std::f64::INFINITY]));
+    }
+
+    #[test]
+    fn serialize_weights() {
+        assert_eq!(
+            ::serde_json::to_string(&data()).unwrap(),
+            "[[1.0,0],[2.0,1]]"
+        );
+        assert_eq!(
+            ::serde_json::to_string(&data().with_weights(arr1(&[0.5, 0.]))).unwrap(),
+            "[[1.0,0,0.5],[2.0,1,0.0]]"
+        );
+    }
+}
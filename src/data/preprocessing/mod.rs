@@ -0,0 +1,108 @@
+//! Transformations of the features of samples, e.g. to bring them to the same scale.
+//!
+//! Transformations are fitted in a single pass over a stream of data. Each worker computes
+//! statistics over its data, which are merged on the first worker and turned into
+//! parameters that are broadcast to all workers. The fitted parameters can then be applied
+//! to other streams, e.g. to the data that is used for predictions.
+
+use data::dataflow::{ApplyLatest, ReduceEachTime};
+use data::serialization::*;
+use data::TrainingData;
+use ndarray::prelude::*;
+use timely::dataflow::operators::{Broadcast, Map};
+use timely::dataflow::{Scope, Stream};
+use timely::{Data, ExchangeData};
+
+pub mod pca;
+pub mod scalers;
+
+pub use self::pca::{Pca, PcaParams, ProjectComponents};
+pub use self::scalers::{MinMaxScaler, RobustScaler, ScalingParameters, StandardScaler};
+
+/// Items that contain a matrix of features, with one row per sample
+pub trait Features<T> {
+    fn features<'a, 'b: 'a>(&'b self) -> ArrayView2<'a, T>;
+    fn features_mut<'a, 'b: 'a>(&'b mut self) -> ArrayViewMut2<'a, T>;
+}
+
+impl<T> Features<T> for AbomonableArray2<T> {
+    fn features<'a, 'b: 'a>(&'b self) -> ArrayView2<'a, T> {
+        self.view()
+    }
+
+    fn features_mut<'a, 'b: 'a>(&'b mut self) -> ArrayViewMut2<'a, T> {
+        self.view_mut()
+    }
+}
+
+impl<T, L> Features<T> for TrainingData<T, L> {
+    fn features<'a, 'b: 'a>(&'b self) -> ArrayView2<'a, T> {
+        self.x()
+    }
+
+    fn features_mut<'a, 'b: 'a>(&'b mut self) -> ArrayViewMut2<'a, T> {
+        self.x_mut()
+    }
+}
+
+/// A transformation of features that is fitted from statistics of the data
+pub trait Transformer<T>: Data {
+    /// Statistics over some samples, from which the transformation is fitted
+    type Statistics: ExchangeData;
+    /// The fitted transformation, usually implementing `Transform`
+    type Fitted: ExchangeData;
+
+    /// Computes the statistics of a matrix of samples
+    fn statistics(&self, x: ArrayView2<T>) -> Self::Statistics;
+
+    /// Combines the statistics of two sets of samples
+    fn merge(&self, first: Self::Statistics, second: Self::Statistics) -> Self::Statistics;
+
+    /// Fits the transformation from the statistics of all samples
+    fn fit(&self, statistics: &Self::Statistics) -> Self::Fitted;
+}
+
+/// A fitted transformation of features
+pub trait Transform<T> {
+    /// Transforms the samples in place
+    fn transform(&self, x: ArrayViewMut2<T>);
+}
+
+/// A fitted transformation that maps features to new values, possibly of another type
+pub trait MapFeatures {
+    type Input;
+    type Output;
+
+    /// Transforms a copy of the samples
+    fn map_features(&self, x: ArrayView2<Self::Input>) -> Array2<Self::Output>;
+}
+
+/// Extension trait for streams of items that contain features
+pub trait TransformFeatures<S: Scope, T, D: Data> {
+    /// Fits the transformer to all items of each timestamp. The fitted transformation
+    /// is emitted on every worker.
+    fn fit_transformer<Tr: Transformer<T>>(&self, transformer: &Tr) -> Stream<S, Tr::Fitted>;
+
+    /// Applies the latest fitted transformation to the features of all items
+    fn transform<F: Transform<T> + Data>(&self, fitted: &Stream<S, F>) -> Stream<S, D>;
+}
+
+impl<S: Scope, T, D: Data + Features<T>> TransformFeatures<S, T, D> for Stream<S, D> {
+    fn fit_transformer<Tr: Transformer<T>>(&self, transformer: &Tr) -> Stream<S, Tr::Fitted> {
+        let statistics_transformer = transformer.clone();
+        let merge_transformer = transformer.clone();
+        let fit_transformer = transformer.clone();
+
+        self.map(move |data| statistics_transformer.statistics(data.features()))
+            .reduce_each_time(move |first, second| merge_transformer.merge(first, second))
+            .map(move |statistics| fit_transformer.fit(&statistics))
+            .broadcast()
+    }
+
+    fn transform<F: Transform<T> + Data>(&self, fitted: &Stream<S, F>) -> Stream<S, D> {
+        fitted.apply_latest(self, |_time, fitted, mut data| {
+            fitted.transform(data.features_mut());
+            data
+        })
+    }
+}
@@ -0,0 +1,324 @@
+//! Principal component analysis, which projects samples onto the directions of largest
+//! variance.
+//!
+//! Each worker calculates the mean and the centered co-moments of its samples, which are
+//! merged without subtracting large sums from each other. From the merged statistics, the
+//! first worker calculates the covariance matrix and its eigendecomposition. The number of
+//! features should therefore be moderate, but the number of samples is not limited.
+
+use super::{MapFeatures, Transformer};
+use data::dataflow::ApplyLatest;
+use data::serialization::*;
+use models::decision_tree::histogram_generics::ContinuousValue;
+use ndarray::prelude::*;
+use ndarray_linalg::{Eigh, Scalar, UPLO};
+use num_traits::cast::cast;
+use num_traits::Float;
+use timely::dataflow::{Scope, Stream};
+use timely::Data;
+
+/// Transformer that projects the samples onto their first principal components
+#[derive(Abomonation, Clone, Copy, Debug)]
+pub struct Pca {
+    components: usize,
+}
+
+impl Pca {
+    /// Creates a transformer that keeps the given number of components. If there are
+    /// less features than components, all features are kept.
+    pub fn new(components: usize) -> Self {
+        assert!(components > 0, "At least one component must be kept");
+        Pca { components }
+    }
+}
+
+/// Number of samples, mean of the features and sum of the outer products of the
+/// differences of the samples from the mean
+#[derive(Abomonation, Clone, Debug)]
+pub struct CovarianceStatistics<T> {
+    count: u64,
+    mean: AbomonableArray1<T>,
+    comoments: AbomonableArray2<T>,
+}
+
+/// Fitted principal components
+#[derive(Abomonation, Clone, Debug, Serialize, Deserialize)]
+pub struct PcaParams<T> {
+    /// Mean of each feature, which is subtracted before the projection
+    pub mean: AbomonableArray1<T>,
+    /// Principal components (k x d), ordered by decreasing variance
+    pub components: AbomonableArray2<T>,
+    /// Variance of the samples along each component
+    pub explained_variance: AbomonableArray1<T>,
+    /// Sum of the variances of all features
+    pub total_variance: T,
+}
+
+impl<T: Scalar<Real = T> + ContinuousValue> PcaParams<T> {
+    /// Portion of the total variance of the samples along each component
+    pub fn explained_variance_ratio(&self) -> Array1<T> {
+        if self.total_variance > T::zero() {
+            self.explained_variance
+                .view()
+                .mapv(|v| v / self.total_variance)
+        } else {
+            Array1::zeros(self.explained_variance.view().len())
+        }
+    }
+}
+
+impl<T: Scalar<Real = T> + ContinuousValue> Transformer<T> for Pca {
+    type Statistics = CovarianceStatistics<T>;
+    type Fitted = PcaParams<T>;
+
+    fn statistics(&self, x: ArrayView2<T>) -> CovarianceStatistics<T> {
+        let count = x.rows() as u64;
+        if count == 0 {
+            return CovarianceStatistics {
+                count,
+                mean: Array1::zeros(x.cols()).into(),
+                comoments: Array2::zeros((x.cols(), x.cols())).into(),
+            };
+        }
+
+        let n: T = cast(count).unwrap();
+        let mean = x.sum_axis(Axis(0)).mapv(|s| s / n);
+        let centered = &x - &mean;
+        CovarianceStatistics {
+            count,
+            comoments: centered.t().dot(&centered).into(),
+            mean: mean.into(),
+        }
+    }
+
+    fn merge(
+        &self,
+        first: CovarianceStatistics<T>,
+        second: CovarianceStatistics<T>,
+    ) -> CovarianceStatistics<T> {
+        if first.count == 0 {
+            return second;
+        }
+        if second.count == 0 {
+            return first;
+        }
+
+        // combines the statistics of both parts (Chan et al.)
+        let count = first.count + second.count;
+        let (n1, n2, n): (T, T, T) = (
+            cast(first.count).unwrap(),
+            cast(second.count).unwrap(),
+            cast(count).unwrap(),
+        );
+        let (m1, m2) = (first.mean.view(), second.mean.view());
+        let delta = &m2 - &m1;
+        let mean = &m1 + &delta.mapv(|d| d * n2 / n);
+        let (c1, c2) = (first.comoments.view(), second.comoments.view());
+        let comoments = Array2::from_shape_fn(c1.dim(), |(i, j)| {
+            c1[(i, j)] + c2[(i, j)] + delta[i] * delta[j] * n1 * n2 / n
+        });
+        CovarianceStatistics {
+            count,
+            mean: mean.into(),
+            comoments: comoments.into(),
+        }
+    }
+
+    fn fit(&self, statistics: &CovarianceStatistics<T>) -> PcaParams<T> {
+        let degrees_of_freedom: T = cast(statistics.count.max(2) - 1).unwrap();
+        let mean = statistics.mean.view().to_owned();
+        let d = mean.len();
+
+        let covariance = statistics.comoments.view().mapv(|c| c / degrees_of_freedom);
+        let total_variance = covariance
+            .diag()
+            .iter()
+            .fold(T::zero(), |total, &v| total + Float::max(v, T::zero()));
+
+        // the eigenvalues are in ascending order
+        let (variances, vectors) = covariance
+            .eigh(UPLO::Upper)
+            .expect("Eigendecomposition of the covariance matrix failed");
+        let k = self.components.min(d);
+        let mut components = Array2::zeros((k, d));
+        let mut explained_variance = Array1::zeros(k);
+        for (i, (mut component, variance)) in components
+            .outer_iter_mut()
+            .zip(explained_variance.iter_mut())
+            .enumerate()
+        {
+            let index = d - 1 - i;
+            component.assign(&vectors.column(index));
+            *variance = Float::max(variances[index], T::zero());
+
+            // the sign of the eigenvectors is arbitrary, so make the entry with the
+            // largest magnitude positive to get deterministic results
+            let largest = component.iter().fold(T::zero(), |largest, &v| {
+                if Float::abs(v) > Float::abs(largest) {
+                    v
+                } else {
+                    largest
+                }
+            });
+            if largest < T::zero() {
+                component.mapv_inplace(|v| -v);
+            }
+        }
+
+        PcaParams {
+            mean: mean.into(),
+            components: components.into(),
+            explained_variance: explained_variance.into(),
+            total_variance,
+        }
+    }
+}
+
+impl<T: Scalar<Real = T> + ContinuousValue> MapFeatures for PcaParams<T> {
+    type Input = T;
+    type Output = T;
+
+    fn map_features(&self, x: ArrayView2<T>) -> Array2<T> {
+        assert_eq!(
+            x.cols(),
+            self.mean.view().len(),
+            "Number of features must match"
+        );
+        let centered = &x - &self.mean.view();
+        centered.dot(&self.components.view().t())
+    }
+}
+
+/// Extension trait for streams of samples
+pub trait ProjectComponents<S: Scope, T: Data> {
+    /// Projects the samples onto the latest fitted principal components
+    fn project(&self, params: &Stream<S, PcaParams<T>>) -> Stream<S, AbomonableArray2<T>>;
+}
+
+impl<S, T> ProjectComponents<S, T> for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: Scalar<Real = T> + ContinuousValue,
+{
+    fn project(&self, params: &Stream<S, PcaParams<T>>) -> Stream<S, AbomonableArray2<T>> {
+        params.apply_latest(self, |_time, params, samples| {
+            params.map_features(samples.view()).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::preprocessing::TransformFeatures;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+
+    /// Samples `t (1, 2) + s (2, -1) + (1, 1)`, with uncorrelated `t` and `s`
+    fn example() -> Array2<f64> {
+        arr2(&[[-0.8, -3.1], [-0.2, -0.9], [1.8, 3.1], [3.2, 4.9]])
+    }
+
+    fn fit(pca: &Pca, x: ArrayView2<f64>) -> PcaParams<f64> {
+        // fit from two separate parts to check merging
+        let first = pca.statistics(x.slice(s![..1, ..]));
+        let second = pca.statistics(x.slice(s![1.., ..]));
+        pca.fit(&pca.merge(first, second))
+    }
+
+    #[test]
+    fn components_and_variance() {
+        let params = fit(&Pca::new(2), example().view());
+        let sqrt_5 = 5f64.sqrt();
+
+        for (a, b) in params.mean.view().iter().zip(&[1., 1.]) {
+            assert!(abs_diff_eq!(a, b, epsilon = 1e-10));
+        }
+        let expected = [1. / sqrt_5, 2. / sqrt_5, 2. / sqrt_5, -1. / sqrt_5];
+        for (a, b) in params.components.view().iter().zip(&expected) {
+            assert!(abs_diff_eq!(a, b, epsilon = 1e-10));
+        }
+        for (a, b) in params
+            .explained_variance
+            .view()
+            .iter()
+            .zip(&[50. / 3., 0.2 / 3.])
+        {
+            assert!(abs_diff_eq!(a, b, epsilon = 1e-10));
+        }
+        let ratio = params.explained_variance_ratio();
+        assert!(abs_diff_eq!(ratio[0], &(50. / 50.2), epsilon = 1e-10));
+        assert!(abs_diff_eq!(&ratio.scalar_sum(), &1., epsilon = 1e-10));
+    }
+
+    #[test]
+    fn projection() {
+        let params = fit(&Pca::new(1), example().view());
+        let projected = params.map_features(example().view());
+        assert_eq!(projected.dim(), (4, 1));
+
+        let sqrt_5 = 5f64.sqrt();
+        for (a, b) in projected.iter().zip(&[-2., -1., 1., 2.]) {
+            assert!(abs_diff_eq!(a, &(b * sqrt_5), epsilon = 1e-10));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Number of features must match")]
+    fn feature_mismatch() {
+        let params = fit(&Pca::new(1), example().view());
+        params.map_features(arr2(&[[1., 2., 3.]]).view());
+    }
+
+    #[test]
+    fn large_offset() {
+        // the covariance does not depend on the offset of the samples, which must not
+        // cancel out in the calculation
+        let offset = example().mapv(|v| v + 1e8);
+        let params = fit(&Pca::new(2), offset.view());
+        for (a, b) in params
+            .explained_variance
+            .view()
+            .iter()
+            .zip(&[50. / 3., 0.2 / 3.])
+        {
+            assert!(abs_diff_eq!(a, b, epsilon = 1e-6));
+        }
+    }
+
+    #[test]
+    fn more_components_than_features() {
+        let params = fit(&Pca::new(5), example().view());
+        assert_eq!(params.components.view().dim(), (2, 2));
+    }
+
+    #[test]
+    fn fit_and_project_streams() {
+        let result = ::timely::example(|scope| {
+            let chunks = vec![
+                AbomonableArray2::from(example().slice(s![..2, ..]).to_owned()),
+                AbomonableArray2::from(example().slice(s![2.., ..]).to_owned()),
+            ]
+            .to_stream(scope);
+
+            let params = chunks.fit_transformer(&Pca::new(1));
+            chunks
+                .project(&params)
+                .map(|x| {
+                    x.view()
+                        .iter()
+                        .map(|v| (v / 5f64.sqrt()).round() as i64)
+                        .collect::<Vec<_>>()
+                })
+                .capture()
+        })
+        .extract();
+
+        let mut projected = result
+            .into_iter()
+            .flat_map(|(_, data)| data)
+            .collect::<Vec<_>>();
+        projected.sort();
+        assert_eq!(projected, vec![vec![-2, -1], vec![1, 2]]);
+    }
+}
@@ -0,0 +1,392 @@
+//! Transformers that scale each feature independently.
+
+use super::{MapFeatures, Transform, Transformer};
+use models::decision_tree::classification::histogram::Histogram;
+use models::decision_tree::histogram_generics::{BaseHistogram, ContinuousValue, HistogramSetItem};
+use ndarray::prelude::*;
+use num_traits::cast::cast;
+
+/// Fitted scaling of each feature: `x * scale + offset`
+#[derive(Abomonation, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScalingParameters<T> {
+    pub scale: Vec<T>,
+    pub offset: Vec<T>,
+}
+
+impl<T: ContinuousValue> ScalingParameters<T> {
+    /// Parameters that map `center` to zero and divide by `spread`. Features with a
+    /// spread of zero are only shifted.
+    fn centering(center: &[T], spread: &[T]) -> Self {
+        let scale = spread
+            .iter()
+            .map(|&s| {
+                if s > T::zero() {
+                    T::one() / s
+                } else {
+                    T::one()
+                }
+            })
+            .collect::<Vec<_>>();
+        let offset = center.iter().zip(&scale).map(|(&c, &s)| -c * s).collect();
+        ScalingParameters { scale, offset }
+    }
+
+    /// Reverts the scaling in place
+    pub fn inverse_transform(&self, mut x: ArrayViewMut2<T>) {
+        for mut row in x.outer_iter_mut() {
+            for ((value, &scale), &offset) in row.iter_mut().zip(&self.scale).zip(&self.offset) {
+                *value = (*value - offset) / scale;
+            }
+        }
+    }
+}
+
+impl<T: ContinuousValue> Transform<T> for ScalingParameters<T> {
+    fn transform(&self, mut x: ArrayViewMut2<T>) {
+        assert_eq!(x.cols(), self.scale.len(), "Number of features must match");
+        for mut row in x.outer_iter_mut() {
+            for ((value, &scale), &offset) in row.iter_mut().zip(&self.scale).zip(&self.offset) {
+                *value = *value * scale + offset;
+            }
+        }
+    }
+}
+
+impl<T: ContinuousValue> MapFeatures for ScalingParameters<T> {
+    type Input = T;
+    type Output = T;
+
+    fn map_features(&self, x: ArrayView2<T>) -> Array2<T> {
+        let mut x = x.to_owned();
+        self.transform(x.view_mut());
+        x
+    }
+}
+
+/// Scales each feature to zero mean and unit variance
+#[derive(Abomonation, Clone, Copy, Debug, Default)]
+pub struct StandardScaler;
+
+/// Number of samples, mean and sum of squared differences from the mean of each feature
+#[derive(Abomonation, Clone, Debug, PartialEq)]
+pub struct MomentStatistics<T> {
+    count: u64,
+    mean: Vec<T>,
+    squared_deviations: Vec<T>,
+}
+
+impl<T: ContinuousValue> Transformer<T> for StandardScaler {
+    type Statistics = MomentStatistics<T>;
+    type Fitted = ScalingParameters<T>;
+
+    fn statistics(&self, x: ArrayView2<T>) -> MomentStatistics<T> {
+        let count = x.rows() as u64;
+        if count == 0 {
+            return MomentStatistics {
+                count,
+                mean: vec![T::zero(); x.cols()],
+                squared_deviations: vec![T::zero(); x.cols()],
+            };
+        }
+
+        let n: T = cast(count).unwrap();
+        let mean = x
+            .gencolumns()
+            .into_iter()
+            .map(|column| column.iter().cloned().sum::<T>() / n)
+            .collect::<Vec<_>>();
+        let squared_deviations = x
+            .gencolumns()
+            .into_iter()
+            .zip(&mean)
+            .map(|(column, &mean)| column.iter().map(|&v| (v - mean) * (v - mean)).sum())
+            .collect();
+        MomentStatistics {
+            count,
+            mean,
+            squared_deviations,
+        }
+    }
+
+    fn merge(
+        &self,
+        first: MomentStatistics<T>,
+        second: MomentStatistics<T>,
+    ) -> MomentStatistics<T> {
+        if first.count == 0 {
+            return second;
+        }
+        if second.count == 0 {
+            return first;
+        }
+
+        // combines the statistics of both parts (Chan et al.)
+        let count = first.count + second.count;
+        let (n1, n2, n): (T, T, T) = (
+            cast(first.count).unwrap(),
+            cast(second.count).unwrap(),
+            cast(count).unwrap(),
+        );
+        let mean = first
+            .mean
+            .iter()
+            .zip(&second.mean)
+            .map(|(&m1, &m2)| m1 + (m2 - m1) * n2 / n)
+            .collect();
+        let squared_deviations = first
+            .squared_deviations
+            .iter()
+            .zip(&second.squared_deviations)
+            .zip(first.mean.iter().zip(&second.mean))
+            .map(|((&s1, &s2), (&m1, &m2))| s1 + s2 + (m2 - m1) * (m2 - m1) * n1 * n2 / n)
+            .collect();
+        MomentStatistics {
+            count,
+            mean,
+            squared_deviations,
+        }
+    }
+
+    fn fit(&self, statistics: &MomentStatistics<T>) -> ScalingParameters<T> {
+        let n: T = cast(statistics.count.max(1)).unwrap();
+        let std_dev = statistics
+            .squared_deviations
+            .iter()
+            .map(|&s| (s / n).sqrt())
+            .collect::<Vec<_>>();
+        ScalingParameters::centering(&statistics.mean, &std_dev)
+    }
+}
+
+/// Scales each feature linearly so that its minimum and maximum map to the given range
+#[derive(Abomonation, Clone, Copy, Debug)]
+pub struct MinMaxScaler<T> {
+    low: T,
+    high: T,
+}
+
+impl<T: ContinuousValue> MinMaxScaler<T> {
+    pub fn new(low: T, high: T) -> Self {
+        assert!(low < high, "Lower bound must be less than upper bound");
+        MinMaxScaler { low, high }
+    }
+}
+
+impl<T: ContinuousValue> Default for MinMaxScaler<T> {
+    /// Scales features to `[0, 1]`
+    fn default() -> Self {
+        MinMaxScaler::new(T::zero(), T::one())
+    }
+}
+
+impl<T: ContinuousValue> Transformer<T> for MinMaxScaler<T> {
+    /// Minimum and maximum of each feature
+    type Statistics = (Vec<T>, Vec<T>);
+    type Fitted = ScalingParameters<T>;
+
+    fn statistics(&self, x: ArrayView2<T>) -> (Vec<T>, Vec<T>) {
+        x.gencolumns()
+            .into_iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .fold((T::infinity(), T::neg_infinity()), |(min, max), &v| {
+                        (min.min(v), max.max(v))
+                    })
+            })
+            .unzip()
+    }
+
+    fn merge(&self, first: (Vec<T>, Vec<T>), second: (Vec<T>, Vec<T>)) -> (Vec<T>, Vec<T>) {
+        (
+            first
+                .0
+                .iter()
+                .zip(&second.0)
+                .map(|(a, &b)| a.min(b))
+                .collect(),
+            first
+                .1
+                .iter()
+                .zip(&second.1)
+                .map(|(a, &b)| a.max(b))
+                .collect(),
+        )
+    }
+
+    fn fit(&self, (min, max): &(Vec<T>, Vec<T>)) -> ScalingParameters<T> {
+        let range = self.high - self.low;
+        let scale = min
+            .iter()
+            .zip(max)
+            .map(|(&min, &max)| {
+                if max > min {
+                    range / (max - min)
+                } else {
+                    T::one()
+                }
+            })
+            .collect::<Vec<_>>();
+        let offset = min
+            .iter()
+            .zip(&scale)
+            .map(|(&min, &scale)| self.low - min * scale)
+            .collect();
+        ScalingParameters { scale, offset }
+    }
+}
+
+/// Scales each feature by removing the median and dividing by the interquartile range,
+/// which makes it robust to outliers. The quantiles are estimated from streaming
+/// histograms with the given number of bins.
+#[derive(Abomonation, Clone, Copy, Debug)]
+pub struct RobustScaler {
+    bins: usize,
+}
+
+impl RobustScaler {
+    pub fn new(bins: usize) -> Self {
+        RobustScaler { bins }
+    }
+}
+
+impl Default for RobustScaler {
+    fn default() -> Self {
+        RobustScaler::new(100)
+    }
+}
+
+impl<T: ContinuousValue> Transformer<T> for RobustScaler {
+    /// Histogram of the values of each feature
+    type Statistics = Vec<Histogram<T>>;
+    type Fitted = ScalingParameters<T>;
+
+    fn statistics(&self, x: ArrayView2<T>) -> Vec<Histogram<T>> {
+        x.gencolumns()
+            .into_iter()
+            .map(|column| {
+                let mut histogram = Histogram::new(self.bins);
+                for &v in column {
+                    histogram.insert(v, 1);
+                }
+                histogram
+            })
+            .collect()
+    }
+
+    fn merge(&self, mut first: Vec<Histogram<T>>, second: Vec<Histogram<T>>) -> Vec<Histogram<T>> {
+        for (histogram, other) in first.iter_mut().zip(second) {
+            histogram.merge(other);
+        }
+        first
+    }
+
+    fn fit(&self, histograms: &Vec<Histogram<T>>) -> ScalingParameters<T> {
+        let quantile = |h: &Histogram<T>, q: f64| h.quantile(cast(q).unwrap()).unwrap_or(T::zero());
+        let median = histograms
+            .iter()
+            .map(|h| quantile(h, 0.5))
+            .collect::<Vec<_>>();
+        let interquartile_range = histograms
+            .iter()
+            .map(|h| quantile(h, 0.75) - quantile(h, 0.25))
+            .collect::<Vec<_>>();
+        ScalingParameters::centering(&median, &interquartile_range)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::preprocessing::TransformFeatures;
+    use data::serialization::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+
+    fn example() -> Array2<f64> {
+        arr2(&[[1., 10.], [2., 10.], [3., 10.], [6., 10.]])
+    }
+
+    fn fit<Tr: Transformer<f64>>(transformer: &Tr, x: ArrayView2<f64>) -> Tr::Fitted {
+        // fit from two separate parts to check merging
+        let first = transformer.statistics(x.slice(s![..1, ..]));
+        let second = transformer.statistics(x.slice(s![1.., ..]));
+        transformer.fit(&transformer.merge(first, second))
+    }
+
+    #[test]
+    fn standard_scaler() {
+        let mut x = example();
+        let parameters = fit(&StandardScaler, x.view());
+        parameters.transform(x.view_mut());
+
+        let mean = x.column(0).scalar_sum() / 4.;
+        let variance = x.column(0).iter().map(|v| v * v).sum::<f64>() / 4.;
+        assert!(abs_diff_eq!(mean, &0., epsilon = 1e-10));
+        assert!(abs_diff_eq!(variance, &1., epsilon = 1e-10));
+        assert!(x
+            .column(1)
+            .iter()
+            .all(|v| abs_diff_eq!(v, &0., epsilon = 1e-10)));
+
+        parameters.inverse_transform(x.view_mut());
+        for (a, b) in x.iter().zip(example().iter()) {
+            assert!(abs_diff_eq!(a, b, epsilon = 1e-10));
+        }
+    }
+
+    #[test]
+    fn min_max_scaler() {
+        let mut x = example();
+        fit(&MinMaxScaler::new(-1., 1.), x.view()).transform(x.view_mut());
+        for (a, b) in x.column(0).iter().zip(&[-1., -0.6, -0.2, 1.]) {
+            assert!(abs_diff_eq!(a, b, epsilon = 1e-10));
+        }
+        assert!(x
+            .column(1)
+            .iter()
+            .all(|v| abs_diff_eq!(v, &-1., epsilon = 1e-10)));
+    }
+
+    #[test]
+    fn robust_scaler() {
+        let mut x = Array2::from_shape_fn((101, 1), |(row, _)| row as f64);
+        x[(100, 0)] = 10_000.;
+        fit(&RobustScaler::new(20), x.view()).transform(x.view_mut());
+
+        // the outlier does not affect the scaling of the other values
+        assert!(abs_diff_eq!(x[(50, 0)], &0., epsilon = 0.1));
+        assert!(abs_diff_eq!(x[(25, 0)], &-0.5, epsilon = 0.1));
+        assert!(abs_diff_eq!(x[(75, 0)], &0.5, epsilon = 0.1));
+    }
+
+    #[test]
+    fn fit_and_transform_streams() {
+        let result = ::timely::example(|scope| {
+            let chunks = vec![
+                AbomonableArray2::from(example().slice(s![..2, ..]).to_owned()),
+                AbomonableArray2::from(example().slice(s![2.., ..]).to_owned()),
+            ]
+            .to_stream(scope);
+
+            let parameters = chunks.fit_transformer(&MinMaxScaler::default());
+            chunks
+                .transform(&parameters)
+                .map(|x| {
+                    x.view()
+                        .iter()
+                        .map(|v| (v * 100.).round() as i64)
+                        .collect::<Vec<_>>()
+                })
+                .capture()
+        })
+        .extract();
+
+        let mut transformed = result
+            .into_iter()
+            .flat_map(|(_, data)| data)
+            .collect::<Vec<_>>();
+        transformed.sort();
+        assert_eq!(transformed, vec![vec![0, 0, 20, 0], vec![40, 0, 100, 0]]);
+    }
+}
@@ -0,0 +1,483 @@
+//! A simple binary columnar file format with random access to rows.
+//!
+//! Rows are stored in row groups. Within a row group, the values of each column are stored
+//! contiguously in little-endian byte order, so a range of rows can be read with a single
+//! seek and read per column. A footer at the end of the file indexes the row groups:
+//!
+//! ```text
+//! MAGIC | row group 0 | row group 1 | ... | footer | footer length (u64) | MAGIC
+//! footer: cols (u64) | rows (u64) | type tag (u64) | groups (u64) | (offset (u64), rows (u64)) per group
+//! ```
+
+use data::providers::{contiguous_chunk_indices, DataSource, DataSourceSpec, IntSliceIndex};
+use data::serialization::*;
+use failure::Error;
+use ndarray::prelude::*;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use timely::Data;
+
+const MAGIC: &[u8; 8] = b"MLDCOL01";
+
+/// A fixed size value that can be stored in a columnar file
+pub trait ColumnarValue: Data + Copy {
+    /// Identifies the value type in the file, to prevent reading values as the wrong type
+    const TYPE_TAG: u8;
+    /// Number of bytes per value
+    const SIZE: usize;
+
+    /// Converts the value into its bit representation. Only the lowest `SIZE` bytes are stored.
+    fn encode(self) -> u64;
+
+    /// Converts a bit representation created by `encode` back into a value
+    fn decode(bits: u64) -> Self;
+}
+
+macro_rules! impl_columnar_value {
+    ($t:ty, $tag:expr, $size:expr, $encode:expr, $decode:expr) => {
+        impl ColumnarValue for $t {
+            const TYPE_TAG: u8 = $tag;
+            const SIZE: usize = $size;
+
+            #[inline]
+            fn encode(self) -> u64 {
+                $encode(self)
+            }
+
+            #[inline]
+            fn decode(bits: u64) -> Self {
+                $decode(bits)
+            }
+        }
+    };
+}
+
+impl_columnar_value!(f64, 1, 8, f64::to_bits, f64::from_bits);
+impl_columnar_value!(
+    f32,
+    2,
+    4,
+    |v: f32| u64::from(v.to_bits()),
+    |bits: u64| f32::from_bits(bits as u32)
+);
+impl_columnar_value!(i64, 3, 8, |v: i64| v as u64, |bits: u64| bits as i64);
+impl_columnar_value!(i32, 4, 4, |v: i32| u64::from(v as u32), |bits: u64| bits as u32 as i32);
+impl_columnar_value!(u64, 5, 8, |v: u64| v, |bits: u64| bits);
+impl_columnar_value!(u32, 6, 4, u64::from, |bits: u64| bits as u32);
+
+fn encode_into<T: ColumnarValue>(value: T, buffer: &mut [u8]) {
+    let bits = value.encode();
+    for (i, byte) in buffer[..T::SIZE].iter_mut().enumerate() {
+        *byte = (bits >> (8 * i)) as u8;
+    }
+}
+
+fn decode_from<T: ColumnarValue>(buffer: &[u8]) -> T {
+    let bits = buffer[..T::SIZE]
+        .iter()
+        .enumerate()
+        .fold(0u64, |bits, (i, &byte)| bits | (u64::from(byte) << (8 * i)));
+    T::decode(bits)
+}
+
+pub(super) fn write_u64<W: Write>(writer: &mut W, value: u64) -> Result<(), Error> {
+    let mut buffer = [0u8; 8];
+    encode_into(value, &mut buffer);
+    writer.write_all(&buffer)?;
+    Ok(())
+}
+
+pub(super) fn read_u64<R: Read>(reader: &mut R) -> Result<u64, Error> {
+    let mut buffer = [0u8; 8];
+    reader.read_exact(&mut buffer)?;
+    Ok(decode_from(&buffer))
+}
+
+/// Writes two-dimensional data into a columnar file. Rows are buffered until a
+/// row group is full. `finish` must be called to write the remaining rows and the footer.
+pub struct ColumnarWriter<T: ColumnarValue> {
+    writer: BufWriter<File>,
+    cols: usize,
+    row_group_size: usize,
+    buffer: Vec<T>,
+    position: u64,
+    row_groups: Vec<(u64, u64)>,
+}
+
+impl<T: ColumnarValue> ColumnarWriter<T> {
+    /// Creates the file at `path` for data with `cols` columns,
+    /// storing at most `row_group_size` rows per row group.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        cols: usize,
+        row_group_size: usize,
+    ) -> Result<Self, Error> {
+        if row_group_size == 0 {
+            return Err(format_err!("Row group size must be larger than 0"));
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+
+        Ok(ColumnarWriter {
+            writer,
+            cols,
+            row_group_size,
+            buffer: Vec::with_capacity(row_group_size * cols),
+            position: MAGIC.len() as u64,
+            row_groups: Vec::new(),
+        })
+    }
+
+    /// Appends the given rows to the file
+    pub fn write_rows(&mut self, rows: &ArrayView2<T>) -> Result<(), Error> {
+        if rows.cols() != self.cols {
+            return Err(format_err!(
+                "Expected rows with {} columns, got {}",
+                self.cols,
+                rows.cols()
+            ));
+        }
+
+        for row in rows.outer_iter() {
+            self.buffer.extend(row.iter());
+            if self.buffer.len() >= self.row_group_size * self.cols {
+                self.flush_row_group()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered rows and the footer and closes the file
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.flush_row_group()?;
+
+        let mut footer = Vec::new();
+        write_u64(&mut footer, self.cols as u64)?;
+        write_u64(&mut footer, self.row_groups.iter().map(|&(_, rows)| rows).sum())?;
+        write_u64(&mut footer, u64::from(T::TYPE_TAG))?;
+        write_u64(&mut footer, self.row_groups.len() as u64)?;
+        for &(offset, rows) in &self.row_groups {
+            write_u64(&mut footer, offset)?;
+            write_u64(&mut footer, rows)?;
+        }
+
+        self.writer.write_all(&footer)?;
+        write_u64(&mut self.writer, footer.len() as u64)?;
+        self.writer.write_all(MAGIC)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn flush_row_group(&mut self) -> Result<(), Error> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let rows = self.buffer.len() / self.cols;
+        let mut bytes = vec![0u8; self.buffer.len() * T::SIZE];
+        // transpose the buffered rows, so each column is stored contiguously
+        for (i, &value) in self.buffer.iter().enumerate() {
+            let (row, col) = (i / self.cols, i % self.cols);
+            let position = (col * rows + row) * T::SIZE;
+            encode_into(value, &mut bytes[position..position + T::SIZE]);
+        }
+
+        self.writer.write_all(&bytes)?;
+        self.row_groups.push((self.position, rows as u64));
+        self.position += bytes.len() as u64;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+/// Writes a complete array into a new columnar file
+pub fn write_columnar_file<T: ColumnarValue, P: AsRef<Path>>(
+    path: P,
+    data: &ArrayView2<T>,
+    row_group_size: usize,
+) -> Result<(), Error> {
+    let mut writer = ColumnarWriter::create(path, data.cols(), row_group_size)?;
+    writer.write_rows(data)?;
+    writer.finish()
+}
+
+/// Specifies a columnar file as a data source
+#[derive(Abomonation, Debug, Clone)]
+pub struct ColumnarFileProviderSpec {
+    path: String,
+}
+
+impl ColumnarFileProviderSpec {
+    pub fn new<P: Into<String>>(path: P) -> Self {
+        ColumnarFileProviderSpec { path: path.into() }
+    }
+}
+
+impl<T: ColumnarValue> DataSourceSpec<AbomonableArray2<T>> for ColumnarFileProviderSpec {
+    type Provider = ColumnarProvider<T>;
+}
+
+impl<T: ColumnarValue> TryFrom<ColumnarFileProviderSpec> for ColumnarProvider<T> {
+    type Error = ::failure::Error;
+
+    fn try_from(spec: ColumnarFileProviderSpec) -> Result<Self, Self::Error> {
+        ColumnarProvider::open(spec.path)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RowGroup {
+    offset: u64,
+    start_row: usize,
+    rows: usize,
+}
+
+/// Data provider that reads rows from a columnar file. Only the row group index
+/// is kept in memory, all rows are read on demand.
+pub struct ColumnarProvider<T: ColumnarValue> {
+    file: File,
+    cols: usize,
+    rows: usize,
+    row_groups: Vec<RowGroup>,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T: ColumnarValue> ColumnarProvider<T> {
+    /// Opens a columnar file and reads its row group index
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(format_err!("Not a columnar data file"));
+        }
+
+        file.seek(SeekFrom::End(-16))?;
+        let footer_length = read_u64(&mut file)?;
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(format_err!("Columnar data file is truncated"));
+        }
+
+        file.seek(SeekFrom::End(-16 - footer_length as i64))?;
+        let cols = read_u64(&mut file)? as usize;
+        let rows = read_u64(&mut file)? as usize;
+        let type_tag = read_u64(&mut file)?;
+        if type_tag != u64::from(T::TYPE_TAG) {
+            return Err(format_err!(
+                "Columnar data file contains values of type {}, expected type {}",
+                type_tag,
+                T::TYPE_TAG
+            ));
+        }
+
+        let group_count = read_u64(&mut file)? as usize;
+        let mut row_groups = Vec::with_capacity(group_count);
+        let mut start_row = 0;
+        for _ in 0..group_count {
+            let offset = read_u64(&mut file)?;
+            let group_rows = read_u64(&mut file)? as usize;
+            row_groups.push(RowGroup {
+                offset,
+                start_row,
+                rows: group_rows,
+            });
+            start_row += group_rows;
+        }
+
+        Ok(ColumnarProvider {
+            file,
+            cols,
+            rows,
+            row_groups,
+            phantom_data: PhantomData,
+        })
+    }
+
+    /// Index of the row group that contains the given row
+    fn row_group_of(&self, row: usize) -> usize {
+        match self
+            .row_groups
+            .binary_search_by_key(&row, |group| group.start_row)
+        {
+            Ok(group) => group,
+            Err(next_group) => next_group - 1,
+        }
+    }
+
+    /// Reads `length` consecutive values of a column, starting at `row`, which
+    /// must all be contained in the given row group
+    fn read_column_run(
+        &mut self,
+        group: usize,
+        col: usize,
+        row: usize,
+        length: usize,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let group = &self.row_groups[group];
+        let position =
+            group.offset + ((col * group.rows + row - group.start_row) * T::SIZE) as u64;
+
+        buffer.resize(length * T::SIZE, 0);
+        self.file.seek(SeekFrom::Start(position))?;
+        self.file.read_exact(buffer)?;
+        Ok(())
+    }
+}
+
+impl<T: ColumnarValue> DataSource<AbomonableArray2<T>> for ColumnarProvider<T> {
+    fn slice(&mut self, idx: IntSliceIndex<usize>) -> Result<AbomonableArray2<T>, Error> {
+        let end = idx.start + idx.length;
+        if end > self.rows {
+            return Err(format_err!(
+                "Slice {}..{} is out of bounds for {} rows",
+                idx.start,
+                end,
+                self.rows
+            ));
+        }
+
+        let mut values = Vec::with_capacity(idx.length * self.cols);
+        let mut columns = vec![Vec::with_capacity(idx.length); self.cols];
+        let mut buffer = Vec::new();
+
+        let mut row = idx.start;
+        while row < end {
+            let group = self.row_group_of(row);
+            let group_end = {
+                let group = &self.row_groups[group];
+                group.start_row + group.rows
+            };
+            let run_length = end.min(group_end) - row;
+
+            for (col, column) in columns.iter_mut().enumerate() {
+                self.read_column_run(group, col, row, run_length, &mut buffer)?;
+                column.extend(buffer.chunks(T::SIZE).map(decode_from::<T>));
+            }
+            row += run_length;
+        }
+
+        for i in 0..idx.length {
+            values.extend(columns.iter().map(|column| column[i]));
+        }
+        Ok(Array2::from_shape_vec((idx.length, self.cols), values)?.into())
+    }
+
+    fn all(&mut self) -> Result<AbomonableArray2<T>, Error> {
+        let rows = self.rows;
+        self.slice(IntSliceIndex::new(0, rows))
+    }
+
+    fn select(&mut self, indices: &[usize]) -> Result<AbomonableArray2<T>, Error> {
+        let mut values = Vec::with_capacity(indices.len() * self.cols);
+        let mut buffer = Vec::new();
+
+        for &row in indices {
+            if row >= self.rows {
+                return Err(format_err!(
+                    "Row {} is out of bounds for {} rows",
+                    row,
+                    self.rows
+                ));
+            }
+
+            let group = self.row_group_of(row);
+            for col in 0..self.cols {
+                self.read_column_run(group, col, row, 1, &mut buffer)?;
+                values.push(decode_from(&buffer));
+            }
+        }
+        Ok(Array2::from_shape_vec((indices.len(), self.cols), values)?.into())
+    }
+
+    fn chunk_indices(
+        &mut self,
+        chunk_length: usize,
+    ) -> Result<Box<Iterator<Item = IntSliceIndex<usize>>>, Error> {
+        contiguous_chunk_indices(self.rows, chunk_length)
+    }
+
+    fn count(&mut self) -> Result<usize, Error> {
+        Ok(self.rows)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::process;
+
+    fn test_file(name: &str) -> String {
+        temp_dir()
+            .join(format!("ml_dataflow_{}_{}.col", name, process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn test_data() -> Array2<f64> {
+        Array2::from_shape_fn((25, 3), |(row, col)| (row * 10 + col) as f64)
+    }
+
+    #[test]
+    fn slice_across_row_groups() {
+        let path = test_file("slice");
+        write_columnar_file(&path, &test_data().view(), 7).unwrap();
+
+        let mut provider =
+            ColumnarProvider::<f64>::try_from(ColumnarFileProviderSpec::new(path.clone())).unwrap();
+        assert_eq!(provider.count().unwrap(), 25);
+
+        let slice: Array2<f64> = provider.slice(IntSliceIndex::new(5, 12)).unwrap().into();
+        assert_eq!(slice, test_data().slice(s![5..17, ..]));
+
+        let all: Array2<f64> = provider.all().unwrap().into();
+        assert_eq!(all, test_data());
+
+        let selected: Array2<f64> = provider.select(&[24, 0, 7]).unwrap().into();
+        assert_eq!(selected, test_data().select(Axis(0), &[24, 0, 7]));
+
+        assert!(provider.slice(IntSliceIndex::new(20, 6)).is_err());
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn chunk_indices() {
+        let path = test_file("chunks");
+        write_columnar_file(&path, &test_data().view(), 10).unwrap();
+
+        let mut provider = ColumnarProvider::<f64>::open(&path).unwrap();
+        let chunks = provider.chunk_indices(10).unwrap().collect::<Vec<_>>();
+        assert_eq!(
+            chunks,
+            vec![
+                IntSliceIndex::new(0, 10),
+                IntSliceIndex::new(10, 10),
+                IntSliceIndex::new(20, 5),
+            ]
+        );
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_wrong_type() {
+        let path = test_file("types");
+        write_columnar_file(&path, &arr2(&[[1i32, 2], [-3, 4]]).view(), 10).unwrap();
+
+        assert!(ColumnarProvider::<f64>::open(&path).is_err());
+        let values: Array2<i32> = ColumnarProvider::<i32>::open(&path)
+            .unwrap()
+            .all()
+            .unwrap()
+            .into();
+        assert_eq!(values, arr2(&[[1, 2], [-3, 4]]));
+        remove_file(path).unwrap();
+    }
+}
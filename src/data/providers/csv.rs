@@ -15,6 +15,15 @@ pub struct CsvFileProviderSpec {
     options: CsvProviderOptions,
 }
 
+impl CsvFileProviderSpec {
+    pub fn new<P: Into<String>>(path: P, options: CsvProviderOptions) -> Self {
+        CsvFileProviderSpec {
+            path: path.into(),
+            options,
+        }
+    }
+}
+
 impl TryFrom<CsvFileProviderSpec> for CsvProvider<File, CsvFileProviderSpec> {
     type Error = ::failure::Error;
 
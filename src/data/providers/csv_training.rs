@@ -0,0 +1,368 @@
+//! Data provider that reads `TrainingData` from a CSV file, with configurable
+//! feature and label columns.
+
+use csv::StringRecord;
+use data::providers::indexed_csv::{IndexedCsvFileProviderSpec, IndexedCsvProvider};
+use data::providers::{contiguous_chunk_indices, DataSource, DataSourceSpec, IntSliceIndex};
+use data::TrainingData;
+use failure::Error;
+use ndarray::prelude::*;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use timely::Data;
+
+/// Selects a column of a CSV file by its header name or by its position
+#[derive(Abomonation, Debug, Clone, PartialEq, Eq)]
+pub enum ColumnSelector {
+    Name(String),
+    Index(usize),
+}
+
+impl ColumnSelector {
+    /// Finds the position of the selected column. Columns can only be selected
+    /// by name if the file has headers.
+    pub fn resolve(&self, headers: Option<&StringRecord>, columns: usize) -> Result<usize, Error> {
+        let index = match self {
+            ColumnSelector::Index(index) => *index,
+            ColumnSelector::Name(name) => headers
+                .ok_or_else(|| {
+                    format_err!("Can't select column '{}' by name without headers", name)
+                })?
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| format_err!("No column named '{}'", name))?,
+        };
+
+        if index >= columns {
+            return Err(format_err!(
+                "Column {} is out of bounds for {} columns",
+                index,
+                columns
+            ));
+        }
+        Ok(index)
+    }
+}
+
+impl<'a> From<&'a str> for ColumnSelector {
+    fn from(name: &'a str) -> Self {
+        ColumnSelector::Name(name.to_owned())
+    }
+}
+
+impl From<usize> for ColumnSelector {
+    fn from(index: usize) -> Self {
+        ColumnSelector::Index(index)
+    }
+}
+
+/// Converts the text of a CSV field into a value
+pub trait ParseValue<T> {
+    fn parse_value(&self, field: &str) -> Result<T, Error>;
+}
+
+/// Parses values using their `FromStr` implementation
+#[derive(Abomonation, Debug, Clone, Copy, Default)]
+pub struct FromStrParser;
+
+impl<T> ParseValue<T> for FromStrParser
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn parse_value(&self, field: &str) -> Result<T, Error> {
+        field
+            .trim()
+            .parse()
+            .map_err(|e| format_err!("Failed to parse '{}': {}", field, e))
+    }
+}
+
+/// Maps a fixed set of class names to the class indices `0..n`
+#[derive(Abomonation, Debug, Clone, PartialEq, Eq)]
+pub struct LabelMap {
+    labels: Vec<String>,
+}
+
+impl LabelMap {
+    pub fn new<S: Into<String>>(labels: Vec<S>) -> Self {
+        LabelMap {
+            labels: labels.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Name of the class with the given index
+    pub fn label(&self, index: usize) -> Option<&str> {
+        self.labels.get(index).map(|label| label.as_str())
+    }
+}
+
+impl ParseValue<usize> for LabelMap {
+    fn parse_value(&self, field: &str) -> Result<usize, Error> {
+        self.labels
+            .iter()
+            .position(|label| label == field.trim())
+            .ok_or_else(|| format_err!("Unknown label '{}'", field))
+    }
+}
+
+/// Specifies a CSV file from which `TrainingData` is read. By default, all columns
+/// except the label column are used as features and values are parsed with `FromStr`.
+#[derive(Abomonation, Debug, Clone)]
+pub struct CsvTrainingDataSpec<Px, Py> {
+    csv: IndexedCsvFileProviderSpec,
+    features: Option<Vec<ColumnSelector>>,
+    label: ColumnSelector,
+    x_parser: Px,
+    y_parser: Py,
+}
+
+impl CsvTrainingDataSpec<FromStrParser, FromStrParser> {
+    pub fn new<C: Into<ColumnSelector>>(csv: IndexedCsvFileProviderSpec, label: C) -> Self {
+        CsvTrainingDataSpec {
+            csv,
+            features: None,
+            label: label.into(),
+            x_parser: FromStrParser,
+            y_parser: FromStrParser,
+        }
+    }
+}
+
+impl<Px, Py> CsvTrainingDataSpec<Px, Py> {
+    /// Use only the given columns as features, in the given order
+    pub fn features<C: Into<ColumnSelector>>(mut self, features: Vec<C>) -> Self {
+        self.features = Some(features.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Configure how feature values are parsed
+    pub fn x_parser<P>(self, x_parser: P) -> CsvTrainingDataSpec<P, Py> {
+        CsvTrainingDataSpec {
+            csv: self.csv,
+            features: self.features,
+            label: self.label,
+            x_parser,
+            y_parser: self.y_parser,
+        }
+    }
+
+    /// Configure how labels are parsed
+    pub fn y_parser<P>(self, y_parser: P) -> CsvTrainingDataSpec<Px, P> {
+        CsvTrainingDataSpec {
+            csv: self.csv,
+            features: self.features,
+            label: self.label,
+            x_parser: self.x_parser,
+            y_parser,
+        }
+    }
+}
+
+impl<T, L, Px, Py> DataSourceSpec<TrainingData<T, L>> for CsvTrainingDataSpec<Px, Py>
+where
+    T: Data,
+    L: Data,
+    Px: ParseValue<T> + Data,
+    Py: ParseValue<L> + Data,
+{
+    type Provider = CsvTrainingDataProvider<T, L, Px, Py>;
+}
+
+impl<T, L, Px, Py> TryFrom<CsvTrainingDataSpec<Px, Py>> for CsvTrainingDataProvider<T, L, Px, Py> {
+    type Error = ::failure::Error;
+
+    fn try_from(spec: CsvTrainingDataSpec<Px, Py>) -> Result<Self, Self::Error> {
+        let mut records = IndexedCsvProvider::try_from(spec.csv)?;
+        let headers = records.headers()?;
+
+        let columns = match headers {
+            Some(ref headers) => headers.len(),
+            None if records.record_count() > 0 => {
+                records.slice_records(IntSliceIndex::new(0, 1))?[0].len()
+            }
+            None => 0,
+        };
+
+        let label_column = spec.label.resolve(headers.as_ref(), columns)?;
+        let feature_columns = match spec.features {
+            Some(features) => features
+                .iter()
+                .map(|feature| feature.resolve(headers.as_ref(), columns))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => (0..columns).filter(|&col| col != label_column).collect(),
+        };
+
+        Ok(CsvTrainingDataProvider {
+            records,
+            feature_columns,
+            label_column,
+            x_parser: spec.x_parser,
+            y_parser: spec.y_parser,
+            phantom_data: PhantomData,
+        })
+    }
+}
+
+/// Data provider that converts the records of a CSV file into `TrainingData`
+pub struct CsvTrainingDataProvider<T, L, Px, Py> {
+    records: IndexedCsvProvider,
+    feature_columns: Vec<usize>,
+    label_column: usize,
+    x_parser: Px,
+    y_parser: Py,
+    phantom_data: PhantomData<(T, L)>,
+}
+
+impl<T, L, Px, Py> CsvTrainingDataProvider<T, L, Px, Py>
+where
+    Px: ParseValue<T>,
+    Py: ParseValue<L>,
+{
+    /// Parses the selected columns of the given records. `first_index` is the
+    /// index of the first record, used for error messages.
+    fn parse_records(
+        &self,
+        records: &[StringRecord],
+        first_index: Option<usize>,
+    ) -> Result<TrainingData<T, L>, Error> {
+        let mut x = Vec::with_capacity(records.len() * self.feature_columns.len());
+        let mut y = Vec::with_capacity(records.len());
+
+        for (i, record) in records.iter().enumerate() {
+            let field = |col: usize| {
+                record.get(col).ok_or_else(|| {
+                    format_err!(
+                        "Record {} has no column {}",
+                        first_index.map_or(i, |first| first + i),
+                        col
+                    )
+                })
+            };
+
+            for &col in &self.feature_columns {
+                x.push(self.x_parser.parse_value(field(col)?)?);
+            }
+            y.push(self.y_parser.parse_value(field(self.label_column)?)?);
+        }
+
+        Ok(TrainingData {
+            x: Array2::from_shape_vec((records.len(), self.feature_columns.len()), x)?.into(),
+            y: Array1::from_vec(y).into(),
+            weights: None,
+        })
+    }
+}
+
+impl<T, L, Px, Py> DataSource<TrainingData<T, L>> for CsvTrainingDataProvider<T, L, Px, Py>
+where
+    T: Data,
+    L: Data,
+    Px: ParseValue<T>,
+    Py: ParseValue<L>,
+{
+    fn slice(&mut self, idx: IntSliceIndex<usize>) -> Result<TrainingData<T, L>, Error> {
+        let records = self.records.slice_records(idx)?;
+        self.parse_records(&records, Some(idx.start))
+    }
+
+    fn all(&mut self) -> Result<TrainingData<T, L>, Error> {
+        let count = self.count()?;
+        self.slice(IntSliceIndex::new(0, count))
+    }
+
+    fn select(&mut self, indices: &[usize]) -> Result<TrainingData<T, L>, Error> {
+        let records = self.records.select_records(indices)?;
+        self.parse_records(&records, None)
+    }
+
+    fn chunk_indices(
+        &mut self,
+        chunk_length: usize,
+    ) -> Result<Box<Iterator<Item = IntSliceIndex<usize>>>, Error> {
+        contiguous_chunk_indices(self.records.record_count(), chunk_length)
+    }
+
+    fn count(&mut self) -> Result<usize, Error> {
+        Ok(self.records.record_count())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::providers::csv::CsvProviderOptions;
+    use std::env::temp_dir;
+    use std::fs;
+    use std::process;
+
+    fn write_test_file(name: &str, content: &str) -> String {
+        let path = temp_dir()
+            .join(format!("ml_dataflow_{}_{}.csv", name, process::id()))
+            .to_string_lossy()
+            .into_owned();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn csv_spec(path: &str, has_headers: bool) -> IndexedCsvFileProviderSpec {
+        IndexedCsvFileProviderSpec::new(
+            path,
+            CsvProviderOptions {
+                has_headers,
+                delimiter: b',',
+            },
+        )
+    }
+
+    #[test]
+    fn select_columns_by_name() {
+        let path = write_test_file(
+            "training_names",
+            "a,b,class,c\n1,2,setosa,3\n4,5,virginica,6\n7,8,setosa,9\n",
+        );
+        let spec = CsvTrainingDataSpec::new(csv_spec(&path, true), "class")
+            .features(vec!["c", "a"])
+            .y_parser(LabelMap::new(vec!["setosa", "virginica"]));
+
+        let mut provider: CsvTrainingDataProvider<f64, usize, _, _> =
+            CsvTrainingDataProvider::try_from(spec).unwrap();
+        assert_eq!(provider.count().unwrap(), 3);
+
+        let data = provider.slice(IntSliceIndex::new(1, 2)).unwrap();
+        assert_eq!(data.x(), arr2(&[[6., 4.], [9., 7.]]));
+        assert_eq!(data.y(), arr1(&[1, 0]));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn select_columns_by_index() {
+        let path = write_test_file("training_indices", "1,10,2\n3,30,4\n");
+        let spec = CsvTrainingDataSpec::new(csv_spec(&path, false), 1);
+
+        let mut provider: CsvTrainingDataProvider<i64, f32, _, _> =
+            CsvTrainingDataProvider::try_from(spec).unwrap();
+        let data = provider.all().unwrap();
+        assert_eq!(data.x(), arr2(&[[1, 2], [3, 4]]));
+        assert_eq!(data.y(), arr1(&[10., 30.]));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn report_errors() {
+        let path = write_test_file("training_errors", "x,y\n1,2\nfoo,3\n");
+
+        let spec = CsvTrainingDataSpec::new(csv_spec(&path, true), "z");
+        assert!(CsvTrainingDataProvider::<f64, f64, _, _>::try_from(spec).is_err());
+
+        let spec = CsvTrainingDataSpec::new(csv_spec(&path, true), "y");
+        let mut provider: CsvTrainingDataProvider<f64, f64, _, _> =
+            CsvTrainingDataProvider::try_from(spec).unwrap();
+        assert!(provider.slice(IntSliceIndex::new(0, 1)).is_ok());
+        assert!(provider.all().is_err());
+        assert!(provider.chunk_indices(0).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
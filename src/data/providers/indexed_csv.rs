@@ -0,0 +1,408 @@
+//! CSV data provider with random access to records.
+//!
+//! When the provider is created, the file is read once to record the byte offset at which
+//! each record starts. Slices and selections then seek directly to the first requested record.
+//! The index can optionally be cached in a sidecar file next to the CSV file (`<path>.idx`),
+//! which is rebuilt whenever the size or modification time of the CSV file changes.
+
+use csv::{ByteRecord, Position, Reader as CsvReader, ReaderBuilder, StringRecord};
+use data::providers::columnar::{read_u64, write_u64};
+use data::providers::csv::CsvProviderOptions;
+use data::providers::{contiguous_chunk_indices, DataSource, DataSourceSpec, IntSliceIndex};
+use failure::Error;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::time::UNIX_EPOCH;
+use timely::Data;
+
+const INDEX_MAGIC: &[u8; 8] = b"MLDCIDX1";
+
+/// Specifies a CSV file that is accessed through a byte-offset index
+#[derive(Abomonation, Debug, Clone)]
+pub struct IndexedCsvFileProviderSpec {
+    path: String,
+    options: CsvProviderOptions,
+    cache_index: bool,
+}
+
+impl IndexedCsvFileProviderSpec {
+    pub fn new<P: Into<String>>(path: P, options: CsvProviderOptions) -> Self {
+        IndexedCsvFileProviderSpec {
+            path: path.into(),
+            options,
+            cache_index: false,
+        }
+    }
+
+    /// Store the record index in a sidecar file (`<path>.idx`) and reuse it
+    /// on subsequent runs, as long as the CSV file does not change.
+    pub fn cache_index(mut self) -> Self {
+        self.cache_index = true;
+        self
+    }
+
+    fn index_path(&self) -> String {
+        format!("{}.idx", self.path)
+    }
+}
+
+impl<T: Data> DataSourceSpec<Vec<T>> for IndexedCsvFileProviderSpec
+where
+    for<'de> T: Deserialize<'de>,
+{
+    type Provider = IndexedCsvProvider;
+}
+
+impl TryFrom<IndexedCsvFileProviderSpec> for IndexedCsvProvider {
+    type Error = ::failure::Error;
+
+    fn try_from(spec: IndexedCsvFileProviderSpec) -> Result<Self, Self::Error> {
+        IndexedCsvProvider::open(&spec)
+    }
+}
+
+/// Identifies the version of a CSV file an index was built for
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    length: u64,
+    modified: u64,
+    has_headers: bool,
+    delimiter: u8,
+}
+
+impl FileStamp {
+    fn of(path: &str, options: &CsvProviderOptions) -> Result<Self, Error> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileStamp {
+            length: metadata.len(),
+            modified: metadata.modified()?.duration_since(UNIX_EPOCH)?.as_secs(),
+            has_headers: options.has_headers,
+            delimiter: options.delimiter,
+        })
+    }
+}
+
+/// Data provider that reads records from a CSV file by seeking to their byte offsets
+pub struct IndexedCsvProvider {
+    reader: CsvReader<File>,
+    offsets: Vec<u64>,
+    has_headers: bool,
+}
+
+impl IndexedCsvProvider {
+    fn open(spec: &IndexedCsvFileProviderSpec) -> Result<Self, Error> {
+        let mut reader = Self::reader(spec)?;
+        let stamp = FileStamp::of(&spec.path, &spec.options)?;
+
+        let cached = if spec.cache_index {
+            Self::read_index(&spec.index_path(), &stamp).ok()
+        } else {
+            None
+        };
+
+        let offsets = match cached {
+            Some(offsets) => offsets,
+            None => {
+                let offsets = Self::build_index(&mut Self::reader(spec)?)?;
+                if spec.cache_index {
+                    Self::write_index(&spec.index_path(), &stamp, &offsets)?;
+                }
+                offsets
+            }
+        };
+
+        // read the headers once, so they are available for deserialization after seeking
+        if spec.options.has_headers {
+            reader.byte_headers()?;
+        }
+
+        Ok(IndexedCsvProvider {
+            reader,
+            offsets,
+            has_headers: spec.options.has_headers,
+        })
+    }
+
+    fn reader(spec: &IndexedCsvFileProviderSpec) -> Result<CsvReader<File>, Error> {
+        Ok(ReaderBuilder::new()
+            .has_headers(spec.options.has_headers)
+            .delimiter(spec.options.delimiter)
+            .from_path(&spec.path)?)
+    }
+
+    /// Reads the whole file once and records the byte offset of each record
+    fn build_index(reader: &mut CsvReader<File>) -> Result<Vec<u64>, Error> {
+        if reader.has_headers() {
+            reader.byte_headers()?;
+        }
+
+        let mut offsets = Vec::new();
+        let mut record = ByteRecord::new();
+        loop {
+            let offset = reader.position().byte();
+            if !reader.read_byte_record(&mut record)? {
+                break;
+            }
+            offsets.push(offset);
+        }
+        Ok(offsets)
+    }
+
+    fn read_index(path: &str, stamp: &FileStamp) -> Result<Vec<u64>, Error> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        let cached_stamp = FileStamp {
+            length: read_u64(&mut file)?,
+            modified: read_u64(&mut file)?,
+            has_headers: read_u64(&mut file)? != 0,
+            delimiter: read_u64(&mut file)? as u8,
+        };
+        if &magic != INDEX_MAGIC || &cached_stamp != stamp {
+            return Err(format_err!("CSV index file {} is outdated", path));
+        }
+
+        let count = read_u64(&mut file)? as usize;
+        (0..count).map(|_| read_u64(&mut file)).collect()
+    }
+
+    fn write_index(path: &str, stamp: &FileStamp, offsets: &[u64]) -> Result<(), Error> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(INDEX_MAGIC)?;
+        write_u64(&mut file, stamp.length)?;
+        write_u64(&mut file, stamp.modified)?;
+        write_u64(&mut file, stamp.has_headers as u64)?;
+        write_u64(&mut file, u64::from(stamp.delimiter))?;
+        write_u64(&mut file, offsets.len() as u64)?;
+        for &offset in offsets {
+            write_u64(&mut file, offset)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Moves the reader to the start of the record with the given index
+    fn seek_to(&mut self, record: usize) -> Result<(), Error> {
+        let offset = *self.offsets.get(record).ok_or_else(|| {
+            format_err!(
+                "Record {} is out of bounds for {} records",
+                record,
+                self.offsets.len()
+            )
+        })?;
+
+        let mut position = Position::new();
+        position.set_byte(offset).set_record(record as u64);
+        self.reader.seek(position)?;
+        Ok(())
+    }
+
+    /// Number of records in the file
+    pub fn record_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// The header record of the file, if it has one
+    pub fn headers(&mut self) -> Result<Option<StringRecord>, Error> {
+        if self.has_headers {
+            Ok(Some(self.reader.headers()?.clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads the raw records in the given slice, without deserializing them
+    pub fn slice_records(&mut self, idx: IntSliceIndex<usize>) -> Result<Vec<StringRecord>, Error> {
+        if idx.length == 0 {
+            return Ok(Vec::new());
+        }
+        self.seek_to(idx.start)?;
+        (0..idx.length).map(|_| self.next_record()).collect()
+    }
+
+    /// Reads the raw records with the given indices, without deserializing them
+    pub fn select_records(&mut self, indices: &[usize]) -> Result<Vec<StringRecord>, Error> {
+        let mut records = Vec::with_capacity(indices.len());
+        let mut next_record = None;
+
+        for &index in indices {
+            if next_record != Some(index) {
+                self.seek_to(index)?;
+            }
+            records.push(self.next_record()?);
+            next_record = Some(index + 1);
+        }
+
+        Ok(records)
+    }
+
+    /// Reads the next raw record
+    fn next_record(&mut self) -> Result<StringRecord, Error> {
+        let mut record = StringRecord::new();
+        if self.reader.read_record(&mut record)? {
+            Ok(record)
+        } else {
+            Err(format_err!("Unexpected end of CSV file"))
+        }
+    }
+
+    /// Deserializes the next record
+    fn next_row<Row>(&mut self) -> Result<Row, Error>
+    where
+        for<'de> Row: Deserialize<'de>,
+    {
+        match self.reader.deserialize().next() {
+            Some(row) => Ok(row?),
+            None => Err(format_err!("Unexpected end of CSV file")),
+        }
+    }
+}
+
+impl<Row> DataSource<Vec<Row>> for IndexedCsvProvider
+where
+    for<'de> Row: Deserialize<'de>,
+    Row: Data,
+{
+    fn slice(&mut self, idx: IntSliceIndex<usize>) -> Result<Vec<Row>, Error> {
+        if idx.length == 0 {
+            return Ok(Vec::new());
+        }
+        if idx.start + idx.length > self.offsets.len() {
+            return Err(format_err!(
+                "Slice {}..{} is out of bounds for {} records",
+                idx.start,
+                idx.start + idx.length,
+                self.offsets.len()
+            ));
+        }
+
+        self.seek_to(idx.start)?;
+        let rows = self
+            .reader
+            .deserialize()
+            .take(idx.length)
+            .collect::<Result<Vec<Row>, _>>()?;
+        if rows.len() < idx.length {
+            return Err(format_err!("Unexpected end of CSV file"));
+        }
+        Ok(rows)
+    }
+
+    fn all(&mut self) -> Result<Vec<Row>, Error> {
+        let count = self.offsets.len();
+        self.slice(IntSliceIndex::new(0, count))
+    }
+
+    fn select(&mut self, indices: &[usize]) -> Result<Vec<Row>, Error> {
+        let mut rows = Vec::with_capacity(indices.len());
+        let mut next_record = None;
+
+        for &index in indices {
+            // consecutive records can be read without seeking
+            if next_record != Some(index) {
+                self.seek_to(index)?;
+            }
+            rows.push(self.next_row()?);
+            next_record = Some(index + 1);
+        }
+
+        Ok(rows)
+    }
+
+    fn chunk_indices(
+        &mut self,
+        chunk_length: usize,
+    ) -> Result<Box<Iterator<Item = IntSliceIndex<usize>>>, Error> {
+        contiguous_chunk_indices(self.offsets.len(), chunk_length)
+    }
+
+    fn count(&mut self) -> Result<usize, Error> {
+        Ok(self.offsets.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::process;
+
+    #[derive(Abomonation, Debug, Deserialize, Clone, PartialEq)]
+    struct TestStruct {
+        name: String,
+        value: u64,
+    }
+
+    fn write_test_file(name: &str, rows: usize) -> String {
+        let path = temp_dir()
+            .join(format!("ml_dataflow_{}_{}.csv", name, process::id()))
+            .to_string_lossy()
+            .into_owned();
+
+        let mut content = "name,value\n".to_owned();
+        for i in 0..rows {
+            content += &format!("\"row\n{}\",{}\n", i, i);
+        }
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn options() -> CsvProviderOptions {
+        CsvProviderOptions {
+            has_headers: true,
+            delimiter: b',',
+        }
+    }
+
+    #[test]
+    fn seek_to_records() {
+        let path = write_test_file("indexed", 50);
+        let mut provider =
+            IndexedCsvProvider::try_from(IndexedCsvFileProviderSpec::new(path.clone(), options()))
+                .unwrap();
+
+        assert_eq!(<DataSource<Vec<TestStruct>>>::count(&mut provider).unwrap(), 50);
+
+        let slice: Vec<TestStruct> = provider.slice(IntSliceIndex::new(20, 3)).unwrap();
+        assert_eq!(
+            slice.iter().map(|row| row.value).collect::<Vec<_>>(),
+            vec![20, 21, 22]
+        );
+        assert_eq!(slice[0].name, "row\n20");
+
+        let selected: Vec<TestStruct> = provider.select(&[49, 3, 4, 0]).unwrap();
+        assert_eq!(
+            selected.iter().map(|row| row.value).collect::<Vec<_>>(),
+            vec![49, 3, 4, 0]
+        );
+
+        assert!(<DataSource<Vec<TestStruct>>>::slice(&mut provider, IntSliceIndex::new(45, 6)).is_err());
+        remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn sidecar_index() {
+        let path = write_test_file("sidecar", 10);
+        let spec = IndexedCsvFileProviderSpec::new(path.clone(), options()).cache_index();
+
+        let mut provider = IndexedCsvProvider::try_from(spec.clone()).unwrap();
+        let first: Vec<TestStruct> = provider.all().unwrap();
+        assert!(fs::metadata(spec.index_path()).is_ok());
+
+        let mut provider = IndexedCsvProvider::try_from(spec.clone()).unwrap();
+        let second: Vec<TestStruct> = provider.all().unwrap();
+        assert_eq!(first, second);
+
+        // a changed file invalidates the cached index
+        write_test_file("sidecar", 12);
+        let mut provider = IndexedCsvProvider::try_from(spec.clone()).unwrap();
+        assert_eq!(<DataSource<Vec<TestStruct>>>::count(&mut provider).unwrap(), 12);
+
+        remove_file(spec.index_path()).unwrap();
+        remove_file(path).unwrap();
+    }
+}
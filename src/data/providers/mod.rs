@@ -1,12 +1,16 @@
 use data::serialization::*;
+use data::TrainingData;
 use failure::Error;
 use num_traits::PrimInt;
 use std::convert::TryFrom;
 use timely::{Data, ExchangeData};
 
 pub mod array;
+pub mod columnar;
 pub mod csv;
 pub mod csv_stream;
+pub mod csv_training;
+pub mod indexed_csv;
 pub mod operators;
 
 /// Trait representing a collection of data that can be retrieved from a `DataSource`. The collection
@@ -29,6 +33,10 @@ impl<T: Data> IndexableData for Vec<T> {
     type SliceIndex = IntSliceIndex<usize>;
 }
 
+impl<T: Data, L: Data> IndexableData for TrainingData<T, L> {
+    type SliceIndex = IntSliceIndex<usize>;
+}
+
 /// Specifies the data needed to create a `DataSource` in a serializable struct that can be sent to
 /// other workers. Types that implement this trait can be converted into their respective
 /// `DataSource` using `try_from`/`try_into`.
@@ -95,3 +103,20 @@ impl<T: PrimInt> IntSliceIndex<T> {
         IntSliceIndex { start, length }
     }
 }
+
+/// Splits `len` items into consecutive chunks of `chunk_length` items. The last chunk
+/// contains the remaining items and may be shorter. Fails for chunks of length zero.
+pub(crate) fn contiguous_chunk_indices(
+    len: usize,
+    chunk_length: usize,
+) -> Result<Box<Iterator<Item = IntSliceIndex<usize>>>, Error> {
+    if chunk_length == 0 {
+        return Err(format_err!("Chunks must contain at least one item"));
+    }
+    let num_chunks = (len + chunk_length - 1) / chunk_length;
+
+    Ok(Box::new((0..num_chunks).map(move |i| {
+        let start = i * chunk_length;
+        IntSliceIndex::new(start, chunk_length.min(len - start))
+    })))
+}
@@ -2,7 +2,7 @@ use std::collections::{hash_map::Entry, HashMap};
 use super::*;
 use timely::dataflow::{
     channels::pact::Pipeline,
-    operators::Operator, Scope,
+    operators::{generic::source, Exchange, Map, Operator}, Scope,
     Stream,
 };
 use std::convert::TryInto;
@@ -61,3 +61,102 @@ impl<Ps: DataSourceSpec<Items>, Items: IndexableData, S: Scope> FetchItems<Ps, I
         })
     }
 }
+
+pub trait LoadPartitioned<S: Scope> {
+    /// Loads the data from a `DataSource` into the dataflow, distributing its chunks evenly
+    /// between all workers. The chunk indices are only determined once, on the first worker,
+    /// and each worker then fetches only the chunks it was assigned.
+    fn load_partitioned<Ps, Items>(&self, provider_spec: Ps, chunk_length: usize) -> Stream<S, Items>
+    where
+        Ps: DataSourceSpec<Items>,
+        Items: IndexableData,
+        Ps::Provider: DataSource<Items>;
+}
+
+impl<S: Scope> LoadPartitioned<S> for S {
+    fn load_partitioned<Ps, Items>(&self, provider_spec: Ps, chunk_length: usize) -> Stream<S, Items>
+    where
+        Ps: DataSourceSpec<Items>,
+        Items: IndexableData,
+        Ps::Provider: DataSource<Items>,
+    {
+        let worker = self.index();
+        let peers = self.peers() as u64;
+
+        let index_spec = provider_spec.clone();
+        let slice_indices = source(self, "ChunkIndices", move |capability| {
+            let mut cap = Some(capability);
+            move |output| {
+                if let Some(cap) = cap.take() {
+                    if worker == 0 {
+                        let mut provider = index_spec.to_provider().expect("Failed to create DataProvider");
+                        let chunk_indices = provider.chunk_indices(chunk_length).expect("Failed to get chunk indices");
+
+                        let mut session = output.session(&cap);
+                        for (chunk_num, slice_index) in chunk_indices.enumerate() {
+                            session.give((chunk_num as u64, slice_index));
+                        }
+                    }
+                }
+            }
+        })
+            // assign the chunks to the workers round-robin
+            .exchange(move |&(chunk_num, _)| chunk_num % peers)
+            .map(|(_, slice_index)| slice_index);
+
+        // every worker needs its own instance of the spec to create a provider
+        let provider_specs = source(self, "ProviderSpec", move |capability| {
+            let mut cap = Some(capability);
+            let mut spec = Some(provider_spec);
+            move |output| {
+                if let (Some(cap), Some(spec)) = (cap.take(), spec.take()) {
+                    output.session(&cap).give(spec);
+                }
+            }
+        });
+
+        FetchItems::<Ps, Items, S>::fetch_items(&slice_indices, provider_specs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::providers::array::ArrayProviderSpec;
+    use ndarray::prelude::*;
+    use std::sync::{Arc, Mutex};
+    use timely::dataflow::operators::Inspect;
+    use timely_communication::initialize::Configuration;
+
+    #[test]
+    fn load_partitioned() {
+        let loaded = Arc::new(Mutex::new(Vec::new()));
+        let loaded_by_workers = loaded.clone();
+
+        ::timely::execute(Configuration::Process(2), move |root| {
+            let worker = root.index();
+            let loaded = loaded_by_workers.clone();
+            root.dataflow::<u64, _, _>(|scope| {
+                let data = Array2::from_shape_fn((10, 2), |(row, _)| row);
+                scope
+                    .load_partitioned::<_, AbomonableArray2<usize>>(ArrayProviderSpec::new(data), 3)
+                    .inspect(move |chunk| {
+                        let rows = chunk.view().column(0).to_vec();
+                        loaded.lock().unwrap().push((worker, rows));
+                    });
+            });
+        }).expect("Execute dataflow");
+
+        let mut loaded = loaded.lock().unwrap().clone();
+        loaded.sort();
+        assert_eq!(
+            loaded,
+            vec![
+                (0, vec![0, 1, 2]),
+                (0, vec![6, 7, 8]),
+                (1, vec![3, 4, 5]),
+                (1, vec![9]),
+            ]
+        );
+    }
+}
@@ -1,8 +1,17 @@
 use data::dataflow::random::params::{NormalParams, UniformParams};
+use data::dataflow::ApplyLatest;
+use data::preprocessing::{MapFeatures, Transformer};
+use data::serialization::*;
+use data::TrainingData;
+use models::decision_tree::classification::histogram::Histogram;
+use models::decision_tree::histogram_generics::{BaseHistogram, HistogramSetItem};
+use ndarray::prelude::*;
 use probability::distribution::{Gaussian, Inverse};
 use std::cmp::Ordering;
 use std::f64::INFINITY;
 use std::f64::NEG_INFINITY;
+use timely::dataflow::{Scope, Stream};
+use timely::Data;
 
 #[derive(Debug, Clone)]
 pub struct NormalQuantizer {
@@ -82,3 +91,262 @@ impl UniformQuantizer {
             .0 as i64 - 1
     }
 }
+
+/// Quantizer whose ranges contain roughly the same number of samples each. Unlike the
+/// other quantizers, the ranges are estimated from the data instead of a known distribution.
+#[derive(Abomonation, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantileQuantizer {
+    thresholds: Vec<f64>,
+}
+
+impl QuantileQuantizer {
+    /// Creates a quantizer from the upper bounds of all but the last range
+    pub fn new(mut thresholds: Vec<f64>) -> Self {
+        thresholds.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Less));
+        thresholds.dedup();
+        QuantileQuantizer { thresholds }
+    }
+
+    /// Creates a quantizer with `steps` ranges of equal frequency, estimated from a
+    /// histogram of the samples. If the histogram contains less than two distinct
+    /// values, all samples are quantized to `0`.
+    pub fn from_histogram(histogram: &Histogram<f64>, steps: usize) -> Self {
+        Self::new(histogram.uniform(steps).unwrap_or_default())
+    }
+
+    /// Number of distinct values that samples are quantized to
+    pub fn steps(&self) -> usize {
+        self.thresholds.len() + 1
+    }
+
+    pub fn quantize(&self, num: f64) -> i64 {
+        self.thresholds
+            .binary_search_by(|&threshold| {
+                if threshold < num {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            })
+            .unwrap_err() as i64
+    }
+}
+
+/// Fits a `QuantileQuantizer` for each feature from streaming histograms with the
+/// given number of bins
+#[derive(Abomonation, Debug, Clone, Copy)]
+pub struct QuantileQuantization {
+    steps: usize,
+    histogram_bins: usize,
+}
+
+impl QuantileQuantization {
+    pub fn new(steps: usize, histogram_bins: usize) -> Self {
+        assert!(steps > 0, "At least one step is required");
+        QuantileQuantization {
+            steps,
+            histogram_bins,
+        }
+    }
+}
+
+impl Transformer<f64> for QuantileQuantization {
+    /// Histogram of the values of each feature
+    type Statistics = Vec<Histogram<f64>>;
+    type Fitted = Vec<QuantileQuantizer>;
+
+    fn statistics(&self, x: ArrayView2<f64>) -> Vec<Histogram<f64>> {
+        x.gencolumns()
+            .into_iter()
+            .map(|column| {
+                let mut histogram = Histogram::new(self.histogram_bins);
+                for &v in column {
+                    histogram.insert(v, 1);
+                }
+                histogram
+            })
+            .collect()
+    }
+
+    fn merge(
+        &self,
+        mut first: Vec<Histogram<f64>>,
+        second: Vec<Histogram<f64>>,
+    ) -> Vec<Histogram<f64>> {
+        for (histogram, other) in first.iter_mut().zip(second) {
+            histogram.merge(other);
+        }
+        first
+    }
+
+    fn fit(&self, histograms: &Vec<Histogram<f64>>) -> Vec<QuantileQuantizer> {
+        histograms
+            .iter()
+            .map(|histogram| QuantileQuantizer::from_histogram(histogram, self.steps))
+            .collect()
+    }
+}
+
+/// Items whose features can be quantized to discrete values
+pub trait QuantizeItem: Data {
+    type Quantized: Data;
+
+    fn quantize(self, quantizers: &[QuantileQuantizer]) -> Self::Quantized;
+}
+
+fn quantize_features(x: ArrayView2<f64>, quantizers: &[QuantileQuantizer]) -> Array2<i64> {
+    assert_eq!(x.cols(), quantizers.len(), "Number of features must match");
+    let mut quantized = Array2::zeros(x.dim());
+    for (mut column, (x_column, quantizer)) in quantized
+        .gencolumns_mut()
+        .into_iter()
+        .zip(x.gencolumns().into_iter().zip(quantizers))
+    {
+        for (q, &v) in column.iter_mut().zip(x_column.iter()) {
+            *q = quantizer.quantize(v);
+        }
+    }
+    quantized
+}
+
+impl MapFeatures for Vec<QuantileQuantizer> {
+    type Input = f64;
+    type Output = i64;
+
+    fn map_features(&self, x: ArrayView2<f64>) -> Array2<i64> {
+        quantize_features(x, self)
+    }
+}
+
+impl QuantizeItem for AbomonableArray2<f64> {
+    type Quantized = AbomonableArray2<i64>;
+
+    fn quantize(self, quantizers: &[QuantileQuantizer]) -> AbomonableArray2<i64> {
+        quantize_features(self.view(), quantizers).into()
+    }
+}
+
+impl<L: Data> QuantizeItem for TrainingData<f64, L> {
+    type Quantized = TrainingData<i64, L>;
+
+    fn quantize(self, quantizers: &[QuantileQuantizer]) -> TrainingData<i64, L> {
+        TrainingData {
+            x: quantize_features(self.x(), quantizers).into(),
+            y: self.y,
+            weights: self.weights,
+        }
+    }
+}
+
+/// Extension trait for streams of items with continuous features
+pub trait QuantizeFeatures<S: Scope, D: QuantizeItem> {
+    /// Quantizes the features of all items with the latest quantizers, e.g. fitted
+    /// with `fit_transformer` and a `QuantileQuantization`
+    fn quantize(&self, quantizers: &Stream<S, Vec<QuantileQuantizer>>) -> Stream<S, D::Quantized>;
+}
+
+impl<S: Scope, D: QuantizeItem> QuantizeFeatures<S, D> for Stream<S, D> {
+    fn quantize(&self, quantizers: &Stream<S, Vec<QuantileQuantizer>>) -> Stream<S, D::Quantized> {
+        quantizers.apply_latest(self, |_time, quantizers, data| data.quantize(quantizers))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::preprocessing::TransformFeatures;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+
+    #[test]
+    fn quantize_thresholds() {
+        let quantizer = QuantileQuantizer::new(vec![2., 1., 2., 3.]);
+        assert_eq!(quantizer.steps(), 4);
+        let quantized = [0., 1., 1.5, 2., 2.5, 3., 10.]
+            .iter()
+            .map(|&v| quantizer.quantize(v))
+            .collect::<Vec<_>>();
+        assert_eq!(quantized, vec![0, 0, 1, 1, 2, 2, 3]);
+    }
+
+    #[test]
+    fn equal_frequency_steps() {
+        let mut histogram = Histogram::new(50);
+        for i in 0..1000 {
+            // skewed data, for which ranges of equal width would be unbalanced
+            histogram.insert((i as f64).powi(2), 1);
+        }
+        let quantizer = QuantileQuantizer::from_histogram(&histogram, 4);
+        assert_eq!(quantizer.steps(), 4);
+
+        let mut counts = vec![0; 4];
+        for i in 0..1000 {
+            counts[quantizer.quantize((i as f64).powi(2)) as usize] += 1;
+        }
+        for count in counts {
+            assert!(count > 200 && count < 300, "Unbalanced step: {}", count);
+        }
+    }
+
+    #[test]
+    fn constant_feature() {
+        let mut histogram = Histogram::new(10);
+        histogram.insert(5., 3);
+        let quantizer = QuantileQuantizer::from_histogram(&histogram, 4);
+        assert_eq!(quantizer.steps(), 1);
+        assert_eq!(quantizer.quantize(5.), 0);
+    }
+
+    #[test]
+    fn quantize_streams() {
+        let result = ::timely::example(|scope| {
+            let data = (0..4)
+                .map(|part| TrainingData {
+                    x: Array2::from_shape_fn((25, 2), |(row, col)| {
+                        let value = (part * 25 + row) as f64;
+                        if col == 0 {
+                            value
+                        } else {
+                            -value
+                        }
+                    })
+                    .into(),
+                    y: Array1::from_elem(25, part as u64).into(),
+                    weights: None,
+                })
+                .collect::<Vec<_>>()
+                .to_stream(scope);
+
+            let quantizers = data.fit_transformer(&QuantileQuantization::new(4, 20));
+            data.quantize(&quantizers)
+                .map(|quantized| {
+                    quantized
+                        .x()
+                        .outer_iter()
+                        .zip(quantized.y().iter())
+                        .map(|(row, &y)| (row[0], row[1], y))
+                        .collect::<Vec<_>>()
+                })
+                .capture()
+        })
+        .extract();
+
+        let rows = result
+            .into_iter()
+            .flat_map(|(_, data)| data)
+            .flat_map(|rows| rows)
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 100);
+
+        // most samples of each quarter end up in the same step
+        for part in 0..4 {
+            let matching = rows
+                .iter()
+                .filter(|&&(first, second, y)| {
+                    y == part as u64 && first == part && second == 3 - part
+                })
+                .count();
+            assert!(matching >= 20, "Part {}: {} matching rows", part, matching);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use ndarray::prelude::*;
 use std::convert::{From, TryInto};
 
-#[derive(Clone, Abomonation, Eq, PartialEq, Debug, PartialOrd, Ord)]
+#[derive(Clone, Abomonation, Eq, PartialEq, Debug, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct AbomonableArray<A, D> {
     data: Vec<A>,
     strides: D,
@@ -20,6 +20,7 @@ extern crate num_traits;
 extern crate ordered_float;
 extern crate rand;
 extern crate serde;
+extern crate serde_json;
 extern crate timely;
 extern crate timely_communication;
 extern crate vec_map;
@@ -30,7 +31,6 @@ extern crate probability;
 #[cfg_attr(test, macro_use)]
 extern crate approx;
 
-#[cfg(test)]
 #[macro_use]
 extern crate serde_derive;
 
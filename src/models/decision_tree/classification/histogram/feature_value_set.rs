@@ -92,19 +92,25 @@ impl<'a, T: ContinuousValue, L: DiscreteValue> FromData<DecisionTree<T, L>, Trai
             let x = training_data.x();
             let y = training_data.y();
 
-            for (x_row, y_i) in x.outer_iter().zip(y.iter()) {
+            for (row, (x_row, y_i)) in x.outer_iter().zip(y.iter()).enumerate() {
+                // samples without weight would only add empty bins
+                let weight = training_data.weight(row);
+                if weight == 0. {
+                    continue;
+                }
                 let node_index = tree
                     .descend_iter(x_row)
                     .last()
                     .expect("Navigate to leaf node");
                 if let Node::Leaf { label: None } = tree[node_index] {
+                    let weight = T::from(weight).unwrap();
                     let node_histograms =
                         histograms.get_or_insert_with(&node_index, Default::default);
                     for (i_attr, x_i) in x_row.iter().enumerate() {
                         node_histograms
                             .get_or_insert_with(&i_attr, Default::default)
                             .get_or_insert_with(y_i, || BaseHistogram::new(bins))
-                            .insert(*x_i, 1);
+                            .insert_weighted(*x_i, weight);
                     }
                 }
             }
@@ -9,6 +9,10 @@ use models::decision_tree::tree::{DecisionTree, Node, NodeIndex};
 use num_traits::Float;
 use std::cmp::Ordering;
 
+/// Width of the interval that the points of a histogram with a single bin are assumed to be
+/// spread over, relative to the magnitude of the bin center (at least 1)
+const SINGLE_BIN_WIDTH: f64 = 1e-3;
+
 #[derive(Abomonation, Debug, Clone, PartialEq)]
 pub struct Histogram<T: Float> {
     bins: usize,
@@ -29,30 +33,7 @@ impl<T: ContinuousValue> BaseHistogram<T, u64> for Histogram<T> {
 
     /// Insert a new data point into this histogram
     fn insert(&mut self, p: T, count: u64) {
-        let bins = &mut self.data;
-
-        match bins.binary_search_by(|probe| probe.p.partial_cmp(&p).unwrap_or(Ordering::Less)) {
-            Ok(found_index) => bins[found_index].m = bins[found_index].m + T::from(count).unwrap(),
-            Err(insert_at) => {
-                bins.insert(insert_at, bin(p, flt(1.)));
-
-                if bins.len() > self.bins {
-                    // find index of the two closest together bins
-                    let least_diff = bins
-                        .iter()
-                        .zip(bins.iter().skip(1))
-                        .map(|(current, next)| next.p - current.p)
-                        .enumerate()
-                        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-                        .unwrap()
-                        .0;
-
-                    let next_bin = bins[least_diff + 1];
-                    bins[least_diff].merge(&next_bin);
-                    bins.remove(least_diff + 1);
-                }
-            }
-        }
+        self.insert_weighted(p, T::from(count).unwrap())
     }
 
     /// Count the total number of data points in this histogram (over all bins)
@@ -109,6 +90,34 @@ impl<T: ContinuousValue> HistogramSetItem for Histogram<T> {
 }
 
 impl<T: ContinuousValue> Histogram<T> {
+    /// Insert a new data point with the given weight into this histogram
+    pub fn insert_weighted(&mut self, p: T, weight: T) {
+        let bins = &mut self.data;
+
+        match bins.binary_search_by(|probe| probe.p.partial_cmp(&p).unwrap_or(Ordering::Less)) {
+            Ok(found_index) => bins[found_index].m = bins[found_index].m + weight,
+            Err(insert_at) => {
+                bins.insert(insert_at, bin(p, weight));
+
+                if bins.len() > self.bins {
+                    // find index of the two closest together bins
+                    let least_diff = bins
+                        .iter()
+                        .zip(bins.iter().skip(1))
+                        .map(|(current, next)| next.p - current.p)
+                        .enumerate()
+                        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+                        .unwrap()
+                        .0;
+
+                    let next_bin = bins[least_diff + 1];
+                    bins[least_diff].merge(&next_bin);
+                    bins.remove(least_diff + 1);
+                }
+            }
+        }
+    }
+
     /// Estimates the number of points in the interval [-inf, b]
     pub fn sum(&self, b: T) -> T {
         if self.data.is_empty() {
@@ -152,37 +161,82 @@ impl<T: ContinuousValue> Histogram<T> {
         sum + bin_i.m / two
     }
 
-    #[allow(many_single_char_names)]
+    /// Estimates the boundaries that split the data points into `bins` intervals
+    /// containing the same number of points
     pub fn uniform(&self, bins: usize) -> Option<Vec<T>> {
         if self.data.len() <= 1 {
             return None;
         }
 
-        let m = |i: usize| self.data[i].m;
-        let p = |i: usize| self.data[i].p;
-
         let uniform = (1..bins)
             .map(|j| {
-                let s: T = (flt::<T>(j as f64) / flt(bins as f64))
-                    * self.data.iter().map(|b| b.m).sum::<T>();
-                debug_assert!(self.data.len() > 1);
-                let i = (1..self.data.len())
-                    .find(|i| self.sum(p(*i)) > s)
-                    .unwrap_or_else(|| self.data.len() - 1) - 1;
-
-                let z = {
-                    let d = s - self.sum(p(i));
-                    let a = (m(i + 1) - m(i)).max(flt(1.));
-                    let b = flt::<T>(2.) * m(i);
-                    let c = flt::<T>(-2.) * d;
-                    ((b * b - flt::<T>(4.) * a * c).sqrt() - b) / (flt::<T>(2.) * a)
-                };
-                p(i) + (p(i + 1) - p(i)) * z
+                self.quantile(flt::<T>(j as f64) / flt(bins as f64))
+                    .expect("Quantile of non-empty histogram")
             })
             .collect();
         Some(uniform)
     }
 
+    /// Estimates the value below which the ratio `q` of the data points lies
+    #[allow(many_single_char_names)]
+    pub fn quantile(&self, q: T) -> Option<T> {
+        match self.data.len() {
+            0 => return None,
+            1 => return Some(self.data[0].p),
+            _ => (),
+        }
+
+        let m = |i: usize| self.data[i].m;
+        let p = |i: usize| self.data[i].p;
+
+        let s: T = q * self.data.iter().map(|b| b.m).sum::<T>();
+        let i = (1..self.data.len())
+            .find(|i| self.sum(p(*i)) > s)
+            .unwrap_or_else(|| self.data.len() - 1) - 1;
+
+        let z = {
+            let d = s - self.sum(p(i));
+            // solve a * z^2 + b * z + c = 0 for the ratio z of the way from p(i) to p(i + 1)
+            let a = m(i + 1) - m(i);
+            let b = flt::<T>(2.) * m(i);
+            let c = flt::<T>(-2.) * d;
+            if a.abs() <= T::epsilon() * b {
+                -c / b
+            } else {
+                ((b * b - flt::<T>(4.) * a * c).max(T::zero()).sqrt() - b) / (flt::<T>(2.) * a)
+            }
+        };
+        Some(p(i) + (p(i + 1) - p(i)) * z)
+    }
+
+    /// Estimates the density of the data points at `b`, assuming that the points between
+    /// two neighbouring bins are distributed uniformly. Zero outside of the range of the bins.
+    /// The points of a single bin are spread over a narrow interval around its center.
+    pub fn density(&self, b: T) -> T {
+        if self.data.len() == 1 {
+            let p = self.data[0].p;
+            let width = p.abs().max(T::one()) * flt(SINGLE_BIN_WIDTH);
+            return if (b - p).abs() <= width / flt(2.) {
+                T::one() / width
+            } else {
+                T::zero()
+            };
+        }
+        if self.data.is_empty() || b < self.data[0].p || b > self.data[self.data.len() - 1].p {
+            return T::zero();
+        }
+
+        let i = self
+            .data
+            .iter()
+            .rposition(|bin| bin.p <= b)
+            .unwrap()
+            .min(self.data.len() - 2);
+        let (bin_i, bin_i_next) = (self.data[i], self.data[i + 1]);
+        let total: T = self.data.iter().map(|bin| bin.m).sum();
+        (bin_i.m + bin_i_next.m) / flt(2.) / (bin_i_next.p - bin_i.p) / total
+    }
+
     pub fn candidate_splits(&self) -> Vec<T> {
         if self.data.len() > 1 {
             self.uniform(self.bins).unwrap()
@@ -345,20 +399,42 @@ fn bin<T: Float>(p: T, m: T) -> Bin<T> {
 
 impl<T: ContinuousValue, L: DiscreteValue> FindNodeLabel<L> for FeatureValueHistogramSet<T, L> {
     fn find_node_label(&self, node: &NodeIndex) -> Option<L> {
-        let histograms = self.get(node)?.into_iter().map(|(_k, h)| h).summarize()?;
+        self.find_node_label_weights(node)?
+            .into_iter()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Less))
+            .map(|(label, _)| label)
+    }
+}
 
-        histograms
+impl<T: ContinuousValue, L: DiscreteValue> FindNodeLabelWeights<L>
+    for FeatureValueHistogramSet<T, L>
+{
+    /// Total weight of the samples of each label at the node, sorted by label
+    fn find_node_label_weights(&self, node: &NodeIndex) -> Option<Vec<(L, f64)>> {
+        // every data point is inserted once for each attribute, so the merged
+        // histograms contain the mass of each label once per attribute
+        let attributes = self.get(node)?;
+        let n_attributes: T = flt(attributes.into_iter().count() as f64);
+        let label_histograms = attributes.into_iter().map(|(_k, h)| h).summarize()?;
+        let mut weights = label_histograms
             .iter()
-            .map(|(label, h)| (label, h.count()))
-            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Less))
-            .and_then(|most_common| Some(*most_common.0))
+            .map(|(label, h)| {
+                let mass = h.bins().iter().fold(T::zero(), |acc, bin| acc + bin.m);
+                (*label, (mass / n_attributes).to_f64().unwrap())
+            })
+            .collect::<Vec<_>>();
+        weights.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Some(weights)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{bin, Histogram};
+    use super::{bin, FeatureValueHistogramSet, Histogram};
+    use data::TrainingData;
     use models::decision_tree::histogram_generics::*;
+    use models::decision_tree::tree::DecisionTree;
+    use ndarray::prelude::*;
 
     const INPUT: &[f64] = &[23., 19., 10., 16., 36., 2., 9., 32., 30., 45.];
 
@@ -408,6 +484,38 @@ mod test {
         );
     }
 
+    #[test]
+    fn insert_counts_and_weights() {
+        let mut hist: Histogram<f64> = Histogram::new(2);
+        hist.insert(1., 3);
+        hist.insert(1., 2);
+        hist.insert_weighted(4., 0.5);
+        assert!(ulps_eq!(
+            hist,
+            &vec![bin(1., 5.), bin(4., 0.5)].into(),
+            max_ulps = 2,
+        ));
+        assert_eq!(hist.count(), 6);
+    }
+
+    #[test]
+    fn weighted_samples() {
+        let data = TrainingData {
+            x: arr2(&[[1.], [2.], [3.], [4.]]).into(),
+            y: arr1(&[0, 1, 1, 2]).into(),
+            weights: None,
+        }
+        .with_weights(arr1(&[4., 1., 0.5, 0.]));
+        let tree = DecisionTree::<f64, i64>::default();
+        let histograms = FeatureValueHistogramSet::from_data(&tree, &[data], 5);
+
+        assert_eq!(
+            histograms.find_node_label_weights(&tree.root()),
+            Some(vec![(0, 4.), (1, 1.5)])
+        );
+        assert_eq!(histograms.find_node_label(&tree.root()), Some(0));
+    }
+
     #[test]
     fn merge() {
         let mut h1 = [23., 19., 10., 16., 36., 2., 9.].iter().fold(
@@ -460,4 +568,37 @@ mod test {
         ].into();
         println!("Uniform(3): {:?}", h.uniform(3));
     }
+
+    #[test]
+    fn quantile() {
+        let mut h: Histogram<f64> = Histogram::new(20);
+        for i in 0..=100 {
+            h.insert(i as f64, 1);
+        }
+
+        assert!(abs_diff_eq!(h.quantile(0.5).unwrap(), &50., epsilon = 5.));
+        assert!(abs_diff_eq!(h.quantile(0.25).unwrap(), &25., epsilon = 5.));
+        assert!(abs_diff_eq!(h.quantile(0.75).unwrap(), &75., epsilon = 5.));
+        assert_eq!(Histogram::<f64>::new(5).quantile(0.5), None);
+    }
+
+    #[test]
+    fn density() {
+        let mut h: Histogram<f64> = Histogram::new(20);
+        for i in 0..=100 {
+            h.insert(i as f64, 1);
+        }
+
+        assert!(abs_diff_eq!(h.density(50.), &0.01, epsilon = 0.001));
+        assert!(abs_diff_eq!(h.density(5.), &0.01, epsilon = 0.001));
+        assert_eq!(h.density(-1.), 0.);
+        assert_eq!(h.density(101.), 0.);
+
+        let mut single: Histogram<f64> = Histogram::new(20);
+        single.insert(2., 1);
+        single.insert(2., 1);
+        assert!(abs_diff_eq!(single.density(2.), &500., epsilon = 1e-6));
+        assert_eq!(single.density(2.1), 0.);
+        assert_eq!(Histogram::<f64>::new(20).density(2.), 0.);
+    }
 }
@@ -0,0 +1,460 @@
+//! Very Fast Decision Trees (Domingos & Hulten, 2000), which grow incrementally as
+//! training data streams in.
+//!
+//! Unlike `StreamingClassificationTree`, the tree is not rebuilt for every timestamp.
+//! The histograms of the samples arriving at each leaf are kept across timestamps, and a
+//! leaf is split as soon as the Hoeffding bound guarantees with high confidence that the
+//! best split is better than the second best one. For each timestamp, every worker
+//! collects the histograms of its new samples using the current tree, the first worker
+//! merges them into the statistics of the leaves and decides on splits, and the grown
+//! tree is broadcast back to all workers for the next timestamp.
+
+use data::dataflow::ApplyLatest;
+use data::serialization::*;
+use data::TrainingData;
+use fnv::FnvHashMap;
+use models::decision_tree::classification::histogram::FeatureValueHistogramSet;
+use models::decision_tree::histogram_generics::*;
+use models::decision_tree::operators::*;
+use models::decision_tree::split_improvement::SplitImprovement;
+use models::decision_tree::tree::{DecisionTree, DecisionTreeError, NodeIndex, Rule};
+use models::{
+    LabelingModelAttributes, ModelAttributes, ModelError, Predict, PredictSamples, Train,
+};
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::*;
+use timely::dataflow::{Scope, Stream};
+use timely::progress::nested::product::Product;
+use timely::progress::Timestamp;
+use timely::ExchangeData;
+
+/// Supervised model that incrementally grows a classification tree from streaming data.
+/// The training result for each timestamp is the tree grown from all samples up to then.
+#[derive(Abomonation, Clone)]
+pub struct HoeffdingTree<I, T, L> {
+    bins: usize,
+    confidence: f64,
+    tie_threshold: f64,
+    grace_period: u64,
+    impurity_algo: I,
+    _t: PhantomData<T>,
+    _l: PhantomData<L>,
+}
+
+impl<I, T, L> HoeffdingTree<I, T, L>
+where
+    T: ExchangeData + ContinuousValue,
+    L: ExchangeData + DiscreteValue,
+    I: ExchangeData + SplitImprovement<T, L, HistogramData = FeatureValueHistogramSet<T, L>>,
+{
+    /// Creates a new model instance. The impurity measure is expected to be at most 1,
+    /// like `Gini`.
+    pub fn new(bins: usize, impurity_algo: I) -> Self {
+        HoeffdingTree {
+            bins,
+            confidence: 1e-7,
+            tie_threshold: 0.05,
+            grace_period: 200,
+            impurity_algo,
+            _t: PhantomData,
+            _l: PhantomData,
+        }
+    }
+
+    /// Sets the probability of choosing a split that is not the best one
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        assert!(
+            confidence > 0. && confidence < 1.,
+            "Confidence must be between 0 and 1"
+        );
+        self.confidence = confidence;
+        self
+    }
+
+    /// Sets the Hoeffding bound below which the best split is chosen, even if the second
+    /// best split is about as good
+    pub fn tie_threshold(mut self, tie_threshold: f64) -> Self {
+        self.tie_threshold = tie_threshold;
+        self
+    }
+
+    /// Sets the number of new samples that have to arrive at a leaf before it is
+    /// considered for a split again
+    pub fn grace_period(mut self, grace_period: u64) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+}
+
+impl<I, T, L> ModelAttributes for HoeffdingTree<I, T, L>
+where
+    T: ExchangeData + ContinuousValue,
+    L: ExchangeData + DiscreteValue,
+    I: ExchangeData + SplitImprovement<T, L, HistogramData = FeatureValueHistogramSet<T, L>>,
+{
+    type TrainingResult = DecisionTree<T, L>;
+}
+
+impl<I, T, L> LabelingModelAttributes for HoeffdingTree<I, T, L>
+where
+    T: ExchangeData + ContinuousValue,
+    L: ExchangeData + DiscreteValue,
+    I: ExchangeData + SplitImprovement<T, L, HistogramData = FeatureValueHistogramSet<T, L>>,
+{
+    type Predictions = AbomonableArray1<L>;
+    type PredictErr = DecisionTreeError;
+}
+
+/// Upper bound for the difference between the mean of `n` samples of a random variable
+/// with the given range and its true mean, which holds with probability `1 - confidence`
+fn hoeffding_bound(range: f64, confidence: f64, n: u64) -> f64 {
+    (range * range * (1. / confidence).ln() / (2. * n as f64)).sqrt()
+}
+
+/// The best split of each attribute at a leaf as `(improvement, attribute, threshold)`,
+/// sorted by decreasing improvement
+fn attribute_splits<T, L, I>(
+    histograms: &FeatureValueHistogramSet<T, L>,
+    leaf: NodeIndex,
+    impurity_algo: &I,
+) -> Vec<(T, usize, T)>
+where
+    T: ContinuousValue,
+    L: DiscreteValue,
+    I: SplitImprovement<T, L, HistogramData = FeatureValueHistogramSet<T, L>>,
+{
+    let node_histograms = match histograms.get(&leaf) {
+        Some(node_histograms) => node_histograms,
+        None => return vec![],
+    };
+
+    let mut splits = node_histograms
+        .iter()
+        .filter_map(|(attr, attr_histograms)| {
+            // merge the histograms of all labels to get the candidate splits
+            let merged_histograms = attr_histograms
+                .into_iter()
+                .map(|(_key, item)| item)
+                .summarize()?;
+            merged_histograms
+                .candidate_splits()
+                .into_iter()
+                .filter_map(|split| {
+                    let delta = impurity_algo.split_improvement(histograms, leaf, attr, split)?;
+                    Some((delta, attr, split))
+                })
+                .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Less))
+        })
+        .collect::<Vec<_>>();
+    splits.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    splits
+}
+
+/// Leaf statistics and split decisions of a growing Hoeffding tree
+struct LeafStatistics<T: ContinuousValue, L: DiscreteValue> {
+    histograms: FeatureValueHistogramSet<T, L>,
+    /// number of samples at each leaf when it was last considered for a split
+    last_evaluated: FnvHashMap<NodeIndex, u64>,
+}
+
+impl<T: ContinuousValue, L: DiscreteValue> LeafStatistics<T, L> {
+    fn new() -> Self {
+        LeafStatistics {
+            histograms: FeatureValueHistogramSet::default(),
+            last_evaluated: FnvHashMap::default(),
+        }
+    }
+
+    /// Splits the leaves of the tree where the Hoeffding bound allows it.
+    /// Returns the number of split leaves.
+    fn grow<I>(&mut self, tree: &mut DecisionTree<T, L>, model: &HoeffdingTree<I, T, L>) -> usize
+    where
+        I: SplitImprovement<T, L, HistogramData = FeatureValueHistogramSet<T, L>>,
+    {
+        let mut split_leaves = 0;
+        for leaf in tree.unlabeled_leaves() {
+            let weights = match self.histograms.find_node_label_weights(&leaf) {
+                Some(weights) => weights,
+                None => continue,
+            };
+            let n = weights.iter().map(|&(_, weight)| weight).sum::<f64>().round() as u64;
+            let last_evaluated = self.last_evaluated.get(&leaf).cloned().unwrap_or(0);
+            // leaves with a single label can not be improved
+            if n - last_evaluated < model.grace_period || weights.len() < 2 {
+                continue;
+            }
+            self.last_evaluated.insert(leaf, n);
+
+            let splits = attribute_splits(&self.histograms, leaf, &model.impurity_algo);
+            let (best_delta, attr, threshold) = match splits.first() {
+                Some(&best) => best,
+                None => continue,
+            };
+            let second_delta = splits.get(1).map_or(T::zero(), |second| second.0);
+            let bound = hoeffding_bound(1., model.confidence, n);
+            let difference = (best_delta - second_delta).to_f64().unwrap();
+
+            if best_delta > T::zero() && (difference > bound || bound < model.tie_threshold) {
+                debug!(
+                    "Splitting leaf {:?} with attribute {:?} < {:?} after {} samples",
+                    leaf, attr, threshold, n
+                );
+                let label = self.histograms.find_node_label(&leaf);
+                tree.split(leaf, Rule::threshold(attr, threshold), label);
+                tree.set_label_weights(leaf, weights);
+
+                // the new leaves start collecting statistics from scratch
+                if let Some(node_histograms) = self.histograms.get_mut(&leaf) {
+                    *node_histograms = Default::default();
+                }
+                self.last_evaluated.remove(&leaf);
+                split_leaves += 1;
+            }
+        }
+        split_leaves
+    }
+
+    /// Copy of the tree with each leaf labeled with the most common label of its samples.
+    /// Leaves without samples predict the label of their parent.
+    fn labeled(&self, tree: &DecisionTree<T, L>) -> DecisionTree<T, L> {
+        let mut labeled = tree.clone();
+        for leaf in tree.unlabeled_leaves() {
+            if let Some(label) = self.histograms.find_node_label(&leaf) {
+                labeled.label(leaf, label);
+                if let Some(weights) = self.histograms.find_node_label_weights(&leaf) {
+                    labeled.set_label_weights(leaf, weights);
+                }
+            }
+        }
+        labeled
+    }
+}
+
+/// Emits the current tree whenever training data arrives. The tree is replaced by the
+/// grown trees of earlier timestamps, which arrive through the `grown_trees` stream.
+fn current_trees<S, TsOuter, T, L>(
+    triggers: &Stream<S, ()>,
+    grown_trees: &Stream<S, DecisionTree<T, L>>,
+) -> Stream<S, DecisionTree<T, L>>
+where
+    S: Scope<Timestamp = Product<TsOuter, u64>>,
+    TsOuter: Timestamp,
+    T: ExchangeData,
+    L: ExchangeData,
+{
+    triggers.binary_frontier(
+        grown_trees,
+        Pipeline,
+        Pipeline,
+        "HoeffdingTreeState",
+        |_, _| {
+            let mut tree = DecisionTree::default();
+            let mut pending: Vec<Capability<Product<TsOuter, u64>>> = Vec::new();
+
+            move |in_triggers, in_trees, out| {
+                in_triggers.for_each(|cap, _| {
+                    if !pending.iter().any(|pending_cap| pending_cap.time() == cap.time()) {
+                        pending.push(cap.retain());
+                    }
+                });
+                in_trees.for_each(|_, trees| {
+                    if let Some(grown_tree) = trees.drain(..).last() {
+                        tree = grown_tree;
+                    }
+                });
+
+                // wait until the trees of all earlier timestamps were received
+                let trees_frontier = in_trees.frontier().frontier();
+                for cap in &pending {
+                    let outer = &cap.time().outer;
+                    if trees_frontier
+                        .iter()
+                        .all(|time| !time.outer.less_than(outer))
+                    {
+                        out.session(cap).give(tree.clone());
+                    }
+                }
+                pending.retain(|cap| {
+                    let outer = &cap.time().outer;
+                    trees_frontier
+                        .iter()
+                        .any(|time| time.outer.less_than(outer))
+                });
+            }
+        },
+    )
+}
+
+impl<S, I, T, L> Train<S, HoeffdingTree<I, T, L>> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: ExchangeData + ContinuousValue,
+    L: ExchangeData + DiscreteValue,
+    I: ExchangeData + SplitImprovement<T, L, HistogramData = FeatureValueHistogramSet<T, L>>,
+{
+    fn train(&self, model: &HoeffdingTree<I, T, L>) -> Stream<S, DecisionTree<T, L>> {
+        let mut scope = self.scope();
+        let model = model.clone();
+
+        scope.scoped::<u64, _, _>(|inner| {
+            // grown trees are fed back to grow the tree further in the next timestamps
+            let (loop_handle, grown_trees) = inner.loop_variable(2, 1);
+            let training_data = self.enter(inner);
+
+            let trees = current_trees(&training_data.map(|_| ()), &grown_trees)
+                .collect_histograms::<FeatureValueHistogramSet<T, L>>(
+                    &training_data,
+                    model.bins,
+                    usize::max_value(),
+                )
+                .aggregate_histograms::<FeatureValueHistogramSet<T, L>>()
+                .unary(Pipeline, "GrowHoeffdingTree", move |_, _| {
+                    let mut statistics = LeafStatistics::new();
+                    move |input, output| {
+                        input.for_each(|time, data| {
+                            for (mut tree, histograms) in data.drain(..) {
+                                statistics.histograms.merge(histograms.into());
+                                let split_leaves = statistics.grow(&mut tree, &model);
+                                info!("Split {} leaves", split_leaves);
+
+                                let labeled = statistics.labeled(&tree);
+                                output.session(&time).give((tree, labeled));
+                            }
+                        });
+                    }
+                });
+
+            trees
+                .map(|(tree, _labeled)| tree)
+                .broadcast()
+                .connect_loop(loop_handle);
+            trees.map(|(_tree, labeled)| labeled).broadcast().leave()
+        })
+    }
+}
+
+impl<S, I, T, L> Predict<S, HoeffdingTree<I, T, L>, DecisionTreeError>
+    for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ExchangeData + ContinuousValue,
+    L: ExchangeData + DiscreteValue,
+    I: ExchangeData + SplitImprovement<T, L, HistogramData = FeatureValueHistogramSet<T, L>>,
+{
+    fn predict(
+        &self,
+        _model: &HoeffdingTree<I, T, L>,
+        train_results: Stream<S, DecisionTree<T, L>>,
+    ) -> Stream<S, Result<AbomonableArray1<L>, ModelError<DecisionTreeError>>> {
+        train_results.apply_latest(self, |_time, tree, samples| {
+            tree.predict_samples(&samples).map(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use models::decision_tree::classification::impurity::Gini;
+    use ndarray::prelude::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::progress::timestamp::RootTimestamp;
+
+    /// Samples whose label depends on whether the first feature is at least 5,
+    /// the second feature is noise
+    fn example(samples: usize, offset: usize) -> TrainingData<f64, i64> {
+        let x = Array2::from_shape_fn((samples, 2), |(row, feature)| {
+            let i = row + offset;
+            if feature == 0 {
+                (i * 7 % 100) as f64 / 10.
+            } else {
+                (i * 13 % 17) as f64
+            }
+        });
+        let y = x.column(0).mapv(|v| if v < 5. { 0 } else { 1 });
+        TrainingData {
+            x: x.into(),
+            y: y.into(),
+            weights: None,
+        }
+    }
+
+    #[test]
+    fn bound() {
+        assert!(abs_diff_eq!(
+            hoeffding_bound(1., 1e-7, 300),
+            &(16.118_095_65 / 600f64).sqrt(),
+            epsilon = 1e-8
+        ));
+        assert!(hoeffding_bound(1., 1e-7, 1000) < hoeffding_bound(1., 1e-7, 100));
+    }
+
+    #[test]
+    fn grow_after_grace_period() {
+        let model = HoeffdingTree::<_, f64, i64>::new(20, Gini).grace_period(100);
+        let mut tree = DecisionTree::default();
+        let mut statistics = LeafStatistics::new();
+
+        // not enough samples to split
+        statistics
+            .histograms
+            .merge(FeatureValueHistogramSet::from_data(
+                &tree,
+                &[example(50, 0)],
+                20,
+            ));
+        assert_eq!(statistics.grow(&mut tree, &model), 0);
+
+        statistics
+            .histograms
+            .merge(FeatureValueHistogramSet::from_data(
+                &tree,
+                &[example(250, 50)],
+                20,
+            ));
+        assert_eq!(statistics.grow(&mut tree, &model), 1);
+        assert_eq!(tree.nodes().len(), 3);
+
+        // the new leaves only predict after receiving samples
+        statistics
+            .histograms
+            .merge(FeatureValueHistogramSet::from_data(
+                &tree,
+                &[example(100, 300)],
+                20,
+            ));
+        let predictions: Array1<i64> = statistics
+            .labeled(&tree)
+            .predict_samples(&arr2(&[[1., 3.], [9., 3.]]))
+            .unwrap()
+            .into();
+        assert_eq!(predictions, arr1(&[0, 1]));
+    }
+
+    #[test]
+    fn train_across_timestamps() {
+        let result = ::timely::example(|scope| {
+            let model = HoeffdingTree::new(20, Gini).grace_period(100);
+            vec![(0_u64, example(300, 0)), (1, example(300, 300))]
+                .to_stream(scope)
+                .delay(|&(epoch, _), _| RootTimestamp::new(epoch))
+                .map(|(_, data)| data)
+                .train(&model)
+                .map(|tree: DecisionTree<f64, i64>| {
+                    let predictions = tree
+                        .predict_samples(&arr2(&[[1., 3.], [9., 3.]]))
+                        .map(|p| p.view().to_vec())
+                        .ok();
+                    (tree.nodes().len(), predictions)
+                })
+                .capture()
+        })
+        .extract();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!((result[0].1)[0].0, 3);
+        assert_eq!(result[1].1, vec![(3, Some(vec![0, 1]))]);
+    }
+}
@@ -1,10 +1,12 @@
 #![allow(unknown_lints)]
 
 pub mod histogram;
+mod hoeffding_tree;
 pub mod impurity;
 mod split_leaves;
 mod streaming_classification_tree;
 
+pub use self::hoeffding_tree::HoeffdingTree;
 pub use self::streaming_classification_tree::StreamingClassificationTree;
 
 /*trait Predict<S: Scope, In: Data, P: Data> {
@@ -109,6 +109,9 @@ impl<
                                             .expect("Get node label");
                                         debug!("Splitting tree node {:?} would result in a negative delta; labeling node with {:?}", leaf, label);
                                         tree.label(*leaf, label);
+                                        if let Some(weights) = histograms.find_node_label_weights(leaf) {
+                                            tree.set_label_weights(*leaf, weights);
+                                        }
                                     }
                                 });
                         } else {
@@ -116,6 +119,9 @@ impl<
                                 if let Some(label) = histograms.find_node_label(&leaf) {
                                     debug!("Labeling node {:?} with {:?}", leaf, label);
                                     tree.label(leaf, label);
+                                    if let Some(weights) = histograms.find_node_label_weights(&leaf) {
+                                        tree.set_label_weights(leaf, weights);
+                                    }
                                 }
                             }
                         }
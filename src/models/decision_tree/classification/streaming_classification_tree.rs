@@ -10,7 +10,7 @@ use models::decision_tree::tree::DecisionTree;
 use models::decision_tree::tree::DecisionTreeError;
 use models::LabelingModelAttributes;
 use models::ModelError;
-use models::{ModelAttributes, Predict, PredictSamples, Train};
+use models::{ModelAttributes, Predict, PredictProbabilities, PredictSamples, Train};
 use std::marker::PhantomData;
 use timely::dataflow::operators::*;
 use timely::dataflow::{Scope, Stream};
@@ -45,6 +45,19 @@ where
             _l: PhantomData,
         }
     }
+
+    /// Predicts the probability of each of the given classes for the samples, using the
+    /// latest trained tree. See `PredictProbabilities` for the layout of the result.
+    pub fn predict_probabilities<S: Scope>(
+        &self,
+        samples: &Stream<S, AbomonableArray2<T>>,
+        train_results: Stream<S, DecisionTree<T, L>>,
+        classes: Vec<L>,
+    ) -> Stream<S, Result<AbomonableArray2<f64>, ModelError<DecisionTreeError>>> {
+        train_results.apply_latest(samples, move |_time, tree, samples| {
+            tree.predict_probabilities(&samples, &classes)
+        })
+    }
 }
 
 impl<I, T, L> ModelAttributes for StreamingClassificationTree<I, T, L>
@@ -100,3 +100,8 @@ pub trait FromData<Tree, D> {
 pub trait FindNodeLabel<T> {
     fn find_node_label(&self, node: &NodeIndex) -> Option<T>;
 }
+
+pub trait FindNodeLabelWeights<T> {
+    /// Total weight of the data points with each label that arrived at a node
+    fn find_node_label_weights(&self, node: &NodeIndex) -> Option<Vec<(T, f64)>>;
+}
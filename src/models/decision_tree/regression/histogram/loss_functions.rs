@@ -7,26 +7,22 @@ pub trait TrimmedLad<L> {
     fn trimmed_lad(&self, trim_ratio: L) -> L;
 }
 
-impl<L: ContinuousValue> TrimmedLad<L> for Histogram<L, u64> {
+impl<L: ContinuousValue> TrimmedLad<L> for Histogram<L, f64> {
     fn trimmed_lad(&self, trim_ratio: L) -> L {
         let count_total = self.count();
-        let count_total_f = L::from(count_total).unwrap();
-        
-        if count_total == 0 { return L::zero() }
-
-        // calculate sample count thresholds for the relevant quantiles at 0.5, trim_ratio and 1 - trim_ratio
-        let half_q_threshold = (count_total as f64 * 0.5).round() as u64;
-        let lower_trim_q_threshold = (L::from(count_total).unwrap() * trim_ratio)
-            .round()
-            .to_u64()
-            .unwrap();
+
+        if count_total <= 0. { return L::zero() }
+
+        // calculate (weighted) sample count thresholds for the relevant quantiles at 0.5, trim_ratio and 1 - trim_ratio
+        let half_q_threshold = count_total * 0.5;
+        let lower_trim_q_threshold = count_total * trim_ratio.to_f64().unwrap();
         let upper_trim_q_threshold = count_total - lower_trim_q_threshold;
 
         let mut half_q = None;
         let mut lower_trim_q = None;
         let mut upper_trim_q = None;
 
-        let mut sample_count = 0;
+        let mut sample_count = 0.;
         let mut s = L::zero();
         let mut s_t = L::zero();
 
@@ -64,9 +60,10 @@ impl<L: ContinuousValue> TrimmedLad<L> for Histogram<L, u64> {
         let (lower_q_bin, lower_q_sum) = lower_trim_q.unwrap();
         let (upper_q_bin, upper_q_sum) = upper_trim_q.unwrap();
 
-        let r = count_total / 2 - half_q_sum;
-        let r1 = (count_total_f * trim_ratio).to_u64().unwrap() - lower_q_sum;
-        let r2 = (count_total_f * (L::one() - trim_ratio)).to_u64().unwrap() - upper_q_sum;
+        // the thresholds lie within the quantile bins, up to rounding errors of the weights
+        let r = (half_q_threshold - half_q_sum).min(half_q_bin.1.count);
+        let r1 = (lower_trim_q_threshold - lower_q_sum).min(lower_q_bin.1.count);
+        let r2 = (upper_trim_q_threshold - upper_q_sum).min(upper_q_bin.1.count);
 
         s_t + lower_q_bin.partial_sum(r1) - lower_q_bin.1.sum
             + upper_q_bin.partial_sum(r2)
@@ -75,14 +72,14 @@ impl<L: ContinuousValue> TrimmedLad<L> for Histogram<L, u64> {
 }
 
 pub trait WeightedLoss<L: Float> {
-    fn weighted_loss(&self, h_total: &Histogram<L, u64>, h_left: &Histogram<L, u64>, h_right: &Histogram<L, u64>) -> L;
+    fn weighted_loss(&self, h_total: &Histogram<L, f64>, h_left: &Histogram<L, f64>, h_right: &Histogram<L, f64>) -> L;
 }
 
 #[derive(Clone, Copy, Constructor)]
 pub struct TrimmedLadWeightedLoss<L>(pub L);
 
 impl<L: ContinuousValue> WeightedLoss<L> for TrimmedLadWeightedLoss<L> {
-    fn weighted_loss(&self, h_total: &Histogram<L, u64>, h_left: &Histogram<L, u64>, h_right: &Histogram<L, u64>) -> L {
+    fn weighted_loss(&self, h_total: &Histogram<L, f64>, h_left: &Histogram<L, f64>, h_right: &Histogram<L, f64>) -> L {
         let lad_l = h_left.trimmed_lad(self.0);
         let count_l = L::from(h_left.count()).unwrap();
 
@@ -146,7 +146,7 @@ pub struct SerializableHistogram<L, C>{
     bins: Vec<(L, L, BinData<L, C>)>
 }
 
-impl<L: ContinuousValue, C: ExchangeData + NumAssign> BaseHistogram<L, C> for Histogram<L, C> {
+impl<L: ContinuousValue, C: ExchangeData + NumAssign + ToPrimitive> BaseHistogram<L, C> for Histogram<L, C> {
     type Bin = (BinAddress<L>, BinData<L, C>);
 
     fn new(n_bins: usize) -> Self {
@@ -158,7 +158,9 @@ impl<L: ContinuousValue, C: ExchangeData + NumAssign> BaseHistogram<L, C> for Hi
     }
 
     fn insert(&mut self, y: L, count: C) {
-        let new_bin_data = BinData::init(y);
+        // inserting a value `count` times adds `count * y` to the sum of its bin
+        let sum = y * L::from(count.clone()).unwrap();
+        let new_bin_data = BinData::new(count.clone(), sum);
         let new_bin_address = BinAddress::init(y);
         let mut found = false;
         let before = self
@@ -168,7 +170,7 @@ impl<L: ContinuousValue, C: ExchangeData + NumAssign> BaseHistogram<L, C> for Hi
             .and_then(|(addr, data)| {
                 if addr.right >= new_bin_address.right {
                     data.count += count;
-                    data.sum = data.sum + y;
+                    data.sum = data.sum + sum;
                     found = true;
                     None
                 } else {
@@ -230,7 +232,7 @@ impl<L: ContinuousValue, C: NumAssign + ExchangeData> From<Histogram<L, C>> for
     }
 }
 
-impl<L: ContinuousValue, C: NumAssign + ExchangeData> Into<Histogram<L, C>> for SerializableHistogram<L, C> {
+impl<L: ContinuousValue, C: NumAssign + ExchangeData + ToPrimitive> Into<Histogram<L, C>> for SerializableHistogram<L, C> {
     /// Recover a item from its serializable representation
     fn into(self) -> Histogram<L, C> {
         let mut histogram = Histogram::new(self.n_bins);
@@ -244,7 +246,7 @@ impl<L: ContinuousValue, C: NumAssign + ExchangeData> Into<Histogram<L, C>> for
     }
 }
 
-impl<L: ContinuousValue, C: ExchangeData + NumAssign> HistogramSetItem for Histogram<L, C>
+impl<L: ContinuousValue, C: ExchangeData + NumAssign + ToPrimitive> HistogramSetItem for Histogram<L, C>
 where SerializableHistogram<L, C>: Into<Histogram<L, C>> {
     type Serializable = SerializableHistogram<L, C>;
     
@@ -447,9 +449,15 @@ impl<'a, 'b, L: Float + NumCast, C: Clone + PartialOrd + ToPrimitive + NumAssign
     fn partial_sum(&self, r: C) -> L {
         let (addr, data) = self;
         let r_float = L::from(r.clone()).unwrap();
-        if r < data.count {
+        if r <= C::zero() {
+            L::zero()
+        } else if r < data.count {
             let count_float = L::from(data.count.clone()).unwrap();
             let two = L::from(2.).unwrap();
+            if count_float <= two {
+                // too few samples to estimate how the values are distributed within the bin
+                return data.sum * r_float / count_float;
+            }
             let delta = (data.sum - *addr.right - count_float * *addr.left + *addr.left)
                 / ((count_float - two) * (count_float - L::one()));
             r_float * *addr.left + r_float * (r_float - L::one()) * delta
@@ -532,6 +540,38 @@ mod test {
         )
     }
 
+    #[test]
+    fn insert_counts() {
+        let mut histogram = Histogram::new(3);
+        histogram.insert(1., 3_u64);
+        histogram.insert(1., 2);
+        histogram.insert(2., 2);
+        assert_eq!(
+            histogram.bins().iter().collect::<Vec<_>>(),
+            vec![
+                (&BinAddress::new(1.0, 1.0), &BinData::new(5, 5.0)),
+                (&BinAddress::new(2.0, 2.0), &BinData::new(2, 4.0)),
+            ]
+        )
+    }
+
+    #[test]
+    fn insert_weights() {
+        let mut histogram = Histogram::new(3);
+        histogram.insert(1., 0.5);
+        histogram.insert(2., 1.5);
+        histogram.insert(2., 0.25);
+        assert_eq!(histogram.count(), 2.25);
+        assert_eq!(
+            histogram.bins().iter().collect::<Vec<_>>(),
+            vec![
+                (&BinAddress::new(1.0, 1.0), &BinData::new(0.5, 0.5)),
+                (&BinAddress::new(2.0, 2.0), &BinData::new(1.75, 3.5)),
+            ]
+        );
+        assert_eq!(histogram.median(), Some(2.));
+    }
+
     #[test]
     fn merge() {
         let mut h1 = Histogram::new(3);
@@ -2,14 +2,14 @@ use super::*;
 use models::decision_tree::tree::NodeIndex;
 
 type K = NodeIndex;
-type Inner<T, L> = VecHistogramSet<FnvHistogramSet<T, Histogram<L, u64>>>;
+type Inner<T, L> = VecHistogramSet<FnvHistogramSet<T, Histogram<L, f64>>>;
 
 /// Nested set of histograms that contains
 /// Node -> Attribute Index -> Feature Value -> Histogram with target values
 #[allow(type_complexity)]
 #[derive(Clone)]
 pub struct TargetValueHistogramSet<T: DiscreteValue, L: ContinuousValue>(
-    FnvHistogramSet<NodeIndex, VecHistogramSet<FnvHistogramSet<T, Histogram<L, u64>>>>,
+    FnvHistogramSet<NodeIndex, VecHistogramSet<FnvHistogramSet<T, Histogram<L, f64>>>>,
 );
 
 #[allow(type_complexity)]
@@ -17,7 +17,7 @@ pub struct TargetValueHistogramSet<T: DiscreteValue, L: ContinuousValue>(
 pub struct SerializableTargetValueHistogramSet<T: DiscreteValue, L: ContinuousValue>(
     SerializableFnvHistogramSet<
         NodeIndex,
-        SerializableVecHistogramSet<SerializableFnvHistogramSet<T, SerializableHistogram<L, u64>>>,
+        SerializableVecHistogramSet<SerializableFnvHistogramSet<T, SerializableHistogram<L, f64>>>,
     >,
 );
 
@@ -89,7 +89,12 @@ impl<'a, T: DiscreteValue, L: ContinuousValue> FromData<DecisionTree<T, L>, Trai
             let x = training_data.x();
             let y = training_data.y();
 
-            for (x_row, y_i) in x.outer_iter().zip(y.iter()) {
+            for (row, (x_row, y_i)) in x.outer_iter().zip(y.iter()).enumerate() {
+                // the histograms count the weight of the samples
+                let weight = training_data.weight(row);
+                if weight == 0. {
+                    continue;
+                }
                 let node_index = tree
                     .descend_iter(x_row)
                     .last()
@@ -101,7 +106,7 @@ impl<'a, T: DiscreteValue, L: ContinuousValue> FromData<DecisionTree<T, L>, Trai
                         node_histograms
                             .get_or_insert_with(&i_attr, Default::default)
                             .get_or_insert_with(x_i, || BaseHistogram::new(bins))
-                            .insert(*y_i, 1);
+                            .insert(*y_i, weight);
                     }
                 }
             }
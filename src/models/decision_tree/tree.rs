@@ -3,9 +3,9 @@
 
 #![allow(dead_code)]
 
-use data::serialization::AbomonableArray1;
+use data::serialization::{AbomonableArray1, AbomonableArray2};
 use models::ModelError;
-use models::PredictSamples;
+use models::{PredictProbabilities, PredictSamples};
 use ndarray::prelude::*;
 use ndarray::Zip;
 use std::cmp::Ordering;
@@ -17,9 +17,11 @@ pub enum DecisionTreeError {
     EndedOnUnlabeled,
 }
 
-#[derive(Abomonation, Debug, Clone, Eq, Hash, PartialEq)]
+#[derive(Abomonation, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DecisionTree<T, L> {
     nodes: Vec<Node<T, L>>,
+    /// total weight of the training data points with each label, for each node
+    label_weights: Vec<Vec<(L, f64)>>,
     root: NodeIndex,
 }
 
@@ -28,6 +30,7 @@ impl<T, L> Default for DecisionTree<T, L> {
         let root = Node::Leaf { label: None };
         DecisionTree {
             nodes: vec![root],
+            label_weights: vec![vec![]],
             root: NodeIndex(0),
         }
     }
@@ -57,6 +60,17 @@ impl<T, L> DecisionTree<T, L> {
         }
     }
 
+    /// Stores the total weight of the training data points with each label that arrived at a node
+    pub fn set_label_weights(&mut self, node: NodeIndex, weights: Vec<(L, f64)>) {
+        self.label_weights[node.0] = weights;
+    }
+
+    /// Total weight of the training data points with each label that arrived at a node.
+    /// Empty if the weights are unknown.
+    pub fn label_weights(&self, node: NodeIndex) -> &[(L, f64)] {
+        &self.label_weights[node.0]
+    }
+
     pub fn nodes(&self) -> &[Node<T, L>] {
         self.nodes.as_slice()
     }
@@ -76,6 +90,7 @@ impl<T, L> DecisionTree<T, L> {
     fn new_node(&mut self, node: Node<T, L>) -> NodeIndex {
         let index = NodeIndex(self.nodes.len());
         self.nodes.push(node);
+        self.label_weights.push(vec![]);
         index
     }
 
@@ -119,6 +134,54 @@ where
     }
 }
 
+impl<A, T, L> PredictProbabilities<A, L, DecisionTreeError> for DecisionTree<T, L>
+where
+    for <'a> &'a A: AsArray<'a, T, Ix2>,
+    T: PartialOrd,
+    L: PartialEq,
+{
+    /// The probability of a class is the ratio of training data points with that class in the
+    /// leaf a sample arrives at. If the counts of a leaf are unknown, its label has probability 1.
+    fn predict_probabilities(
+        &self,
+        samples: &A,
+        classes: &[L],
+    ) -> Result<AbomonableArray2<f64>, ModelError<DecisionTreeError>> {
+        let samples: ArrayView2<T> = samples.into();
+        let mut probabilities = Array2::zeros((samples.rows(), classes.len()));
+
+        for (sample, mut sample_probabilities) in
+            samples.outer_iter().zip(probabilities.outer_iter_mut())
+        {
+            let leaf = self.descend_iter(sample).last().expect("Navigate to leaf node");
+            let label = match self[leaf] {
+                Node::Leaf { label: Some(ref label) } => label,
+                _ => {
+                    return Err(ModelError::PredictionFailed(
+                        DecisionTreeError::EndedOnUnlabeled,
+                    ))
+                }
+            };
+
+            let weights = self.label_weights(leaf);
+            let total = weights.iter().map(|(_, weight)| weight).sum::<f64>();
+            if total == 0. {
+                if let Some(class) = classes.iter().position(|class| class == label) {
+                    sample_probabilities[class] = 1.;
+                }
+            } else {
+                for (label, weight) in weights {
+                    if let Some(class) = classes.iter().position(|class| class == label) {
+                        sample_probabilities[class] = weight / total;
+                    }
+                }
+            }
+        }
+
+        Ok(probabilities.into())
+    }
+}
+
 impl<T: PartialOrd, L: Copy> DecisionTree<T, L> {
     pub fn descend<'a, 'b: 'a>(&'b self, value: ArrayView1<'a, T>) -> Option<&L> {
         self.descend_iter(value)
@@ -128,7 +191,9 @@ impl<T: PartialOrd, L: Copy> DecisionTree<T, L> {
             })
             .last()
     }
+}
 
+impl<T: PartialOrd, L> DecisionTree<T, L> {
     pub fn descend_iter<'a, 'b: 'a>(
         &'b self,
         value: ArrayView1<'a, T>,
@@ -175,7 +240,9 @@ impl<T, L> IndexMut<NodeIndex> for DecisionTree<T, L> {
     }
 }
 
-#[derive(Hash, Abomonation, Debug, Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(
+    Hash, Abomonation, Debug, Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize,
+)]
 pub struct NodeIndex(usize);
 
 impl NodeIndex {
@@ -184,7 +251,7 @@ impl NodeIndex {
     }
 }
 
-#[derive(Abomonation, Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Abomonation, Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum Node<T, L> {
     Inner {
         rule: Rule<T>,
@@ -197,13 +264,13 @@ pub enum Node<T, L> {
     },
 }
 
-#[derive(Abomonation, Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Abomonation, Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Rule<T> {
     feature: usize,
     inner: InnerRule<T>,
 }
 
-#[derive(Abomonation, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Abomonation, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum InnerRule<T> {
     Threshold(T),
     Subset(Vec<T>),
@@ -263,6 +330,7 @@ impl<T: PartialOrd, L> Node<T, L> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use data::serialization::AsView;
 
     #[test]
     fn descend_tree() {
@@ -292,4 +360,26 @@ mod test {
 
         assert_eq!("Pure Red", *tree.descend(v_red.view()).unwrap());
     }
+
+    #[test]
+    fn predict_probabilities() {
+        let mut tree = DecisionTree::default();
+        let root = tree.root();
+        let (left, right) = tree.split(root, Rule::threshold(0, 1), None);
+        tree.label(left, 0);
+        tree.set_label_weights(left, vec![(0, 3.), (1, 1.)]);
+        tree.label(right, 2);
+
+        let samples = arr2(&[[0], [1]]);
+        let probabilities = tree.predict_probabilities(&samples, &[0, 1, 2]).unwrap();
+        assert_eq!(
+            probabilities.view(),
+            arr2(&[[0.75, 0.25, 0.], [0., 0., 1.]])
+        );
+
+        let mut unlabeled = DecisionTree::<i32, i32>::default();
+        let root = unlabeled.root();
+        unlabeled.split(root, Rule::threshold(0, 1), None);
+        assert!(unlabeled.predict_probabilities(&samples, &[0]).is_err());
+    }
 }
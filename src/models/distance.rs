@@ -0,0 +1,95 @@
+//! Distance measures between samples, e.g. to find the closest centroid or neighbor.
+
+use ndarray::prelude::*;
+use ndarray_linalg::{Norm, Scalar};
+use std::cmp::Ordering;
+use timely::ExchangeData;
+
+/// A measure of the distance between two samples
+pub trait Distance: ExchangeData {
+    fn distance<T: Scalar>(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> T::Real;
+
+    /// Returns the index of and the distance to the candidate (row) that is closest to
+    /// the point, or `None` if there are no candidates
+    fn closest<T: Scalar>(
+        &self,
+        point: ArrayView1<T>,
+        candidates: ArrayView2<T>,
+    ) -> Option<(usize, T::Real)> {
+        candidates
+            .outer_iter()
+            .map(|candidate| self.distance(point.view(), candidate))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Less))
+    }
+}
+
+/// Euclidean (L2) distance
+#[derive(Abomonation, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Euclidean;
+
+impl Distance for Euclidean {
+    fn distance<T: Scalar>(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> T::Real {
+        (&a - &b).norm_l2()
+    }
+}
+
+/// Manhattan (L1) distance
+#[derive(Abomonation, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Manhattan;
+
+impl Distance for Manhattan {
+    fn distance<T: Scalar>(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> T::Real {
+        (&a - &b).norm_l1()
+    }
+}
+
+/// Chebyshev (maximum) distance
+#[derive(Abomonation, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Chebyshev;
+
+impl Distance for Chebyshev {
+    fn distance<T: Scalar>(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> T::Real {
+        (&a - &b).norm_max()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        let a = arr1(&[1., 2.]);
+        let b = arr1(&[4., -2.]);
+        assert!(abs_diff_eq!(
+            Euclidean.distance(a.view(), b.view()),
+            5.,
+            epsilon = 1e-10
+        ));
+        assert!(abs_diff_eq!(
+            Manhattan.distance(a.view(), b.view()),
+            7.,
+            epsilon = 1e-10
+        ));
+        assert!(abs_diff_eq!(
+            Chebyshev.distance(a.view(), b.view()),
+            4.,
+            epsilon = 1e-10
+        ));
+    }
+
+    #[test]
+    fn closest() {
+        let candidates = arr2(&[[0., 0.], [5., 5.], [1., 1.]]);
+        let (index, distance) = Euclidean
+            .closest(arr1(&[2., 2.]).view(), candidates.view())
+            .unwrap();
+        assert_eq!(index, 2);
+        assert!(abs_diff_eq!(distance, 2f64.sqrt(), epsilon = 1e-10));
+
+        assert!(Euclidean
+            .closest(arr1(&[2., 2.]).view(), Array2::<f64>::zeros((0, 2)).view())
+            .is_none());
+    }
+}
@@ -0,0 +1,96 @@
+use super::mixture::GaussianMixtureParams;
+use super::*;
+
+pub(crate) trait ExpectationStep<S: Scope, D: Data + Debug> {
+    /// Calculates the component responsibilities of the (weighted) points on this worker for
+    /// the incoming mixture parameters and summarizes them in `MixtureStatistics`
+    fn expectation_step<P: WeightedSamples<D>>(
+        &self,
+        points_stream: &Stream<S, P>,
+    ) -> Stream<S, AbomonableMixtureStatistics<D>>;
+}
+
+impl<S: Scope<Timestamp = Product<Ts, usize>>, Ts: Timestamp, D> ExpectationStep<S, D>
+    for Stream<S, GaussianMixtureParams<D>>
+where
+    D: Data + Debug + Scalar + Float + FromPrimitive,
+{
+    fn expectation_step<P: WeightedSamples<D>>(
+        &self,
+        points_stream: &Stream<S, P>,
+    ) -> Stream<S, AbomonableMixtureStatistics<D>> {
+        let worker_index = self.scope().index();
+        self.binary_frontier(
+            &points_stream,
+            Pipeline,
+            Pipeline,
+            "ExpectationStep",
+            |_, _| {
+                let mut point_stash = HashMap::new();
+                let mut params_stash = HashMap::new();
+
+                move |in_params, in_points, out| {
+                    in_params.for_each(|time, data| {
+                        params_stash
+                            .entry(time.retain())
+                            .or_insert_with(Vec::new)
+                            .extend(data.drain(..));
+                    });
+
+                    in_points.for_each(|time, data| {
+                        debug!(
+                            "Worker {} receiving {} data chunks",
+                            worker_index,
+                            data.len()
+                        );
+                        assert_eq!(time.inner, 0);
+                        point_stash
+                            .entry(time.outer.clone())
+                            .or_insert_with(Vec::new)
+                            .extend(data.drain(..));
+                    });
+
+                    let frontiers = [in_params.frontier(), in_points.frontier()];
+                    for (cap, params_list) in &mut params_stash {
+                        // if neither input can produce data at `time`, compute statistics
+                        if frontiers.iter().all(|f| !f.less_equal(cap.time())) {
+                            let mut session = out.session(&cap);
+
+                            for params in params_list.drain(..) {
+                                let means = params.means.view();
+                                let mut stats = MixtureStatistics::new(means.rows(), means.cols());
+
+                                if let Some(points_list) = point_stash.get(&cap.time().outer) {
+                                    for points in points_list {
+                                        let points_view = points.samples();
+                                        let (resp, log_likelihoods) = params
+                                            .responsibilities(&points_view)
+                                            // the M-step keeps the covariances positive definite
+                                            .expect("Calculate responsibilities");
+                                        stats.collect_moments(
+                                            &points_view,
+                                            points.weights(),
+                                            &resp.view(),
+                                            &log_likelihoods.view(),
+                                            params.covariance_type,
+                                        );
+                                    }
+                                }
+
+                                session.give(stats.into());
+                            }
+                        }
+                    }
+
+                    params_stash.retain(|_time, list| !list.is_empty());
+                    // drop the points once no more iterations can occur for their outer time
+                    point_stash.retain(|outer, _| {
+                        in_params
+                            .frontier()
+                            .less_equal(&Product::new(outer.clone(), <usize>::max_value()))
+                    });
+                }
+            },
+        )
+    }
+}
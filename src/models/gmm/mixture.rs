@@ -0,0 +1,181 @@
+use super::GaussianMixtureError;
+use data::serialization::*;
+use ndarray::prelude::*;
+use ndarray_linalg::{into_scalar, Cholesky, Inverse, Scalar, UPLO};
+use num_traits::Float;
+use std::f64::consts::PI;
+
+/// Shape of the covariance matrices of the mixture components
+#[derive(Abomonation, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CovarianceType {
+    /// Each component has its own general covariance matrix
+    Full,
+    /// Each component has its own diagonal covariance matrix
+    Diagonal,
+}
+
+/// Parameters of a fitted Gaussian Mixture Model. This is the training result of
+/// the `GaussianMixture` model.
+#[derive(Abomonation, Clone, Debug)]
+pub struct GaussianMixtureParams<T> {
+    /// Mixing weight of each component (k)
+    pub weights: AbomonableArray1<T>,
+    /// Mean of each component (k x d)
+    pub means: AbomonableArray2<T>,
+    /// Covariance matrix of each component (k x d x d). For diagonal
+    /// covariances, all entries outside of the diagonal are zero.
+    pub covariances: AbomonableArray3<T>,
+    pub covariance_type: CovarianceType,
+    /// Average log-likelihood of the training data under the parameters
+    /// of the previous EM iteration. `None` for the initial parameters.
+    pub log_likelihood: Option<T>,
+}
+
+impl<T: Scalar + Float> GaussianMixtureParams<T> {
+    /// Initial parameters, placing one component with unit covariance and
+    /// uniform weight on each of the given centroids
+    pub fn from_centroids(centroids: ArrayView2<T>, covariance_type: CovarianceType) -> Self {
+        let (k, d) = centroids.dim();
+        let mut covariances = Array3::zeros((k, d, d));
+        for mut covariance in covariances.outer_iter_mut() {
+            covariance.diag_mut().fill(T::one());
+        }
+        let weight = T::one() / into_scalar(k as f64);
+
+        GaussianMixtureParams {
+            weights: Array1::from_elem(k, weight).into(),
+            means: centroids.to_owned().into(),
+            covariances: covariances.into(),
+            covariance_type,
+            log_likelihood: None,
+        }
+    }
+
+    pub fn n_components(&self) -> usize {
+        self.weights.view().len()
+    }
+
+    /// Calculates `ln(w_j) + ln(N(x | mean_j, cov_j))` for every sample `x` and
+    /// component `j`. Returns an `n x k` array.
+    pub fn weighted_log_densities(
+        &self,
+        points: &ArrayView2<T>,
+    ) -> Result<Array2<T>, GaussianMixtureError> {
+        let weights = self.weights.view();
+        let means = self.means.view();
+        let covariances = self.covariances.view();
+        let dims = means.cols();
+        let log_two_pi: T = into_scalar((2. * PI).ln() * dims as f64);
+        let half: T = into_scalar(0.5);
+
+        let mut densities = Array2::zeros((points.rows(), weights.len()));
+        for (j, mut component_densities) in densities.axis_iter_mut(Axis(1)).enumerate() {
+            let mean = means.row(j);
+            let covariance = covariances.subview(Axis(0), j);
+            let log_weight = Float::ln(weights[j]);
+
+            match self.covariance_type {
+                CovarianceType::Diagonal => {
+                    let variances = covariance.diag();
+                    if variances.iter().any(|&v| v <= T::zero()) {
+                        return Err(GaussianMixtureError::SingularCovariance(j));
+                    }
+                    let log_det = variances.iter().fold(T::zero(), |acc, &v| acc + Float::ln(v));
+
+                    for (density, point) in component_densities.iter_mut().zip(points.outer_iter()) {
+                        let mahalanobis = point
+                            .iter()
+                            .zip(mean.iter())
+                            .zip(variances.iter())
+                            .fold(T::zero(), |acc, ((&x, &m), &v)| acc + (x - m) * (x - m) / v);
+                        *density = log_weight - half * (log_two_pi + log_det + mahalanobis);
+                    }
+                }
+                CovarianceType::Full => {
+                    let lower = covariance
+                        .cholesky(UPLO::Lower)
+                        .map_err(|_| GaussianMixtureError::SingularCovariance(j))?;
+                    let precision = covariance
+                        .inv()
+                        .map_err(|_| GaussianMixtureError::SingularCovariance(j))?;
+                    let log_det = lower
+                        .diag()
+                        .iter()
+                        .fold(T::zero(), |acc, &l| acc + Float::ln(l) + Float::ln(l));
+
+                    for (density, point) in component_densities.iter_mut().zip(points.outer_iter()) {
+                        let diff = &point - &mean;
+                        let mahalanobis = diff.dot(&precision.dot(&diff));
+                        *density = log_weight - half * (log_two_pi + log_det + mahalanobis);
+                    }
+                }
+            }
+        }
+
+        Ok(densities)
+    }
+
+    /// Calculates the posterior probability of each component for every sample (`n x k`)
+    /// and the log-likelihood of each sample.
+    pub fn responsibilities(
+        &self,
+        points: &ArrayView2<T>,
+    ) -> Result<(Array2<T>, Array1<T>), GaussianMixtureError> {
+        let mut log_densities = self.weighted_log_densities(points)?;
+        let mut log_likelihoods = Array1::zeros(points.rows());
+
+        for (mut row, log_likelihood) in log_densities
+            .outer_iter_mut()
+            .zip(log_likelihoods.iter_mut())
+        {
+            // log-sum-exp, shifted by the maximum for numerical stability
+            let max = row.iter().fold(Float::neg_infinity(), |acc: T, &x| Float::max(acc, x));
+            let sum = row.iter().fold(T::zero(), |acc, &x| acc + Float::exp(x - max));
+            let log_sum = max + Float::ln(sum);
+
+            row.mapv_inplace(|x| Float::exp(x - log_sum));
+            *log_likelihood = log_sum;
+        }
+
+        Ok((log_densities, log_likelihoods))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn responsibilities_sum_to_one() {
+        let centroids = arr2(&[[0., 0.], [10., 10.]]);
+        let points = arr2(&[[0., 0.5], [9., 10.], [5., 5.]]);
+
+        for &covariance_type in &[CovarianceType::Diagonal, CovarianceType::Full] {
+            let params = GaussianMixtureParams::from_centroids(centroids.view(), covariance_type);
+            let (resp, log_likelihoods) = params.responsibilities(&points.view()).unwrap();
+
+            for row in resp.outer_iter() {
+                assert!(abs_diff_eq!(row.scalar_sum(), &1., epsilon = 1e-10));
+            }
+            assert!(resp[[0, 0]] > 0.99);
+            assert!(resp[[1, 1]] > 0.99);
+            assert!(abs_diff_eq!(resp[[2, 0]], &0.5, epsilon = 1e-10));
+            assert!(log_likelihoods.iter().all(|&l| l < 0.));
+        }
+    }
+
+    #[test]
+    fn diagonal_and_full_agree() {
+        let centroids = arr2(&[[1., 2.], [-1., 0.]]);
+        let points = arr2(&[[0., 0.], [1., 1.], [3., -2.]]);
+
+        let diagonal = GaussianMixtureParams::from_centroids(centroids.view(), CovarianceType::Diagonal);
+        let full = GaussianMixtureParams::from_centroids(centroids.view(), CovarianceType::Full);
+
+        let d1 = diagonal.weighted_log_densities(&points.view()).unwrap();
+        let d2 = full.weighted_log_densities(&points.view()).unwrap();
+        for (a, b) in d1.iter().zip(d2.iter()) {
+            assert!(abs_diff_eq!(a, b, epsilon = 1e-10));
+        }
+    }
+}
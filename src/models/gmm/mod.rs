@@ -0,0 +1,330 @@
+//! Gaussian Mixture Models, fitted with the Expectation-Maximization Algorithm.
+//!
+//! The mixture components are initialized from the centroids of a K-Means run on the
+//! same data. Each EM iteration broadcasts the current parameters to all workers,
+//! which calculate the responsibility-weighted moments of their local points (E-step).
+//! These statistics are summed up on a single worker, where the parameters for the next
+//! iteration are estimated (M-step). When training on `TrainingData`, the moments and the
+//! log-likelihood of each sample are scaled by its sample weight.
+
+use self::expectation::ExpectationStep;
+pub use self::mixture::*;
+use self::statistics::*;
+pub use self::stop_condition::EmConvergenceCriteria;
+use self::stop_condition::MixtureStopCondition;
+use data::dataflow::ApplyLatest;
+use data::serialization::*;
+use data::TrainingData;
+use models::kmeans::{
+    initializers::KMeansInitializer, ConvergenceCriteria, Kmeans, WeightedSamples,
+};
+use models::*;
+use ndarray::ScalarOperand;
+use ndarray_linalg::{into_scalar, Scalar};
+use num_traits::{cast::FromPrimitive, Float, NumAssignOps};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use timely::dataflow::channels::pact::Pipeline;
+use timely::progress::Timestamp;
+use timely::{
+    dataflow::{operators::*, Scope, Stream},
+    progress::nested::product::Product,
+    Data, ExchangeData,
+};
+
+mod expectation;
+mod mixture;
+mod statistics;
+mod stop_condition;
+
+#[derive(Abomonation, Clone)]
+pub struct GaussianMixture<T: Data, Init: KMeansInitializer<T> + Data> {
+    n_components: usize,
+    cols: usize,
+    covariance_type: CovarianceType,
+    end_criteria: EmConvergenceCriteria<T>,
+    reg_covar: T,
+    seed: u64,
+    init: Kmeans<T, Init>,
+}
+
+impl<T: Data + Scalar, Init: Data + KMeansInitializer<T>> GaussianMixture<T, Init> {
+    /// Creates a mixture of `n_components` gaussians over samples with `cols` features.
+    /// The components are initialized by running K-Means for at most 10 iterations.
+    pub fn new(
+        n_components: usize,
+        cols: usize,
+        covariance_type: CovarianceType,
+        end_criteria: EmConvergenceCriteria<T>,
+    ) -> Self {
+        let kmeans_criteria = ConvergenceCriteria {
+            max_iterations: Some(10),
+            min_centroid_change: None,
+        };
+        GaussianMixture {
+            n_components,
+            cols,
+            covariance_type,
+            end_criteria,
+            reg_covar: into_scalar(1e-6),
+            seed: 0,
+            init: Kmeans::new(n_components, cols, kmeans_criteria),
+        }
+    }
+
+    /// Sets the convergence criteria of the K-Means run used for initialization.
+    pub fn kmeans_init(mut self, criteria: ConvergenceCriteria<T>) -> Self {
+        self.init = Kmeans::new(self.n_components, self.cols, criteria).seed(self.seed);
+        self
+    }
+
+    /// Sets the seed for the random choices of the K-Means initialization.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self.init = self.init.seed(seed);
+        self
+    }
+
+    /// Sets the non-negative regularization added to the diagonal of the covariances.
+    pub fn reg_covar(mut self, reg_covar: T) -> Self {
+        self.reg_covar = reg_covar;
+        self
+    }
+}
+
+impl<T: ExchangeData, Init: ExchangeData + KMeansInitializer<T>> ModelAttributes
+    for GaussianMixture<T, Init>
+{
+    type TrainingResult = GaussianMixtureParams<T>;
+}
+
+impl<T: ExchangeData, Init: ExchangeData + KMeansInitializer<T>> LabelingModelAttributes
+    for GaussianMixture<T, Init>
+{
+    /// Membership probabilities of each sample (rows) for each component (columns)
+    type Predictions = AbomonableArray2<T>;
+    type PredictErr = GaussianMixtureError;
+}
+
+#[derive(Fail, Debug, Abomonation, Clone)]
+pub enum GaussianMixtureError {
+    #[fail(display = "Covariance matrix of component {} is not positive definite", _0)]
+    SingularCovariance(usize),
+}
+
+impl<S, T, Init> Train<S, GaussianMixture<T, Init>> for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar + NumAssignOps + ScalarOperand + Float + Debug + FromPrimitive,
+    Init: ExchangeData + KMeansInitializer<T>,
+{
+    fn train(&self, model: &GaussianMixture<T, Init>) -> Stream<S, GaussianMixtureParams<T>> {
+        fit_mixture(model, &self.train(&model.init), self)
+    }
+}
+
+/// Trains on the samples of the training data, weighted by their sample weights
+impl<S, T, L, Init> Train<S, GaussianMixture<T, Init>> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar + NumAssignOps + ScalarOperand + Float + Debug + FromPrimitive,
+    L: Data,
+    Init: ExchangeData + KMeansInitializer<T>,
+{
+    fn train(&self, model: &GaussianMixture<T, Init>) -> Stream<S, GaussianMixtureParams<T>> {
+        fit_mixture(model, &self.train(&model.init), self)
+    }
+}
+
+/// Places the initial components on the K-Means `centroids` and improves them with EM
+/// iterations over the (weighted) `points`
+fn fit_mixture<S, T, Init, P>(
+    model: &GaussianMixture<T, Init>,
+    centroids: &Stream<S, AbomonableArray2<T>>,
+    points: &Stream<S, P>,
+) -> Stream<S, GaussianMixtureParams<T>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar + NumAssignOps + ScalarOperand + Float + Debug + FromPrimitive,
+    Init: ExchangeData + KMeansInitializer<T>,
+    P: WeightedSamples<T>,
+{
+    let covariance_type = model.covariance_type;
+    let reg_covar = model.reg_covar;
+
+    let end_criteria = model.end_criteria.clone();
+    let max_iterations = model
+        .end_criteria
+        .max_iterations
+        .unwrap_or(<usize>::max_value());
+
+    let initial_params = centroids.map(move |centroids| {
+        GaussianMixtureParams::from_centroids(centroids.view(), covariance_type)
+    });
+
+    points.scope().scoped(|inner_scope| {
+        let worker_index = inner_scope.index();
+
+        let (loop_handle, loop_stream) = inner_scope.loop_variable(max_iterations, 1);
+
+        let (done, next_iteration) = initial_params
+            .enter(inner_scope)
+            .concat(&loop_stream)
+            // checks whether the log-likelihood has converged and aborts the loop
+            .mixture_stop_condition(end_criteria);
+
+        next_iteration
+            .broadcast()
+            .expectation_step(&points.enter(inner_scope))
+            .exchange(|_| 0u64)
+            .accumulate_mixture_statistics()
+            .map(move |stats| {
+                debug!(
+                    "Worker {}: Aggregated all responsibilities, estimating new parameters",
+                    worker_index
+                );
+                MixtureStatistics::from(stats).parameter_estimate(covariance_type, reg_covar)
+            })
+            .connect_loop(loop_handle);
+
+        done.leave()
+    })
+}
+
+impl<S, T, Init> Predict<S, GaussianMixture<T, Init>, GaussianMixtureError>
+    for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar + Float,
+    Init: ExchangeData + KMeansInitializer<T>,
+{
+    fn predict(
+        &self,
+        _model: &GaussianMixture<T, Init>,
+        train_results: Stream<S, GaussianMixtureParams<T>>,
+    ) -> Stream<S, Result<AbomonableArray2<T>, ModelError<GaussianMixtureError>>> {
+        train_results.apply_latest(self, |_time, params, samples| {
+            params.predict_samples(&samples)
+        })
+    }
+}
+
+impl<T: Data + Scalar + Float> PredictSamples<AbomonableArray2<T>, AbomonableArray2<T>, GaussianMixtureError>
+    for GaussianMixtureParams<T>
+{
+    fn predict_samples(
+        &self,
+        samples: &AbomonableArray2<T>,
+    ) -> Result<AbomonableArray2<T>, ModelError<GaussianMixtureError>> {
+        self.responsibilities(&samples.view())
+            .map(|(responsibilities, _)| responsibilities.into())
+            .map_err(ModelError::PredictionFailed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::dataflow::ExtractUnordered;
+    use models::kmeans::initializers::RandomSample;
+    use ndarray::prelude::*;
+
+    #[test]
+    fn fit_separated_clusters() {
+        let points = arr2(&[
+            [0., 0.2],
+            [0.5, 0.],
+            [0., -0.4],
+            [-0.3, 0.],
+            [5., 5.1],
+            [4.6, 5.],
+            [5., 5.3],
+            [5.2, 4.8],
+        ]);
+        let end_criteria = <EmConvergenceCriteria<f64>>::default()
+            .limit_iterations(20)
+            .likelihood_change(1e-8);
+        let model =
+            GaussianMixture::<f64, RandomSample>::new(2, 2, CovarianceType::Diagonal, end_criteria);
+
+        let result = ::timely::example(move |scope| {
+            vec![AbomonableArray2::from(points.clone())]
+                .to_stream(scope)
+                .train(&model)
+                .capture()
+        })
+        .extract_unordered();
+
+        assert_eq!(result.len(), 1);
+        let params = &(result[0].1)[0];
+        assert!(params.log_likelihood.unwrap().is_finite());
+
+        let means = params.means.view();
+        let (first, second) = if means[[0, 0]] < means[[1, 0]] {
+            (0, 1)
+        } else {
+            (1, 0)
+        };
+        let expected = [(first, [0.05, -0.05]), (second, [4.95, 5.05])];
+        for &(component, ref mean) in &expected {
+            for (a, b) in means.row(component).iter().zip(mean.iter()) {
+                assert!(abs_diff_eq!(a, b, epsilon = 1e-6));
+            }
+        }
+        for weight in params.weights.view().iter() {
+            assert!(abs_diff_eq!(weight, &0.5, epsilon = 1e-6));
+        }
+    }
+
+    #[test]
+    fn fit_weighted_clusters() {
+        let points = arr2(&[
+            [0., 0.2],
+            [0.5, 0.],
+            [0., -0.4],
+            [-0.3, 0.],
+            [5., 5.1],
+            [4.6, 5.],
+            [5., 5.3],
+            [5.2, 4.8],
+        ]);
+        let weights = arr1(&[3., 3., 3., 3., 1., 1., 0.5, 1.5]);
+        let end_criteria = <EmConvergenceCriteria<f64>>::default()
+            .limit_iterations(20)
+            .likelihood_change(1e-8);
+        let model =
+            GaussianMixture::<f64, RandomSample>::new(2, 2, CovarianceType::Diagonal, end_criteria);
+
+        let result = ::timely::example(move |scope| {
+            let data = TrainingData {
+                x: points.clone().into(),
+                y: Array1::<usize>::zeros(8).into(),
+                weights: None,
+            };
+            vec![data.with_weights(weights.clone())]
+                .to_stream(scope)
+                .train(&model)
+                .capture()
+        })
+        .extract_unordered();
+
+        assert_eq!(result.len(), 1);
+        let params = &(result[0].1)[0];
+        let (means, mixing) = (params.means.view(), params.weights.view());
+        let (first, second) = if means[[0, 0]] < means[[1, 0]] {
+            (0, 1)
+        } else {
+            (1, 0)
+        };
+        let expected = [
+            (first, [0.05, -0.05], 0.75),
+            (second, [4.975, 4.9875], 0.25),
+        ];
+        for &(component, ref mean, weight) in &expected {
+            for (a, b) in means.row(component).iter().zip(mean.iter()) {
+                assert!(abs_diff_eq!(a, b, epsilon = 1e-6));
+            }
+            assert!(abs_diff_eq!(mixing[component], &weight, epsilon = 1e-6));
+        }
+    }
+}
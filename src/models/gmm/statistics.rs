@@ -0,0 +1,391 @@
+use super::mixture::{CovarianceType, GaussianMixtureParams};
+use data::serialization::*;
+use ndarray::prelude::*;
+use ndarray::Zip;
+use ndarray_linalg::{into_scalar, Cholesky, Scalar, UPLO};
+use num_traits::{cast::FromPrimitive, Float};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use timely::dataflow::{channels::pact::Pipeline, operators::generic::Operator, Scope, Stream};
+use timely::Data;
+
+/// Sufficient statistics of the E-step, from which the parameters
+/// of the next EM iteration are estimated.
+#[derive(Clone, Debug)]
+pub(crate) struct MixtureStatistics<T: Debug> {
+    pub responsibility_sums: Array1<T>,
+    pub weighted_sums: Array2<T>,
+    pub weighted_products: Array3<T>,
+    pub log_likelihood: T,
+    pub total_weight: T,
+}
+
+#[derive(Abomonation, Clone)]
+pub(crate) struct AbomonableMixtureStatistics<T: Debug> {
+    pub responsibility_sums: AbomonableArray1<T>,
+    pub weighted_sums: AbomonableArray2<T>,
+    pub weighted_products: AbomonableArray3<T>,
+    pub log_likelihood: T,
+    pub total_weight: T,
+}
+
+impl<T: Data + Debug> From<AbomonableMixtureStatistics<T>> for MixtureStatistics<T> {
+    fn from(from: AbomonableMixtureStatistics<T>) -> Self {
+        MixtureStatistics {
+            responsibility_sums: from.responsibility_sums.into(),
+            weighted_sums: from.weighted_sums.into(),
+            weighted_products: from.weighted_products.into(),
+            log_likelihood: from.log_likelihood,
+            total_weight: from.total_weight,
+        }
+    }
+}
+
+impl<T: Data + Debug> From<MixtureStatistics<T>> for AbomonableMixtureStatistics<T> {
+    fn from(from: MixtureStatistics<T>) -> Self {
+        AbomonableMixtureStatistics {
+            responsibility_sums: from.responsibility_sums.into(),
+            weighted_sums: from.weighted_sums.into(),
+            weighted_products: from.weighted_products.into(),
+            log_likelihood: from.log_likelihood,
+            total_weight: from.total_weight,
+        }
+    }
+}
+
+impl<T> MixtureStatistics<T>
+where
+    T: Scalar + Float + FromPrimitive + Debug,
+{
+    pub fn new(components: usize, cols: usize) -> Self {
+        MixtureStatistics {
+            responsibility_sums: Array1::zeros(components),
+            weighted_sums: Array2::zeros((components, cols)),
+            weighted_products: Array3::zeros((components, cols, cols)),
+            log_likelihood: T::zero(),
+            total_weight: T::zero(),
+        }
+    }
+
+    /// Adds the responsibility-weighted moments of the given points, each scaled by the weight
+    /// of its point. Without weights, each point has a weight of 1. For diagonal covariances,
+    /// only the diagonal of the second moments is collected.
+    pub fn collect_moments(
+        &mut self,
+        points: &ArrayView2<T>,
+        weights: Option<ArrayView1<f64>>,
+        responsibilities: &ArrayView2<T>,
+        log_likelihoods: &ArrayView1<T>,
+        covariance_type: CovarianceType,
+    ) {
+        let cols = points.cols();
+        for (i, (point, point_resp)) in points
+            .outer_iter()
+            .zip(responsibilities.outer_iter())
+            .enumerate()
+        {
+            let weight = weights.as_ref().map_or_else(T::one, |weights| {
+                <T as FromPrimitive>::from_f64(weights[i]).expect("Weight of a point")
+            });
+            if weight == T::zero() {
+                continue;
+            }
+            self.log_likelihood = self.log_likelihood + weight * log_likelihoods[i];
+            self.total_weight = self.total_weight + weight;
+
+            for (j, &r) in point_resp.iter().enumerate() {
+                let r = r * weight;
+                self.responsibility_sums[j] = self.responsibility_sums[j] + r;
+
+                let mut sums = self.weighted_sums.row_mut(j);
+                let mut products = self.weighted_products.subview_mut(Axis(0), j);
+                for a in 0..cols {
+                    sums[a] = sums[a] + r * point[a];
+                    match covariance_type {
+                        CovarianceType::Diagonal => {
+                            products[[a, a]] = products[[a, a]] + r * point[a] * point[a];
+                        }
+                        CovarianceType::Full => for b in 0..cols {
+                            products[[a, b]] = products[[a, b]] + r * point[a] * point[b];
+                        },
+                    }
+                }
+            }
+        }
+    }
+
+    /// Add the statistics of another instance to this one
+    pub fn merge(&mut self, other: &MixtureStatistics<T>) {
+        Zip::from(&mut self.responsibility_sums)
+            .and(&other.responsibility_sums)
+            .apply(|a, &b| *a = *a + b);
+        Zip::from(&mut self.weighted_sums)
+            .and(&other.weighted_sums)
+            .apply(|a, &b| *a = *a + b);
+        Zip::from(&mut self.weighted_products)
+            .and(&other.weighted_products)
+            .apply(|a, &b| *a = *a + b);
+        self.log_likelihood = self.log_likelihood + other.log_likelihood;
+        self.total_weight = self.total_weight + other.total_weight;
+    }
+
+    /// M-step: estimate a new set of mixture parameters from the collected statistics.
+    /// `reg_covar` is added to the diagonal of each covariance matrix to keep them positive
+    /// definite. Covariances that are still singular are regularized further.
+    pub fn parameter_estimate(
+        &self,
+        covariance_type: CovarianceType,
+        reg_covar: T,
+    ) -> GaussianMixtureParams<T> {
+        let (components, cols) = self.weighted_sums.dim();
+        let total = if self.total_weight > T::zero() {
+            self.total_weight
+        } else {
+            T::one()
+        };
+        // keeps the weights of components without responsibilities above zero,
+        // so that their log-densities stay finite
+        let min_responsibility: T = <T as Float>::epsilon() * into_scalar(10.);
+
+        let mut weights = Array1::zeros(components);
+        let mut means = Array2::zeros((components, cols));
+        let mut covariances = Array3::zeros((components, cols, cols));
+
+        for j in 0..components {
+            let n_j = self.responsibility_sums[j] + min_responsibility;
+            let mut covariance = covariances.subview_mut(Axis(0), j);
+            weights[j] = n_j;
+
+            // components that no sample is assigned to keep unit covariance
+            if self.responsibility_sums[j] <= T::zero() {
+                covariance.diag_mut().fill(T::one());
+                continue;
+            }
+
+            let mut mean = means.row_mut(j);
+            mean.assign(&self.weighted_sums.row(j));
+            mean.mapv_inplace(|s| s / n_j);
+
+            let products = self.weighted_products.subview(Axis(0), j);
+            for a in 0..cols {
+                match covariance_type {
+                    CovarianceType::Diagonal => {
+                        covariance[[a, a]] = products[[a, a]] / n_j - mean[a] * mean[a];
+                    }
+                    CovarianceType::Full => for b in 0..cols {
+                        covariance[[a, b]] = products[[a, b]] / n_j - mean[a] * mean[b];
+                    },
+                }
+                covariance[[a, a]] = covariance[[a, a]] + reg_covar;
+            }
+            regularize(covariance, covariance_type);
+        }
+
+        let weight_sum = weights.scalar_sum();
+        weights.mapv_inplace(|w| w / weight_sum);
+
+        GaussianMixtureParams {
+            weights: weights.into(),
+            means: means.into(),
+            covariances: covariances.into(),
+            covariance_type,
+            log_likelihood: Some(self.log_likelihood / total),
+        }
+    }
+}
+
+/// Adds increasing values to the diagonal of a covariance matrix until it is positive
+/// definite beyond the rounding errors of its entries. Falls back to unit covariance
+/// if that does not help.
+fn regularize<T: Scalar + Float>(
+    mut covariance: ArrayViewMut2<T>,
+    covariance_type: CovarianceType,
+) {
+    let scale = covariance
+        .diag()
+        .iter()
+        .fold(T::zero(), |scale, &v| Float::max(scale, Float::abs(v)));
+    let tolerance = <T as Float>::epsilon() * scale;
+    let positive_definite = |covariance: ArrayView2<T>| match covariance_type {
+        CovarianceType::Diagonal => covariance.diag().iter().all(|&v| v > T::zero()),
+        CovarianceType::Full => covariance
+            .cholesky(UPLO::Lower)
+            .map(|lower| lower.diag().iter().all(|&l| l * l > tolerance))
+            .unwrap_or(false),
+    };
+    if positive_definite(covariance.view()) {
+        return;
+    }
+
+    let mut reg = <T as Float>::epsilon() * Float::max(scale, T::one());
+    for _ in 0..20 {
+        covariance.diag_mut().mapv_inplace(|v| v + reg);
+        if positive_definite(covariance.view()) {
+            return;
+        }
+        reg = reg * into_scalar(10.);
+    }
+    covariance.fill(T::zero());
+    covariance.diag_mut().fill(T::one());
+}
+
+pub(crate) trait AccumulateMixtureStatistics<G: Scope, T: Data + Debug> {
+    /// Accumulates all incoming `MixtureStatistics` instances into one per timestamp by summing
+    /// them up.
+    fn accumulate_mixture_statistics(&self) -> Stream<G, AbomonableMixtureStatistics<T>>;
+}
+
+impl<S: Scope, T: Scalar + Float + FromPrimitive + Data + Debug> AccumulateMixtureStatistics<S, T>
+    for Stream<S, AbomonableMixtureStatistics<T>>
+{
+    fn accumulate_mixture_statistics(&self) -> Stream<S, AbomonableMixtureStatistics<T>> {
+        let mut accums: HashMap<_, MixtureStatistics<T>> = HashMap::new();
+        self.unary_notify(
+            Pipeline,
+            "AccumulateMixtureStatistics",
+            vec![],
+            move |input, output, notificator| {
+                input.for_each(|time, data| {
+                    for incoming in data.drain(..) {
+                        let incoming = MixtureStatistics::from(incoming);
+                        if let Some(agg) = accums.get_mut(time.time()) {
+                            agg.merge(&incoming);
+                            continue;
+                        }
+                        accums.insert(time.time().clone(), incoming);
+                    }
+                    notificator.notify_at(time.retain());
+                });
+
+                notificator.for_each(|time, _, _| {
+                    if let Some(accum) = accums.remove(&time) {
+                        output.session(&time).give(accum.into());
+                    }
+                });
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn statistics(
+        points: ArrayView2<f64>,
+        responsibilities: ArrayView2<f64>,
+    ) -> MixtureStatistics<f64> {
+        let mut statistics = MixtureStatistics::new(responsibilities.cols(), points.cols());
+        let log_likelihoods = Array1::from_elem(points.rows(), -1.);
+        statistics.collect_moments(
+            &points,
+            None,
+            &responsibilities,
+            &log_likelihoods.view(),
+            CovarianceType::Full,
+        );
+        statistics
+    }
+
+    #[test]
+    fn merge() {
+        let points = arr2(&[[0., 1.], [2., 3.], [4., 1.]]);
+        let responsibilities = arr2(&[[1., 0.], [0.25, 0.75], [0., 1.]]);
+
+        let whole = statistics(points.view(), responsibilities.view());
+        let mut merged = statistics(
+            points.slice(s![..1, ..]),
+            responsibilities.slice(s![..1, ..]),
+        );
+        merged.merge(&statistics(
+            points.slice(s![1.., ..]),
+            responsibilities.slice(s![1.., ..]),
+        ));
+
+        assert_eq!(merged.responsibility_sums, whole.responsibility_sums);
+        assert_eq!(merged.weighted_sums, whole.weighted_sums);
+        assert_eq!(merged.weighted_products, whole.weighted_products);
+        assert_eq!(merged.log_likelihood, -3.);
+        assert_eq!(merged.total_weight, 3.);
+    }
+
+    #[test]
+    fn weighted_points() {
+        let points = arr2(&[[0., 1.], [2., 3.], [4., 1.]]);
+        let responsibilities = arr2(&[[1., 0.], [0.25, 0.75], [0., 1.]]);
+        let log_likelihoods = arr1(&[-1., -2., -3.]);
+
+        let mut weighted = MixtureStatistics::new(2, 2);
+        weighted.collect_moments(
+            &points.view(),
+            Some(arr1(&[2., 0., 1.]).view()),
+            &responsibilities.view(),
+            &log_likelihoods.view(),
+            CovarianceType::Full,
+        );
+
+        // a weight of 2 counts like a duplicated point, a weight of 0 like no point
+        let duplicated = arr2(&[[0., 1.], [0., 1.], [4., 1.]]);
+        let duplicated_resp = arr2(&[[1., 0.], [1., 0.], [0., 1.]]);
+        let mut expected = MixtureStatistics::new(2, 2);
+        expected.collect_moments(
+            &duplicated.view(),
+            None,
+            &duplicated_resp.view(),
+            &arr1(&[-1., -1., -3.]).view(),
+            CovarianceType::Full,
+        );
+
+        assert_eq!(weighted.responsibility_sums, expected.responsibility_sums);
+        assert_eq!(weighted.weighted_sums, expected.weighted_sums);
+        assert_eq!(weighted.weighted_products, expected.weighted_products);
+        assert_eq!(weighted.log_likelihood, expected.log_likelihood);
+        assert_eq!(weighted.total_weight, 3.);
+    }
+
+    #[test]
+    fn empty_component() {
+        let points = arr2(&[[0., 1.], [2., 3.]]);
+        let responsibilities = arr2(&[[1., 0.], [1., 0.]]);
+        let params = statistics(points.view(), responsibilities.view())
+            .parameter_estimate(CovarianceType::Diagonal, 1e-6);
+
+        let weights = params.weights.view();
+        assert!(weights[1] > 0.);
+        assert!(abs_diff_eq!(weights.scalar_sum(), &1., epsilon = 1e-10));
+
+        let densities = params.weighted_log_densities(&points.view()).unwrap();
+        assert!(densities.iter().all(|density| density.is_finite()));
+    }
+
+    #[test]
+    fn singular_covariance() {
+        // points on a line have a singular covariance without regularization
+        let points = arr2(&[[0., 0.], [1., 1.], [2., 2.]]);
+        let responsibilities = arr2(&[[1.], [1.], [1.]]);
+        for &covariance_type in &[CovarianceType::Diagonal, CovarianceType::Full] {
+            let mut statistics = MixtureStatistics::new(1, 2);
+            statistics.collect_moments(
+                &points.view(),
+                None,
+                &responsibilities.view(),
+                &Array1::from_elem(3, -1.).view(),
+                covariance_type,
+            );
+            let params = statistics.parameter_estimate(covariance_type, 0.);
+            assert!(params.responsibilities(&points.view()).is_ok());
+
+            let constant = arr2(&[[1., 1.], [1., 1.], [1., 1.]]);
+            let mut statistics = MixtureStatistics::new(1, 2);
+            statistics.collect_moments(
+                &constant.view(),
+                None,
+                &responsibilities.view(),
+                &Array1::from_elem(3, -1.).view(),
+                covariance_type,
+            );
+            let params = statistics.parameter_estimate(covariance_type, 0.);
+            assert!(params.responsibilities(&constant.view()).is_ok());
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use super::mixture::GaussianMixtureParams;
+use super::*;
+
+/// Convergence Criteria for the Expectation-Maximization iterations of a Gaussian Mixture Model.
+#[derive(Default, Clone, Abomonation)]
+pub struct EmConvergenceCriteria<T> {
+    pub max_iterations: Option<usize>,
+    pub min_likelihood_change: Option<T>,
+}
+
+impl<T> EmConvergenceCriteria<T> {
+    /// Adds an iteration limit.
+    pub fn limit_iterations(mut self, iterations: usize) -> Self {
+        self.max_iterations = Some(iterations);
+        self
+    }
+
+    /// Abort if the average log-likelihood of the training data changes less than
+    /// the given amount between two iterations.
+    pub fn likelihood_change(mut self, min_change: T) -> Self {
+        self.min_likelihood_change = Some(min_change);
+        self
+    }
+}
+
+impl<T: Float> EmConvergenceCriteria<T> {
+    /// Check if the algorithm should abort, given the log-likelihoods of the previous and the
+    /// current iteration and the number of iterations
+    pub fn converges(&self, old: Option<T>, new: Option<T>, iteration: usize) -> bool {
+        if let Some(max_iterations) = self.max_iterations {
+            if max_iterations <= iteration {
+                return true;
+            }
+        }
+
+        if let (Some(min_change), Some(old), Some(new)) = (self.min_likelihood_change, old, new) {
+            return (new - old).abs() <= min_change;
+        }
+
+        false
+    }
+}
+
+pub(crate) trait MixtureStopCondition<S: Scope, D: Data> {
+    /// Splits the stream of mixture parameters into the converged parameters (first stream)
+    /// and the ones that are fed into the next iteration (second stream)
+    fn mixture_stop_condition(
+        &self,
+        criteria: EmConvergenceCriteria<D>,
+    ) -> (
+        Stream<S, GaussianMixtureParams<D>>,
+        Stream<S, GaussianMixtureParams<D>>,
+    );
+}
+
+impl<S: Scope<Timestamp = Product<T, usize>>, T: Timestamp, D: Data + Debug + Float>
+    MixtureStopCondition<S, D> for Stream<S, GaussianMixtureParams<D>>
+{
+    fn mixture_stop_condition(
+        &self,
+        criteria: EmConvergenceCriteria<D>,
+    ) -> (
+        Stream<S, GaussianMixtureParams<D>>,
+        Stream<S, GaussianMixtureParams<D>>,
+    ) {
+        let worker = self.scope().index();
+        let mut outputs = self.unary(Pipeline, "CheckMixtureConvergence", |_, _| {
+            let mut likelihood_stash: HashMap<T, Option<D>> = HashMap::new();
+
+            move |input, output| {
+                input.for_each(|cap, data| {
+                    let cap = cap.retain();
+                    for params in data.drain(..) {
+                        let previous = likelihood_stash.remove(&cap.outer).and_then(|ll| ll);
+                        let done = criteria.converges(previous, params.log_likelihood, cap.inner);
+
+                        if done {
+                            debug!(
+                                "Worker {}: EM converged after {} iterations, log-likelihood {:?}",
+                                worker, cap.inner, params.log_likelihood
+                            );
+                        } else {
+                            likelihood_stash.insert(cap.outer.clone(), params.log_likelihood);
+                        }
+
+                        output.session(&cap).give((done, params));
+                    }
+                });
+            }
+        })
+            // split the parameters off into a separate stream (out of the loop) if the computation is done
+            .partition(2, |(done, params)| {
+                if done { (1, params) } else { (0, params) }
+            });
+
+        (outputs.pop().unwrap(), outputs.pop().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn converges() {
+        let criteria = <EmConvergenceCriteria<f64>>::default()
+            .limit_iterations(5)
+            .likelihood_change(0.1);
+
+        assert!(!criteria.converges(None, Some(-3.), 0));
+        assert!(!criteria.converges(Some(-3.), Some(-2.), 1));
+        assert!(criteria.converges(Some(-2.), Some(-1.95), 2));
+        assert!(criteria.converges(Some(-3.), Some(-2.), 5));
+    }
+}
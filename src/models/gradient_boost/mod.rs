@@ -6,6 +6,7 @@ use data::serialization::*;
 use data::TrainingData;
 use failure::Fail;
 use models::decision_tree::histogram_generics::ContinuousValue;
+use models::decision_tree::regression::StreamingRegressionTree;
 use models::*;
 use ndarray::prelude::*;
 use ndarray::ScalarOperand;
@@ -160,3 +161,25 @@ where
         })
     }
 }
+
+/// Allows boosted regression trees to be used wherever a model implementing `Train` is
+/// expected, e.g. for cross-validation and parameter search. The inner model is fixed, since a
+/// blanket impl over any inner model lets the compiler recurse through ever deeper nested scopes
+/// whenever it looks for a `Train` impl of a stream with an inferred model.
+impl<S, T, L> Train<S, GradientBoostingRegression<StreamingRegressionTree<T, L>, T, L>>
+    for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: ExchangeData,
+    L: ExchangeData,
+    StreamingRegressionTree<T, L>: LabelingModelAttributes,
+    Stream<S, TrainingData<T, L>>:
+        TrainMeta<S, GradientBoostingRegression<StreamingRegressionTree<T, L>, T, L>>,
+{
+    fn train(
+        &self,
+        model: &GradientBoostingRegression<StreamingRegressionTree<T, L>, T, L>,
+    ) -> Stream<S, BoostChain<StreamingRegressionTree<T, L>, T, L>> {
+        self.train_meta(model)
+    }
+}
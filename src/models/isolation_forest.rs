@@ -0,0 +1,387 @@
+//! Isolation forests, which detect anomalies by how easily samples are separated from
+//! the rest of the data by random splits.
+//!
+//! Each worker grows its own isolation trees on random subsamples of the data it received.
+//! The trees of all workers are merged into one forest on the first worker, which is then
+//! broadcast to all workers for the predictions. The leaves of the trees are labeled with
+//! the (normalized) length of the path to them, so that the average label of the leaves a
+//! sample arrives at determines its anomaly score.
+
+use data::dataflow::random::seeded_rng;
+use data::dataflow::{ApplyLatest, ReduceEachTime};
+use data::serialization::*;
+use fnv::FnvHashMap;
+use models::decision_tree::histogram_generics::ContinuousValue;
+use models::decision_tree::tree::{DecisionTree, Rule};
+use models::*;
+use ndarray::prelude::*;
+use ndarray::stack;
+use num_traits::cast::cast;
+use rand::seq::sample_indices;
+use rand::Rng;
+use std::marker::PhantomData;
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Broadcast, Operator};
+use timely::dataflow::{Scope, Stream};
+
+#[derive(Fail, Debug, Abomonation, Clone)]
+pub enum IsolationForestError {
+    #[fail(display = "Expected samples with {} features, got {}", _0, _1)]
+    FeatureMismatch(usize, usize),
+    #[fail(display = "The forest does not contain any trees")]
+    NoTrees,
+}
+
+/// Anomaly detection model. Predicts an anomaly score between 0 and 1 for each sample,
+/// scores well above 0.5 indicate anomalies.
+#[derive(Abomonation, Clone, Debug)]
+pub struct IsolationForest<T> {
+    trees: usize,
+    subsample_size: usize,
+    seed: u64,
+    _t: PhantomData<T>,
+}
+
+impl<T> IsolationForest<T> {
+    /// Creates a model that grows the given number of trees on each worker
+    pub fn new(trees: usize) -> Self {
+        assert!(trees > 0, "At least one tree per worker is necessary");
+        IsolationForest {
+            trees,
+            subsample_size: 256,
+            seed: 0,
+            _t: PhantomData,
+        }
+    }
+
+    /// Sets the number of samples each tree is grown from. The depth of the trees is
+    /// limited to the logarithm of this number.
+    pub fn subsample_size(mut self, subsample_size: usize) -> Self {
+        assert!(subsample_size > 1, "Subsamples need at least two samples");
+        self.subsample_size = subsample_size;
+        self
+    }
+
+    /// Sets the seed for the random subsamples and splits
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// Trees of a trained isolation forest. Each leaf is labeled with the expected length
+/// of the path to isolate a sample that arrives there, relative to the average path
+/// length in the tree.
+#[derive(Abomonation, Clone, Debug)]
+pub struct IsolationForestParams<T> {
+    pub trees: Vec<DecisionTree<T, T>>,
+    pub features: usize,
+}
+
+impl<T> IsolationForestParams<T> {
+    fn merge(mut self, other: Self) -> Self {
+        self.trees.extend(other.trees);
+        self.features = self.features.max(other.features);
+        self
+    }
+}
+
+impl<T: ContinuousValue> ModelAttributes for IsolationForest<T> {
+    type TrainingResult = IsolationForestParams<T>;
+}
+
+impl<T: ContinuousValue> LabelingModelAttributes for IsolationForest<T> {
+    type Predictions = AbomonableArray1<T>;
+    type PredictErr = IsolationForestError;
+}
+
+/// Average length of the path to an unsuccessful search in a binary search tree with
+/// `n` samples, which normalizes the path lengths in isolation trees
+fn average_path_length(n: usize) -> f64 {
+    const EULER_MASCHERONI: f64 = 0.577_215_664_901_532_9;
+    match n {
+        0 | 1 => 0.,
+        2 => 1.,
+        n => {
+            let n = n as f64;
+            2. * ((n - 1.).ln() + EULER_MASCHERONI) - 2. * (n - 1.) / n
+        }
+    }
+}
+
+/// Picks a random feature that is not constant within the rows, and a random threshold
+/// between its minimum and maximum
+fn random_split<T: ContinuousValue, R: Rng>(
+    x: ArrayView2<T>,
+    rows: &[usize],
+    rng: &mut R,
+) -> Option<(usize, T)> {
+    let ranges = (0..x.cols())
+        .filter_map(|feature| {
+            let (min, max) = rows
+                .iter()
+                .map(|&row| x[(row, feature)])
+                .fold((T::infinity(), T::neg_infinity()), |(min, max), v| {
+                    (min.min(v), max.max(v))
+                });
+            if min < max {
+                Some((feature, min, max))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    if ranges.is_empty() {
+        return None;
+    }
+
+    let (feature, min, max) = ranges[rng.gen_range(0, ranges.len())];
+    let threshold = min + (max - min) * cast(rng.gen::<f64>()).unwrap();
+    Some((feature, threshold))
+}
+
+fn isolation_tree<T: ContinuousValue, R: Rng>(
+    x: ArrayView2<T>,
+    max_depth: usize,
+    rng: &mut R,
+) -> DecisionTree<T, T> {
+    let normalization: T = cast(average_path_length(x.rows())).unwrap();
+    let mut tree = DecisionTree::default();
+    let mut open = vec![(tree.root(), (0..x.rows()).collect::<Vec<_>>(), 0)];
+
+    while let Some((node, rows, depth)) = open.pop() {
+        let split = if depth < max_depth && rows.len() > 1 {
+            random_split(x.view(), &rows, rng)
+        } else {
+            None
+        };
+
+        match split {
+            Some((feature, threshold)) => {
+                let (left_rows, right_rows): (Vec<_>, Vec<_>) =
+                    rows.iter().partition(|&&row| x[(row, feature)] < threshold);
+                let (left, right) = tree.split(node, Rule::threshold(feature, threshold), None);
+                open.push((left, left_rows, depth + 1));
+                open.push((right, right_rows, depth + 1));
+            }
+            None => {
+                // the samples that were not isolated yet would need about as many more
+                // splits as an unsuccessful search in a binary tree
+                let path_length = depth as f64 + average_path_length(rows.len());
+                tree.label(node, cast::<_, T>(path_length).unwrap() / normalization);
+            }
+        }
+    }
+    tree
+}
+
+fn grow_forest<T: ContinuousValue, R: Rng>(
+    x: ArrayView2<T>,
+    trees: usize,
+    subsample_size: usize,
+    rng: &mut R,
+) -> IsolationForestParams<T> {
+    let n = subsample_size.min(x.rows());
+    let trees = if n < 2 {
+        vec![]
+    } else {
+        let max_depth = (n as f64).log2().ceil() as usize;
+        (0..trees)
+            .map(|_| {
+                let rows = sample_indices(rng, x.rows(), n);
+                isolation_tree(x.select(Axis(0), &rows).view(), max_depth, rng)
+            })
+            .collect()
+    };
+
+    IsolationForestParams {
+        trees,
+        features: x.cols(),
+    }
+}
+
+impl<S, T> Train<S, IsolationForest<T>> for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ContinuousValue,
+{
+    fn train(&self, model: &IsolationForest<T>) -> Stream<S, IsolationForestParams<T>> {
+        let worker = self.scope().index();
+        let trees = model.trees;
+        let subsample_size = model.subsample_size;
+        let seed = model.seed;
+
+        self.unary_frontier(Pipeline, "GrowIsolationTrees", move |_, _| {
+            let mut stash = FnvHashMap::default();
+            move |input, output| {
+                input.for_each(|time, data| {
+                    stash
+                        .entry(time.retain())
+                        .or_insert_with(Vec::new)
+                        .extend(data.drain(..));
+                });
+
+                // grow the trees once all samples of a time have arrived
+                for (cap, samples) in &mut stash {
+                    if !input.frontier().less_equal(cap.time()) {
+                        let views = samples.iter().map(|s| s.view()).collect::<Vec<_>>();
+                        let x = stack(Axis(0), &views)
+                            .expect("Samples with the same number of features");
+                        let mut rng = seeded_rng(seed, &(worker, cap.time()));
+                        output.session(&cap).give(grow_forest(
+                            x.view(),
+                            trees,
+                            subsample_size,
+                            &mut rng,
+                        ));
+                        samples.clear();
+                    }
+                }
+                stash.retain(|_, samples| !samples.is_empty());
+            }
+        })
+        .reduce_each_time(IsolationForestParams::merge)
+        .broadcast()
+    }
+}
+
+impl<S, T> Predict<S, IsolationForest<T>, IsolationForestError> for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ContinuousValue,
+{
+    fn predict(
+        &self,
+        _model: &IsolationForest<T>,
+        train_results: Stream<S, IsolationForestParams<T>>,
+    ) -> Stream<S, Result<AbomonableArray1<T>, ModelError<IsolationForestError>>> {
+        train_results.apply_latest(self, |_time, params, samples| {
+            params.predict_samples(&samples)
+        })
+    }
+}
+
+impl<A, T> PredictSamples<A, AbomonableArray1<T>, IsolationForestError> for IsolationForestParams<T>
+where
+    for<'a> &'a A: AsArray<'a, T, Ix2>,
+    T: ContinuousValue,
+{
+    fn predict_samples(
+        &self,
+        samples: &A,
+    ) -> Result<AbomonableArray1<T>, ModelError<IsolationForestError>> {
+        let samples: ArrayView2<T> = samples.into();
+        if self.trees.is_empty() {
+            return Err(ModelError::PredictionFailed(IsolationForestError::NoTrees));
+        }
+        if samples.cols() != self.features {
+            return Err(ModelError::PredictionFailed(
+                IsolationForestError::FeatureMismatch(self.features, samples.cols()),
+            ));
+        }
+
+        let trees: T = cast(self.trees.len()).unwrap();
+        let two: T = cast(2.).unwrap();
+        let scores = samples
+            .outer_iter()
+            .map(|sample| {
+                let path_length = self
+                    .trees
+                    .iter()
+                    .map(|tree| tree.descend(sample.view()).cloned().unwrap_or_else(T::zero))
+                    .fold(T::zero(), |sum, length| sum + length);
+                two.powf(-path_length / trees)
+            })
+            .collect::<Array1<_>>();
+        Ok(scores.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+    use timely::progress::timestamp::RootTimestamp;
+
+    /// Grid of 100 samples in the unit square, and one outlier
+    fn example() -> Array2<f64> {
+        let mut x = Array2::from_shape_fn((101, 2), |(row, feature)| {
+            if feature == 0 {
+                (row % 10) as f64 / 9.
+            } else {
+                (row / 10) as f64 / 9.
+            }
+        });
+        x.row_mut(100).fill(10.);
+        x
+    }
+
+    #[test]
+    fn path_length_normalization() {
+        assert_eq!(average_path_length(1), 0.);
+        assert_eq!(average_path_length(2), 1.);
+        assert!(abs_diff_eq!(
+            average_path_length(256),
+            &10.244_77,
+            epsilon = 1e-4
+        ));
+    }
+
+    #[test]
+    fn outlier_scores() {
+        let mut rng = seeded_rng(0, &0);
+        let params = grow_forest(example().view(), 100, 64, &mut rng);
+        assert_eq!(params.trees.len(), 100);
+
+        let scores: Array1<f64> = params
+            .predict_samples(&arr2(&[[0.5, 0.5], [10., 10.]]))
+            .unwrap()
+            .into();
+        assert!(scores.iter().all(|&s| s > 0. && s < 1.));
+        assert!(scores[0] < 0.55);
+        assert!(scores[1] > 0.6);
+    }
+
+    #[test]
+    fn seeded_trees() {
+        let first = grow_forest(example().view(), 5, 32, &mut seeded_rng(1, &0));
+        let second = grow_forest(example().view(), 5, 32, &mut seeded_rng(1, &0));
+        assert_eq!(first.trees, second.trees);
+    }
+
+    #[test]
+    fn invalid_samples() {
+        let params = grow_forest(example().view(), 5, 32, &mut seeded_rng(0, &0));
+        assert!(params.predict_samples(&arr2(&[[1.]])).is_err());
+
+        let empty = grow_forest(example().slice(s![..1, ..]), 5, 32, &mut seeded_rng(0, &0));
+        assert!(empty.trees.is_empty());
+        assert!(empty.predict_samples(&arr2(&[[1., 1.]])).is_err());
+    }
+
+    #[test]
+    fn train_and_predict_streams() {
+        let result = ::timely::example(|scope| {
+            let model = IsolationForest::new(50).subsample_size(64).seed(3);
+            let forest = vec![
+                AbomonableArray2::from(example().slice(s![..50, ..]).to_owned()),
+                AbomonableArray2::from(example().slice(s![50.., ..]).to_owned()),
+            ]
+            .to_stream(scope)
+            .train(&model);
+
+            vec![AbomonableArray2::from(arr2(&[[0.5, 0.5], [10., 10.]]))]
+                .to_stream(scope)
+                .predict(&model, forest)
+                .map(|scores| {
+                    let scores = scores.unwrap().view().to_vec();
+                    scores[1] > scores[0]
+                })
+                .capture()
+        })
+        .extract();
+
+        assert_eq!(result, vec![(RootTimestamp::new(0), vec![true])]);
+    }
+}
@@ -1,10 +1,10 @@
 use data::providers::IndexesSlice;
 use data::serialization::*;
+use models::distance::{Distance, Euclidean};
 use ndarray::prelude::*;
 use ndarray::ScalarOperand;
 use ndarray::Zip;
 use ndarray_linalg::types::Scalar;
-use ndarray_linalg::Norm;
 use num_traits::cast::FromPrimitive;
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -16,14 +16,14 @@ use timely::Data;
 pub(crate) struct AggregationStatistics<T: Debug> {
     pub centroid_assignments: Vec<(usize, usize)>,
     pub cluster_sums: Array2<T>,
-    pub cluster_counts: Array1<usize>,
+    pub cluster_counts: Array1<T>,
 }
 
 #[derive(Abomonation, Clone)]
 pub(crate) struct AbomonableAggregationStatistics<T: Debug> {
     pub centroid_assignments: Vec<(usize, usize)>,
     pub cluster_sums: AbomonableArray2<T>,
-    pub cluster_counts: AbomonableArray1<usize>,
+    pub cluster_counts: AbomonableArray1<T>,
 }
 
 impl<T: Data + Debug> From<AbomonableAggregationStatistics<T>> for AggregationStatistics<T> {
@@ -58,11 +58,13 @@ where
         }
     }
 
-    /// Assigns the given points to the given set of centroids, sums up the values of the assigned
-    /// points and counts how many were assigned to each centroid.
+    /// Assigns the given points to the given set of centroids, sums up the (weighted) values of
+    /// the assigned points and counts how many were assigned to each centroid. Without weights,
+    /// each point has a weight of 1.
     pub fn collect_assignment_statistics<'a>(
         &mut self,
         points: &ArrayView2<'a, T>,
+        weights: Option<ArrayView1<'a, f64>>,
         centroids: &ArrayView2<'a, T>,
         slice_index: &impl IndexesSlice<Idx = usize>,
     ) where
@@ -70,26 +72,23 @@ where
     {
         for (point_idx, point) in points.outer_iter().enumerate() {
             // find closest centroid
-            let centroid_idx = centroids
-                .outer_iter()
-                .enumerate()
-                .map(|(index, candidate_centroid)| {
-                    let distance = (&point - &candidate_centroid).norm_l2();
-                    (index, distance)
-                })
-                .min_by(|&(_, a), &(_, b)| a.partial_cmp(&b).unwrap_or(::std::cmp::Ordering::Less))
-                .unwrap()
+            let centroid_idx = Euclidean
+                .closest(point.view(), centroids.view())
+                .expect("At least one centroid")
                 .0;
 
             // save assignment
             self.centroid_assignments
                 .push((slice_index.absolute_index(point_idx), centroid_idx));
-            // add point to sum of all points assigned to centroid
+            let weight = weights.as_ref().map_or_else(T::one, |weights| {
+                <T as FromPrimitive>::from_f64(weights[point_idx]).expect("Weight of a point")
+            });
+            // add weighted point to sum of all points assigned to centroid
             self.cluster_sums
                 .subview_mut(Axis(0), centroid_idx)
-                .add_assign(&point);
-            // increment count of assigned points
-            self.cluster_counts[centroid_idx] += 1;
+                .add_assign(&(&point * weight));
+            // add weight to the count of assigned points
+            self.cluster_counts[centroid_idx] += weight;
         }
     }
 
@@ -102,10 +101,10 @@ where
         Zip::from(estimates.outer_iter_mut())
             .and(&counts)
             .apply(|mut sum, &count| {
-                if count > 0 {
-                    sum /= T::from_usize(count).unwrap()
+                if !count.is_zero() {
+                    sum /= count
                 } else {
-                    sum.fill(T::from_usize(0).unwrap())
+                    sum.fill(T::zero())
                 }
             });
 
@@ -173,3 +172,30 @@ impl<S: Scope, D: Scalar + AddAssign<D> + Data + Debug> AccumulateStatistics<S,
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::providers::IntSliceIndex;
+
+    #[test]
+    fn weighted_statistics() {
+        let points = arr2(&[[0., 0.], [2., 0.], [10., 10.]]);
+        let weights = arr1(&[3., 1., 2.]);
+        let centroids = arr2(&[[1., 0.], [9., 9.]]);
+
+        let mut statistics = AggregationStatistics::new(2, 2);
+        statistics.collect_assignment_statistics(
+            &points.view(),
+            Some(weights.view()),
+            &centroids.view(),
+            &IntSliceIndex::new(0, 3),
+        );
+
+        assert_eq!(statistics.cluster_counts, arr1(&[4., 2.]));
+        assert_eq!(
+            statistics.centroid_estimate(),
+            arr2(&[[0.5, 0.], [10., 10.]])
+        );
+    }
+}
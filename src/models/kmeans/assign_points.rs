@@ -3,9 +3,9 @@ use data::providers::IntSliceIndex;
 use super::*;
 
 pub(crate) trait AssignPoints<S: Scope, D: Data + ::std::fmt::Debug> {
-    fn assign_points(
+    fn assign_points<P: WeightedSamples<D>>(
         &self,
-        points_stream: &Stream<S, (IntSliceIndex<usize>, AbomonableArray2<D>)>,
+        points_stream: &Stream<S, (IntSliceIndex<usize>, P)>,
     ) -> Stream<S, AbomonableAggregationStatistics<D>>;
 }
 
@@ -14,9 +14,9 @@ impl<S: Scope<Timestamp = Product<T, usize>>, T: Timestamp, D> AssignPoints<S, D
 where
     D: ::std::fmt::Debug + Data + NumAssignOps + Scalar + FromPrimitive + ScalarOperand,
 {
-    fn assign_points(
+    fn assign_points<P: WeightedSamples<D>>(
         &self,
-        points_stream: &Stream<S, (IntSliceIndex<usize>, AbomonableArray2<D>)>,
+        points_stream: &Stream<S, (IntSliceIndex<usize>, P)>,
     ) -> Stream<S, AbomonableAggregationStatistics<D>> {
         let worker_index = self.scope().index();
         self.binary_frontier(
@@ -64,11 +64,12 @@ where
                                 );
 
                                 for &(slice_index, ref points) in &point_stash {
-                                    let points_view: ArrayView<
-                                        _,
-                                        _,
-                                    > = points.into();
-                                    agg.collect_assignment_statistics(&points_view, &centroids_view, &slice_index);
+                                    agg.collect_assignment_statistics(
+                                        &points.samples(),
+                                        points.weights(),
+                                        &centroids_view,
+                                        &slice_index,
+                                    );
                                 }
 
                                 session.give(agg.into());
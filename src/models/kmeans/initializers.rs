@@ -1,3 +1,4 @@
+use data::dataflow::random::seeded_rng;
 use data::serialization::*;
 use fnv::FnvHashMap;
 use ndarray::prelude::*;
@@ -14,9 +15,12 @@ use timely::progress::Timestamp;
 use timely::{Data, ExchangeData};
 
 pub trait KMeansInitializer<T: Data> {
+    /// Selects `n_centroids` initial centroids from the samples. All random choices are
+    /// derived from `seed`, so the same seed and data always result in the same centroids.
     fn select_initial_centroids<S: Scope>(
         samples: &Stream<S, AbomonableArray2<T>>,
         n_centroids: usize,
+        seed: u64,
     ) -> Stream<S, AbomonableArray2<T>>;
 }
 
@@ -27,10 +31,11 @@ impl<T: ExchangeData + Copy + Num + RealScalar> KMeansInitializer<T> for RandomS
     fn select_initial_centroids<S: Scope>(
         samples: &Stream<S, AbomonableArray2<T>>,
         n_centroids: usize,
+        seed: u64,
     ) -> Stream<S, AbomonableArray2<T>> {
         let centroids_per_peer = (n_centroids / samples.scope().peers()) + 1;
         samples
-            .select_random_samples_uniform(centroids_per_peer)
+            .select_random_samples_uniform(centroids_per_peer, seed)
             .exchange(|_| 0_u64)
             .aggregate_centroids(n_centroids)
     }
@@ -42,6 +47,7 @@ impl<T: ExchangeData + Copy + Num + RealScalar> KMeansInitializer<T> for KMeansP
     fn select_initial_centroids<S: Scope>(
         samples: &Stream<S, AbomonableArray2<T>>,
         n_centroids: usize,
+        seed: u64,
     ) -> Stream<S, AbomonableArray2<T>> {
         let mut scope = samples.scope();
         let worker = scope.index();
@@ -49,15 +55,19 @@ impl<T: ExchangeData + Copy + Num + RealScalar> KMeansInitializer<T> for KMeansP
 
         let first_centroid = samples
             .filter(move |_| worker == 0)
-            .select_random_samples_uniform(1);
+            .select_random_samples_uniform(1, seed);
 
         scope.scoped(|loop_scope| {
             let (loop_handle, loop_stream) = loop_scope.loop_variable(n_centroids, 1);
             let (next_iter, finished) = first_centroid
                 .enter(loop_scope)
                 .concat(&loop_stream)
-                .exchange(move |_| ::rand::thread_rng().gen_range(0, peers as u64))
-                .select_random_distance_weighted(&samples.enter(loop_scope))
+                // the centroids grow by one row per iteration, so their row count
+                // determines the worker that selects the next centroid
+                .exchange(move |centroids: &AbomonableArray2<T>| {
+                    seeded_rng(seed, &centroids.view().rows()).gen_range(0, peers as u64)
+                })
+                .select_random_distance_weighted(&samples.enter(loop_scope), seed)
                 .branch_when(move |time| time.inner >= n_centroids);
             next_iter.connect_loop(loop_handle);
             finished.leave()
@@ -66,17 +76,30 @@ impl<T: ExchangeData + Copy + Num + RealScalar> KMeansInitializer<T> for KMeansP
 }
 
 trait SelectRandomSamplesUniform<S: Scope, T: Data> {
-    fn select_random_samples_uniform(&self, per_peer: usize) -> Stream<S, AbomonableArray2<T>>;
+    fn select_random_samples_uniform(
+        &self,
+        per_peer: usize,
+        seed: u64,
+    ) -> Stream<S, AbomonableArray2<T>>;
 }
 
 impl<S: Scope, T: Data + Copy> SelectRandomSamplesUniform<S, T> for Stream<S, AbomonableArray2<T>> {
-    fn select_random_samples_uniform(&self, per_peer: usize) -> Stream<S, AbomonableArray2<T>> {
-        self.unary(Pipeline, "SelectRandomSamples", |_, _| {
+    fn select_random_samples_uniform(
+        &self,
+        per_peer: usize,
+        seed: u64,
+    ) -> Stream<S, AbomonableArray2<T>> {
+        let worker = self.scope().index();
+        self.unary_frontier(Pipeline, "SelectRandomSamples", |_, _| {
             let mut count = per_peer;
+            // one generator per time, so that batches continue its random sequence
+            let mut rngs = FnvHashMap::default();
             move |input, output| {
                 if count > 0 {
                     input.for_each(|time, data| {
-                        let mut rng = ::rand::thread_rng();
+                        let rng = rngs
+                            .entry(time.time().clone())
+                            .or_insert_with(|| seeded_rng(seed, &(worker, time.time())));
                         for datum in data.iter() {
                             let matrix_view = datum.view();
                             while count > 0 {
@@ -90,6 +113,8 @@ impl<S: Scope, T: Data + Copy> SelectRandomSamplesUniform<S, T> for Stream<S, Ab
                         }
                     });
                 }
+                // generators of times that the input has passed are not needed anymore
+                rngs.retain(|time, _| input.frontier().less_equal(time));
             }
         })
     }
@@ -143,6 +168,7 @@ trait SelectRandomDistanceWeighted<'a, S: Scope, Ts: Timestamp, T: Data> {
     fn select_random_distance_weighted(
         &self,
         samples: &Stream<Child<'a, S, Ts>, AbomonableArray2<T>>,
+        seed: u64,
     ) -> Self;
 }
 
@@ -152,7 +178,9 @@ impl<'a, S: Scope, T: Data + Num + RealScalar, Ts: Timestamp>
     fn select_random_distance_weighted(
         &self,
         samples: &Stream<Child<'a, S, Ts>, AbomonableArray2<T>>,
+        seed: u64,
     ) -> Stream<Child<'a, S, Ts>, AbomonableArray2<T>> {
+        let worker = self.scope().index();
         self.binary_frontier(
             samples,
             Pipeline,
@@ -218,9 +246,9 @@ impl<'a, S: Scope, T: Data + Num + RealScalar, Ts: Timestamp>
                                 }
                             }
 
+                            let mut rng = seeded_rng(seed, &(worker, cap.time()));
                             let rand_num: T::Real =
-                                into_scalar::<T::Real>(::rand::thread_rng().gen())
-                                    * cumulative_distance;
+                                into_scalar::<T::Real>(rng.gen()) * cumulative_distance;
 
                             for (chunk_idx, chunk) in distances.iter().enumerate() {
                                 let index = match chunk.binary_search_by(|probe| {
@@ -3,15 +3,17 @@
 use self::aggregator::*;
 use self::assign_points::AssignPoints;
 pub use self::convergence::*;
-use self::stop_condition::StopCondition;
-use data::dataflow::{ApplyLatest, IndexDataStream};
+pub(crate) use self::stop_condition::StopCondition;
+use data::dataflow::{ApplyLatest, IndexDataStream, SampleCount};
 use data::serialization::*;
+use data::TrainingData;
+use models::distance::{Distance, Euclidean};
 use models::kmeans::initializers::KMeansInitializer;
 use models::*;
 use ndarray::indices;
 use ndarray::prelude::*;
 use ndarray::{NdProducer, ScalarOperand, Zip};
-use ndarray_linalg::{Norm, Scalar};
+use ndarray_linalg::Scalar;
 use num_traits::{cast::FromPrimitive, Float, NumAssignOps};
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -35,6 +37,7 @@ pub struct Kmeans<Item: Data, Init: KMeansInitializer<Item> + Data> {
     n_clusters: usize,
     cols: usize,
     end_criteria: ConvergenceCriteria<Item>,
+    seed: u64,
     phantom_data: PhantomData<Init>,
 }
 
@@ -44,9 +47,16 @@ impl<Item: Data, Init: Data + KMeansInitializer<Item>> Kmeans<Item, Init> {
             n_clusters,
             cols,
             end_criteria,
+            seed: 0,
             phantom_data: PhantomData,
         }
     }
+
+    /// Sets the seed for the random selection of the initial centroids.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
 }
 
 impl<T: ExchangeData, Init: ExchangeData + KMeansInitializer<T>> ModelAttributes
@@ -68,6 +78,32 @@ pub enum KMeansError {
     Unknown,
 }
 
+/// Chunks of samples that K-Means can be trained on, optionally with a weight for each sample
+pub(crate) trait WeightedSamples<T>: Data + SampleCount {
+    fn samples(&self) -> ArrayView2<T>;
+    fn weights(&self) -> Option<ArrayView1<f64>>;
+}
+
+impl<T: Data> WeightedSamples<T> for AbomonableArray2<T> {
+    fn samples(&self) -> ArrayView2<T> {
+        self.view()
+    }
+
+    fn weights(&self) -> Option<ArrayView1<f64>> {
+        None
+    }
+}
+
+impl<T: Data, L: Data> WeightedSamples<T> for TrainingData<T, L> {
+    fn samples(&self) -> ArrayView2<T> {
+        self.x()
+    }
+
+    fn weights(&self) -> Option<ArrayView1<f64>> {
+        TrainingData::weights(self)
+    }
+}
+
 impl<S, T, Init> Train<S, Kmeans<T, Init>> for Stream<S, AbomonableArray2<T>>
 where
     S: Scope,
@@ -75,58 +111,88 @@ where
     Init: ExchangeData + KMeansInitializer<T>,
 {
     fn train(&self, model: &Kmeans<T, Init>) -> Stream<S, AbomonableArray2<T>> {
-        let n_clusters = model.n_clusters;
-        let cols = model.cols;
-
-        let end_criteria = model.end_criteria.clone();
-        let max_iterations = model
-            .end_criteria
-            .max_iterations
-            .unwrap_or(<usize>::max_value());
-
-        let initial_centroids = Init::select_initial_centroids(self, n_clusters);
-
-        initial_centroids.inspect(|initial_centroids| {
-            debug!("Selected initial centroids: {:?}", initial_centroids.view());
-        });
-
-        self.scope().scoped(|inner_scope| {
-            let worker_index = inner_scope.index();
-            debug!("Constructing worker {}", inner_scope.index());
-
-            let (loop_handle, loop_stream) = inner_scope.loop_variable(max_iterations, 1);
-
-            let (done, next_iteration) = initial_centroids
-                .enter(inner_scope)
-                .concat(&loop_stream)
-                // checks whether the convergence criteria are met and aborts the loop
-                .stop_condition(end_criteria);
-
-            next_iteration
-                .broadcast()
-                .assign_points(&(self.index_data().enter(inner_scope)))
-                .exchange(|_| 0u64)
-                .accumulate_statistics(AggregationStatistics::new(n_clusters, cols))
-                .map(move |stats| {
-                    debug!(
-                        "Worker {}: Aggregated all assignments, calculating new centroids",
-                        worker_index
-                    );
-                    // re-estimate centroids
-                    AggregationStatistics::from(stats)
-                        .centroid_estimate()
-                        .into()
-                })
-                .connect_loop(loop_handle);
-
-            done.inspect(move |c| {
-                debug!("worker {}", worker_index);
-                debug!("Finished: {:?}", c.view());
-            }).leave()
-        })
+        train_centroids(model, self, self)
+    }
+}
+
+/// Trains on the samples of the training data, weighted by their sample weights
+impl<S, T, L, Init> Train<S, Kmeans<T, Init>> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar + NumAssignOps + ScalarOperand + Float + Debug + FromPrimitive,
+    L: Data,
+    Init: ExchangeData + KMeansInitializer<T>,
+{
+    fn train(&self, model: &Kmeans<T, Init>) -> Stream<S, AbomonableArray2<T>> {
+        train_centroids(model, &self.map(|data| data.x), self)
     }
 }
 
+/// Selects the initial centroids from `samples` and iteratively improves them using the
+/// (weighted) `points`
+fn train_centroids<S, T, Init, P>(
+    model: &Kmeans<T, Init>,
+    samples: &Stream<S, AbomonableArray2<T>>,
+    points: &Stream<S, P>,
+) -> Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar + NumAssignOps + ScalarOperand + Float + Debug + FromPrimitive,
+    Init: ExchangeData + KMeansInitializer<T>,
+    P: WeightedSamples<T>,
+{
+    let n_clusters = model.n_clusters;
+    let cols = model.cols;
+
+    let end_criteria = model.end_criteria.clone();
+    let max_iterations = model
+        .end_criteria
+        .max_iterations
+        .unwrap_or(<usize>::max_value());
+
+    let initial_centroids = Init::select_initial_centroids(samples, n_clusters, model.seed);
+
+    initial_centroids.inspect(|initial_centroids| {
+        debug!("Selected initial centroids: {:?}", initial_centroids.view());
+    });
+
+    samples.scope().scoped(|inner_scope| {
+        let worker_index = inner_scope.index();
+        debug!("Constructing worker {}", inner_scope.index());
+
+        let (loop_handle, loop_stream) = inner_scope.loop_variable(max_iterations, 1);
+
+        let (done, next_iteration) = initial_centroids
+            .enter(inner_scope)
+            .concat(&loop_stream)
+            // checks whether the convergence criteria are met and aborts the loop
+            .stop_condition(end_criteria);
+
+        next_iteration
+            .broadcast()
+            .assign_points(&(points.index_data().enter(inner_scope)))
+            .exchange(|_| 0u64)
+            .accumulate_statistics(AggregationStatistics::new(n_clusters, cols))
+            .map(move |stats| {
+                debug!(
+                    "Worker {}: Aggregated all assignments, calculating new centroids",
+                    worker_index
+                );
+                // re-estimate centroids
+                AggregationStatistics::from(stats)
+                    .centroid_estimate()
+                    .into()
+            })
+            .connect_loop(loop_handle);
+
+        done.inspect(move |c| {
+            debug!("worker {}", worker_index);
+            debug!("Finished: {:?}", c.view());
+        })
+        .leave()
+    })
+}
+
 impl<S, T, Init> Predict<S, Kmeans<T, Init>, KMeansError> for Stream<S, AbomonableArray2<T>>
 where
     S: Scope,
@@ -147,14 +213,9 @@ where
                 .and(samples.genrows())
                 .and(indices(samples.genrows().raw_dim()))
                 .apply(|mut assignment, point, point_idx| {
-                    let centroid_index = centroids
-                        .outer_iter()
-                        .map(|centroid| (&point - &centroid).norm_l2())
-                        .enumerate()
-                        .min_by(|&(_, a), &(_, b)| {
-                            a.partial_cmp(&b).unwrap_or(::std::cmp::Ordering::Less)
-                        })
-                        .unwrap()
+                    let centroid_index = Euclidean
+                        .closest(point, centroids.view())
+                        .expect("At least one centroid")
                         .0;
                     assignment[0] = point_idx;
                     assignment[1] = centroid_index;
@@ -0,0 +1,582 @@
+//! k-nearest neighbors classification and regression.
+//!
+//! Training only collects the training data that each worker received, it is not sent
+//! anywhere. Each batch of samples that should be predicted is broadcast to all workers,
+//! which search their part of the training data for the `k` nearest neighbors of each
+//! sample. The candidates are sent back to the worker the batch came from, where the `k`
+//! nearest neighbors among all candidates determine the prediction. The neighbors are
+//! weighted by their sample weights, samples with a weight of 0 are never neighbors.
+
+use data::dataflow::{ApplyLatest, CombineEachTime};
+use data::serialization::*;
+use data::TrainingData;
+use models::decision_tree::histogram_generics::DiscreteValue;
+use models::distance::Distance;
+use models::*;
+use ndarray::prelude::*;
+use ndarray::stack;
+use ndarray_linalg::Scalar;
+use num_traits::cast::cast;
+use num_traits::Float;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Broadcast, Exchange, Map, Operator};
+use timely::dataflow::{Scope, Stream};
+use timely::ExchangeData;
+
+#[derive(Fail, Debug, Abomonation, Clone)]
+pub enum KnnError {
+    #[fail(display = "Expected samples with {} features, got {}", _0, _1)]
+    FeatureMismatch(usize, usize),
+    #[fail(display = "No training data to search for neighbors")]
+    NoTrainingData,
+}
+
+/// Classifier that predicts the label with the largest total weight among the `k` nearest
+/// training samples. Ties are broken in favor of the label of the nearest neighbor.
+#[derive(Abomonation, Clone, Debug)]
+pub struct KnnClassifier<T, L, D> {
+    k: usize,
+    metric: D,
+    _t: PhantomData<(T, L)>,
+}
+
+impl<T, L, D: Distance> KnnClassifier<T, L, D> {
+    pub fn new(k: usize, metric: D) -> Self {
+        assert!(k > 0, "At least one neighbor is necessary");
+        KnnClassifier {
+            k,
+            metric,
+            _t: PhantomData,
+        }
+    }
+}
+
+/// Regression that predicts the weighted mean target value of the `k` nearest training
+/// samples
+#[derive(Abomonation, Clone, Debug)]
+pub struct KnnRegressor<T, D> {
+    k: usize,
+    metric: D,
+    _t: PhantomData<T>,
+}
+
+impl<T, D: Distance> KnnRegressor<T, D> {
+    pub fn new(k: usize, metric: D) -> Self {
+        assert!(k > 0, "At least one neighbor is necessary");
+        KnnRegressor {
+            k,
+            metric,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<T: ExchangeData, L: ExchangeData, D: Distance> ModelAttributes for KnnClassifier<T, L, D> {
+    type TrainingResult = TrainingData<T, L>;
+}
+
+impl<T: ExchangeData, L: ExchangeData, D: Distance> LabelingModelAttributes
+    for KnnClassifier<T, L, D>
+{
+    type Predictions = AbomonableArray1<L>;
+    type PredictErr = KnnError;
+}
+
+impl<T: ExchangeData, D: Distance> ModelAttributes for KnnRegressor<T, D> {
+    type TrainingResult = TrainingData<T, T>;
+}
+
+impl<T: ExchangeData, D: Distance> LabelingModelAttributes for KnnRegressor<T, D> {
+    type Predictions = AbomonableArray1<T>;
+    type PredictErr = KnnError;
+}
+
+/// Candidates for the nearest neighbors of a sample with their labels and weights, sorted by
+/// increasing distance
+type Neighbors<T, L> = Vec<(T, L, f64)>;
+
+/// Collects all training data of each time on each worker. Every worker emits its part,
+/// even if it did not receive any training data itself.
+fn collect_partitions<S, T, L>(
+    data: &Stream<S, TrainingData<T, L>>,
+) -> Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: ExchangeData + Copy,
+    L: ExchangeData + Copy,
+{
+    let trigger = data.map(|_| ()).broadcast();
+    data.combine_each_time(&trigger, |parts, triggers| {
+        triggers.clear();
+        vec![concatenate(parts.drain(..).collect())]
+    })
+}
+
+fn concatenate<T: Copy, L: Copy>(parts: Vec<TrainingData<T, L>>) -> TrainingData<T, L> {
+    if parts.is_empty() {
+        return TrainingData {
+            x: Array2::from_shape_vec((0, 0), vec![]).unwrap().into(),
+            y: Array1::from_vec(vec![]).into(),
+            weights: None,
+        };
+    }
+
+    // parts without weights are weighted equally
+    let weights = if parts.iter().any(|part| part.weights.is_some()) {
+        let weights = parts
+            .iter()
+            .map(|part| match part.weights() {
+                Some(weights) => weights.to_owned(),
+                None => Array1::ones(part.y().len()),
+            })
+            .collect::<Vec<_>>();
+        let views = weights.iter().map(|w| w.view()).collect::<Vec<_>>();
+        Some(stack(Axis(0), &views).unwrap().into())
+    } else {
+        None
+    };
+
+    let x = parts.iter().map(|part| part.x()).collect::<Vec<_>>();
+    let y = parts.iter().map(|part| part.y()).collect::<Vec<_>>();
+    TrainingData {
+        x: stack(Axis(0), &x)
+            .expect("Training data with the same number of features")
+            .into(),
+        y: stack(Axis(0), &y).unwrap().into(),
+        weights,
+    }
+}
+
+fn sort_by_distance<T: Float, L>(neighbors: &mut Neighbors<T, L>) {
+    neighbors.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Less));
+}
+
+/// Finds the `k` nearest neighbors of each sample in a part of the training data
+fn local_neighbors<T, L, D>(
+    metric: &D,
+    k: usize,
+    partition: &TrainingData<T, L>,
+    samples: ArrayView2<T>,
+) -> Result<Vec<Neighbors<T, L>>, KnnError>
+where
+    T: Scalar<Real = T> + Float,
+    L: Copy,
+    D: Distance,
+{
+    let x = partition.x();
+    if x.rows() > 0 && x.cols() != samples.cols() {
+        return Err(KnnError::FeatureMismatch(x.cols(), samples.cols()));
+    }
+
+    Ok(samples
+        .outer_iter()
+        .map(|sample| {
+            let mut neighbors = x
+                .outer_iter()
+                .zip(partition.y().iter())
+                .enumerate()
+                .map(|(i, (row, &label))| {
+                    let distance = metric.distance(sample.view(), row);
+                    (distance, label, partition.weight(i))
+                })
+                .filter(|&(_, _, weight)| weight > 0.)
+                .collect::<Vec<_>>();
+            sort_by_distance(&mut neighbors);
+            neighbors.truncate(k);
+            neighbors
+        })
+        .collect())
+}
+
+/// Merges the candidates for the nearest neighbors of the same samples
+fn merge_neighbors<T: Float, L>(
+    first: Result<Vec<Neighbors<T, L>>, KnnError>,
+    second: Result<Vec<Neighbors<T, L>>, KnnError>,
+    k: usize,
+) -> Result<Vec<Neighbors<T, L>>, KnnError> {
+    Ok(first?
+        .into_iter()
+        .zip(second?)
+        .map(|(mut neighbors, other)| {
+            neighbors.extend(other);
+            sort_by_distance(&mut neighbors);
+            neighbors.truncate(k);
+            neighbors
+        })
+        .collect())
+}
+
+/// Finds the `k` nearest neighbors of each batch of samples in the training data of all
+/// workers. The neighbors are emitted on the worker that received the batch.
+fn search_neighbors<S, T, L, D>(
+    samples: &Stream<S, AbomonableArray2<T>>,
+    partitions: &Stream<S, TrainingData<T, L>>,
+    k: usize,
+    metric: D,
+) -> Stream<S, Result<Vec<Neighbors<T, L>>, KnnError>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar<Real = T> + Float,
+    L: ExchangeData + Copy,
+    D: Distance,
+{
+    let worker_index = samples.scope().index();
+    let peers = samples.scope().peers();
+
+    // tag each batch with its origin, so that the candidates can be sent back
+    let batches = samples
+        .unary(Pipeline, "TagBatches", move |_, _| {
+            let mut next_batch: usize = 0;
+            move |input, output| {
+                input.for_each(|time, data| {
+                    let mut session = output.session(&time);
+                    for samples in data.drain(..) {
+                        session.give((worker_index, next_batch, samples));
+                        next_batch += 1;
+                    }
+                });
+            }
+        })
+        .broadcast();
+
+    partitions
+        .apply_latest(
+            &batches,
+            move |_time, partition, (origin, batch, samples)| {
+                let neighbors = local_neighbors(&metric, k, partition, samples.view());
+                (origin, batch, neighbors)
+            },
+        )
+        .exchange(|&(origin, _, _)| origin as u64)
+        .unary(Pipeline, "MergeNeighbors", move |_, _| {
+            let mut pending: HashMap<usize, (_, usize, Option<_>)> = HashMap::new();
+            move |input, output| {
+                input.for_each(|time, data| {
+                    for (_origin, batch, neighbors) in data.drain(..) {
+                        let complete = {
+                            let entry = pending
+                                .entry(batch)
+                                .or_insert_with(|| (time.delayed(time.time()), 0, None));
+                            entry.1 += 1;
+                            entry.2 = Some(match entry.2.take() {
+                                Some(merged) => merge_neighbors(merged, neighbors, k),
+                                None => neighbors,
+                            });
+                            entry.1 == peers
+                        };
+
+                        // all workers have sent their candidates
+                        if complete {
+                            let (cap, _, neighbors) = pending.remove(&batch).unwrap();
+                            output.session(&cap).give(neighbors.unwrap());
+                        }
+                    }
+                });
+            }
+        })
+}
+
+fn predictions<T, L, P>(
+    neighbors: Result<Vec<Neighbors<T, L>>, KnnError>,
+    predict: impl Fn(&[(T, L, f64)]) -> P,
+) -> Result<AbomonableArray1<P>, ModelError<KnnError>> {
+    let neighbors = neighbors.map_err(ModelError::PredictionFailed)?;
+    if neighbors.iter().any(|n| n.is_empty()) {
+        return Err(ModelError::PredictionFailed(KnnError::NoTrainingData));
+    }
+    Ok(neighbors
+        .iter()
+        .map(|n| predict(n))
+        .collect::<Array1<_>>()
+        .into())
+}
+
+fn majority_vote<T, L: DiscreteValue>(neighbors: &[(T, L, f64)]) -> L {
+    // the labels are ordered by their nearest neighbor
+    let mut votes: Vec<(L, f64)> = Vec::new();
+    for &(_, label, weight) in neighbors {
+        match votes.iter_mut().find(|(l, _)| *l == label) {
+            Some(vote) => vote.1 += weight,
+            None => votes.push((label, weight)),
+        }
+    }
+    votes
+        .into_iter()
+        .fold(None, |best: Option<(L, f64)>, vote| match best {
+            Some(best) if best.1 >= vote.1 => Some(best),
+            _ => Some(vote),
+        })
+        .expect("At least one neighbor")
+        .0
+}
+
+fn mean<T: Float>(neighbors: &[(T, T, f64)]) -> T {
+    let (sum, total_weight) = neighbors.iter().fold(
+        (T::zero(), T::zero()),
+        |(sum, total_weight), &(_, y, weight)| {
+            let weight: T = cast(weight).unwrap();
+            (sum + weight * y, total_weight + weight)
+        },
+    );
+    sum / total_weight
+}
+
+impl<S, T, L, D> Train<S, KnnClassifier<T, L, D>> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar<Real = T> + Float,
+    L: DiscreteValue,
+    D: Distance,
+{
+    fn train(&self, _model: &KnnClassifier<T, L, D>) -> Stream<S, TrainingData<T, L>> {
+        collect_partitions(self)
+    }
+}
+
+impl<S, T, L, D> Predict<S, KnnClassifier<T, L, D>, KnnError> for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar<Real = T> + Float,
+    L: DiscreteValue,
+    D: Distance,
+{
+    fn predict(
+        &self,
+        model: &KnnClassifier<T, L, D>,
+        train_results: Stream<S, TrainingData<T, L>>,
+    ) -> Stream<S, Result<AbomonableArray1<L>, ModelError<KnnError>>> {
+        search_neighbors(self, &train_results, model.k, model.metric.clone())
+            .map(|neighbors| predictions(neighbors, majority_vote))
+    }
+}
+
+impl<S, T, D> Train<S, KnnRegressor<T, D>> for Stream<S, TrainingData<T, T>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar<Real = T> + Float,
+    D: Distance,
+{
+    fn train(&self, _model: &KnnRegressor<T, D>) -> Stream<S, TrainingData<T, T>> {
+        collect_partitions(self)
+    }
+}
+
+impl<S, T, D> Predict<S, KnnRegressor<T, D>, KnnError> for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar<Real = T> + Float,
+    D: Distance,
+{
+    fn predict(
+        &self,
+        model: &KnnRegressor<T, D>,
+        train_results: Stream<S, TrainingData<T, T>>,
+    ) -> Stream<S, Result<AbomonableArray1<T>, ModelError<KnnError>>> {
+        search_neighbors(self, &train_results, model.k, model.metric.clone())
+            .map(|neighbors| predictions(neighbors, mean))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use models::distance::{Euclidean, Manhattan};
+    use std::sync::{Arc, Mutex};
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+    use timely::progress::timestamp::RootTimestamp;
+    use timely_communication::initialize::Configuration;
+
+    fn example() -> TrainingData<f64, i64> {
+        TrainingData {
+            x: arr2(&[[0., 0.], [1., 0.], [0., 1.], [5., 5.], [6., 5.], [5., 6.]]).into(),
+            y: arr1(&[0, 0, 1, 1, 1, 1]).into(),
+            weights: None,
+        }
+    }
+
+    #[test]
+    fn local_and_merged_neighbors() {
+        let data = example();
+        let samples = arr2(&[[0.2, 0.], [5., 5.2]]);
+        let first = TrainingData {
+            x: data.x().slice(s![..3, ..]).to_owned().into(),
+            y: data.y().slice(s![..3]).to_owned().into(),
+            weights: None,
+        };
+        let second = TrainingData {
+            x: data.x().slice(s![3.., ..]).to_owned().into(),
+            y: data.y().slice(s![3..]).to_owned().into(),
+            weights: None,
+        };
+
+        let neighbors = merge_neighbors(
+            local_neighbors(&Manhattan, 2, &first, samples.view()),
+            local_neighbors(&Manhattan, 2, &second, samples.view()),
+            2,
+        )
+        .unwrap();
+        let labels = neighbors
+            .iter()
+            .map(|n| n.iter().map(|&(_, label, _)| label).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec![vec![0, 0], vec![1, 1]]);
+        assert!(abs_diff_eq!(neighbors[0][0].0, &0.2, epsilon = 1e-10));
+
+        assert!(local_neighbors(&Manhattan, 2, &first, arr2(&[[1.]]).view()).is_err());
+    }
+
+    #[test]
+    fn vote_and_mean() {
+        assert_eq!(
+            majority_vote(&[(0., 3, 1.), (1., 2, 1.), (2., 2, 1.), (3., 3, 1.)]),
+            3
+        );
+        assert_eq!(majority_vote(&[(0., 3, 1.), (1., 2, 1.), (2., 2, 1.)]), 2);
+        assert_eq!(mean(&[(0., 1., 1.), (1., 2., 1.), (2., 6., 1.)]), 3.);
+    }
+
+    #[test]
+    fn weighted_neighbors() {
+        assert_eq!(majority_vote(&[(0., 3, 2.5), (1., 2, 1.), (2., 2, 1.)]), 3);
+        assert_eq!(mean(&[(0., 1., 2.), (1., 4., 1.)]), 2.);
+
+        // samples without weight are not neighbors
+        let data = TrainingData {
+            x: arr2(&[[0.], [1.], [2.]]).into(),
+            y: arr1(&[0, 1, 1]).into(),
+            weights: None,
+        }
+        .with_weights(arr1(&[0., 1., 3.]));
+        let neighbors = local_neighbors(&Manhattan, 2, &data, arr2(&[[0.]]).view()).unwrap();
+        assert_eq!(neighbors, vec![vec![(1., 1, 1.), (2., 1, 3.)]]);
+    }
+
+    #[test]
+    fn concatenate_weights() {
+        let data = example();
+        let first = TrainingData {
+            x: data.x().slice(s![..2, ..]).to_owned().into(),
+            y: data.y().slice(s![..2]).to_owned().into(),
+            weights: None,
+        };
+        let second = TrainingData {
+            x: data.x().slice(s![2.., ..]).to_owned().into(),
+            y: data.y().slice(s![2..]).to_owned().into(),
+            weights: None,
+        }
+        .with_weights(arr1(&[2., 0.5, 0., 1.]));
+
+        let merged = concatenate(vec![first, second]);
+        assert_eq!(merged.weights().unwrap(), arr1(&[1., 1., 2., 0.5, 0., 1.]));
+        assert!(concatenate(vec![example()]).weights.is_none());
+    }
+
+    #[test]
+    fn no_training_data() {
+        let neighbors: Result<Vec<Neighbors<f64, i64>>, _> = Ok(vec![vec![]]);
+        assert!(predictions(neighbors, majority_vote).is_err());
+    }
+
+    #[test]
+    fn classify_streams() {
+        let result = ::timely::example(|scope| {
+            let data = example();
+            let parts = vec![
+                TrainingData {
+                    x: data.x().slice(s![..2, ..]).to_owned().into(),
+                    y: data.y().slice(s![..2]).to_owned().into(),
+                    weights: None,
+                },
+                TrainingData {
+                    x: data.x().slice(s![2.., ..]).to_owned().into(),
+                    y: data.y().slice(s![2..]).to_owned().into(),
+                    weights: None,
+                },
+            ];
+
+            let model = KnnClassifier::new(3, Euclidean);
+            let partitions = parts.to_stream(scope).train(&model);
+            vec![AbomonableArray2::from(arr2(&[[0.5, 0.2], [4., 4.]]))]
+                .to_stream(scope)
+                .predict(&model, partitions)
+                .map(|predictions| predictions.unwrap().view().to_vec())
+                .capture()
+        })
+        .extract();
+
+        assert_eq!(result, vec![(RootTimestamp::new(0), vec![vec![0, 1]])]);
+    }
+
+    #[test]
+    fn regress_streams() {
+        let result = ::timely::example(|scope| {
+            let data = TrainingData {
+                x: arr2(&[[0.], [1.], [2.], [10.]]).into(),
+                y: arr1(&[0., 2., 4., 20.]).into(),
+                weights: None,
+            };
+
+            let model = KnnRegressor::new(2, Euclidean);
+            let partitions = vec![data].to_stream(scope).train(&model);
+            vec![AbomonableArray2::from(arr2(&[[0.4], [9.]]))]
+                .to_stream(scope)
+                .predict(&model, partitions)
+                .map(|predictions| {
+                    predictions
+                        .unwrap()
+                        .view()
+                        .iter()
+                        .map(|v| v.round() as i64)
+                        .collect::<Vec<_>>()
+                })
+                .capture()
+        })
+        .extract();
+
+        assert_eq!(result, vec![(RootTimestamp::new(0), vec![vec![1, 12]])]);
+    }
+
+    #[test]
+    fn classify_across_workers() {
+        let predicted = Arc::new(Mutex::new(Vec::new()));
+        let predicted_by_workers = predicted.clone();
+
+        ::timely::execute(Configuration::Process(2), move |root| {
+            let worker = root.index();
+            let predicted = predicted_by_workers.clone();
+            root.dataflow::<u64, _, _>(|scope| {
+                // the nearest neighbors of each sample are spread across both workers
+                let data = example();
+                let (rows, samples) = if worker == 0 {
+                    (vec![0, 3, 4], arr2(&[[0.5, 0.2]]))
+                } else {
+                    (vec![1, 2, 5], arr2(&[[4., 4.]]))
+                };
+                let part = TrainingData {
+                    x: data.x().select(Axis(0), &rows).into(),
+                    y: data.y().select(Axis(0), &rows).into(),
+                    weights: None,
+                };
+
+                let model = KnnClassifier::new(3, Euclidean);
+                let partitions = vec![part].to_stream(scope).train(&model);
+                vec![AbomonableArray2::from(samples)]
+                    .to_stream(scope)
+                    .predict(&model, partitions)
+                    .inspect(move |predictions| {
+                        let labels = predictions.as_ref().unwrap().view().to_vec();
+                        predicted.lock().unwrap().push((worker, labels));
+                    });
+            });
+        })
+        .expect("Execute dataflow");
+
+        let mut predicted = predicted.lock().unwrap().clone();
+        predicted.sort();
+        assert_eq!(predicted, vec![(0, vec![0]), (1, vec![1])]);
+    }
+}
@@ -0,0 +1,769 @@
+//! Logistic regression, trained with synchronous mini-batch gradient descent.
+//!
+//! In each iteration, the current weights are broadcast to all workers, which sum up the
+//! gradients of the log-loss over a random mini-batch of their local samples. The sums are
+//! merged on the first worker, which takes a gradient step and applies the regularization.
+//! Two classes are modeled with a single weight vector and the logistic function, more
+//! classes with one weight vector per class and the softmax function (multinomial).
+
+use super::LinearModelError;
+use data::dataflow::random::seeded_rng;
+use data::dataflow::{ApplyLatest, InitEachTime, ReduceEachTime};
+use data::serialization::*;
+use data::TrainingData;
+use fnv::FnvHashMap;
+use models::kmeans::{ConvergenceCheck, StopCondition};
+use models::*;
+use ndarray::prelude::*;
+use ndarray::ScalarOperand;
+use num_traits::{cast::cast, Float};
+use rand::Rng;
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::*;
+use timely::dataflow::{Scope, Stream};
+use timely::progress::nested::product::Product;
+use timely::progress::Timestamp;
+use timely::{Data, ExchangeData};
+
+/// Convergence criteria for gradient descent
+#[derive(Default, Clone, Abomonation)]
+pub struct SgdConvergenceCriteria<T> {
+    pub max_iterations: Option<usize>,
+    pub min_weight_change: Option<T>,
+}
+
+impl<T> SgdConvergenceCriteria<T> {
+    /// Adds an iteration limit.
+    pub fn limit_iterations(mut self, iterations: usize) -> Self {
+        self.max_iterations = Some(iterations);
+        self
+    }
+
+    /// Abort if the euclidean distance between the weights of the current and
+    /// previous iteration is smaller than the given change.
+    pub fn weight_change(mut self, min_change: T) -> Self {
+        self.min_weight_change = Some(min_change);
+        self
+    }
+}
+
+impl<T: Float> ConvergenceCheck<T> for SgdConvergenceCriteria<T> {
+    fn converges<'a, 'b>(
+        &self,
+        old: &ArrayView2<'a, T>,
+        new: &ArrayView2<'b, T>,
+        iteration: usize,
+    ) -> bool
+    where
+        T: 'a + 'b,
+    {
+        if let Some(max_iterations) = self.max_iterations {
+            if max_iterations <= iteration {
+                return true;
+            }
+        }
+
+        if let Some(min_change) = self.min_weight_change {
+            let distance = old
+                .iter()
+                .zip(new.iter())
+                .fold(T::zero(), |sum, (&o, &n)| sum + (n - o) * (n - o))
+                .sqrt();
+            return distance <= min_change;
+        }
+
+        false
+    }
+}
+
+/// Linear classifier that models the probability of each class
+#[derive(Abomonation, Clone)]
+pub struct LogisticRegression<L, T> {
+    classes: Vec<L>,
+    cols: usize,
+    end_criteria: SgdConvergenceCriteria<T>,
+    learning_rate: T,
+    batch_size: usize,
+    l1: T,
+    l2: T,
+    seed: u64,
+}
+
+impl<L: PartialEq, T: Float> LogisticRegression<L, T> {
+    /// Creates a model for samples with `cols` features, which belong to one of the given
+    /// (at least two) classes. By default, a learning rate of 0.1 and mini-batches of 100
+    /// samples per worker are used without regularization.
+    pub fn new(classes: Vec<L>, cols: usize, end_criteria: SgdConvergenceCriteria<T>) -> Self {
+        assert!(classes.len() >= 2, "At least two classes are required");
+        LogisticRegression {
+            classes,
+            cols,
+            end_criteria,
+            learning_rate: cast(0.1).unwrap(),
+            batch_size: 100,
+            l1: T::zero(),
+            l2: T::zero(),
+            seed: 0,
+        }
+    }
+
+    /// Sets the step size of each iteration.
+    pub fn learning_rate(mut self, learning_rate: T) -> Self {
+        assert!(learning_rate > T::zero(), "Learning rate must be positive");
+        self.learning_rate = learning_rate;
+        self
+    }
+
+    /// Sets the number of samples that each worker draws in each iteration. If a worker has
+    /// fewer samples, all of them are used.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "Batch size must be positive");
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Sets the strength of the L1 regularization, which drives small weights to zero.
+    pub fn l1(mut self, l1: T) -> Self {
+        assert!(l1 >= T::zero(), "Regularization must not be negative");
+        self.l1 = l1;
+        self
+    }
+
+    /// Sets the strength of the L2 regularization.
+    pub fn l2(mut self, l2: T) -> Self {
+        assert!(l2 >= T::zero(), "Regularization must not be negative");
+        self.l2 = l2;
+        self
+    }
+
+    /// Sets the seed for the random selection of the mini-batches.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Number of weight vectors, one for binary classification and one per class otherwise
+    fn outputs(&self) -> usize {
+        if self.classes.len() == 2 {
+            1
+        } else {
+            self.classes.len()
+        }
+    }
+
+    /// Samples with the targets of the model outputs and the sample weights. Samples whose label
+    /// is not one of the classes of the model are skipped.
+    fn sample_chunk(&self, data: TrainingData<T, L>) -> SampleChunk<T> {
+        let mut rows = Vec::with_capacity(data.y().len());
+        let mut classes = Vec::with_capacity(data.y().len());
+        for (row, label) in data.y().iter().enumerate() {
+            match self.class_index(row, label) {
+                Ok(class) => {
+                    rows.push(row);
+                    classes.push(class);
+                }
+                Err(err) => warn!("Skipping training sample: {}", err),
+            }
+        }
+
+        let outputs = self.outputs();
+        let mut targets = Array2::zeros((classes.len(), outputs));
+        for (mut target, &class) in targets.outer_iter_mut().zip(classes.iter()) {
+            if outputs == 1 {
+                target[0] = cast(class).unwrap();
+            } else {
+                target[class] = T::one();
+            }
+        }
+
+        if rows.len() == data.y().len() {
+            (data.x, targets.into(), data.weights)
+        } else {
+            let x = data.x().select(Axis(0), &rows);
+            let weights = data.weights().map(|w| w.select(Axis(0), &rows).into());
+            (x.into(), targets.into(), weights)
+        }
+    }
+
+    /// Index of the class of a label, fails if the label is not one of the classes of the model
+    fn class_index(&self, row: usize, label: &L) -> Result<usize, ModelError<LinearModelError>> {
+        self.classes
+            .iter()
+            .position(|class| class == label)
+            .ok_or(ModelError::TrainingFailed(LinearModelError::UnknownLabel(
+                row,
+            )))
+    }
+}
+
+/// Classes and weights of a trained logistic regression. The last row of the weights
+/// contains the intercepts.
+#[derive(Abomonation, Clone, Debug)]
+pub struct LogisticParams<L, T> {
+    pub classes: Vec<L>,
+    pub weights: AbomonableArray2<T>,
+}
+
+/// Model outputs for the samples: the probability of the second class if there is only one
+/// weight vector, the probability of each class otherwise
+fn model_outputs<T: Float + ScalarOperand>(
+    weights: ArrayView2<T>,
+    samples: ArrayView2<T>,
+) -> Array2<T> {
+    let cols = weights.rows() - 1;
+    let mut outputs = samples.dot(&weights.slice(s![..cols, ..])) + &weights.row(cols);
+
+    if outputs.cols() == 1 {
+        outputs.mapv_inplace(|z| T::one() / (T::one() + (-z).exp()));
+    } else {
+        for mut row in outputs.outer_iter_mut() {
+            let max = row.fold(T::neg_infinity(), |max, &z| max.max(z));
+            row.mapv_inplace(|z| (z - max).exp());
+            let sum = row.fold(T::zero(), |sum, &z| sum + z);
+            row.mapv_inplace(|z| z / sum);
+        }
+    }
+    outputs
+}
+
+impl<L: Data, T: Float + ScalarOperand> LogisticParams<L, T> {
+    fn check_features(&self, samples: &ArrayView2<T>) -> Result<(), ModelError<LinearModelError>> {
+        let cols = self.weights.view().rows() - 1;
+        if samples.cols() != cols {
+            return Err(ModelError::PredictionFailed(
+                LinearModelError::FeatureMismatch(cols, samples.cols()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Probability of each class of the model (columns) for each sample (rows)
+    fn class_probabilities(&self, samples: ArrayView2<T>) -> Array2<T> {
+        let outputs = model_outputs(self.weights.view(), samples);
+        if outputs.cols() == 1 {
+            let mut probabilities = Array2::zeros((outputs.rows(), 2));
+            for (mut row, &p) in probabilities.outer_iter_mut().zip(outputs.iter()) {
+                row[0] = T::one() - p;
+                row[1] = p;
+            }
+            probabilities
+        } else {
+            outputs
+        }
+    }
+}
+
+impl<L: ExchangeData, T: ExchangeData> ModelAttributes for LogisticRegression<L, T> {
+    type TrainingResult = LogisticParams<L, T>;
+}
+
+impl<L: ExchangeData, T: ExchangeData> LabelingModelAttributes for LogisticRegression<L, T> {
+    type Predictions = AbomonableArray1<L>;
+    type PredictErr = LinearModelError;
+}
+
+/// Sum of the gradients of the log-loss over some samples
+#[derive(Abomonation, Clone, Debug)]
+struct GradientSum<T> {
+    /// The weights for which the gradients were computed, only sent by the first worker
+    weights: Option<AbomonableArray2<T>>,
+    gradient: AbomonableArray2<T>,
+    /// Total weight of the samples
+    sample_weight: T,
+}
+
+impl<T: Float + ScalarOperand> GradientSum<T> {
+    fn merge(self, other: Self) -> Self {
+        let gradient: Array2<T> = self.gradient.into();
+        GradientSum {
+            weights: self.weights.or(other.weights),
+            gradient: (gradient + &other.gradient.view()).into(),
+            sample_weight: self.sample_weight + other.sample_weight,
+        }
+    }
+}
+
+/// Chunk of samples with the targets of the model outputs and the optional sample weights
+type SampleChunk<T> = (
+    AbomonableArray2<T>,
+    AbomonableArray2<T>,
+    Option<AbomonableArray1<f64>>,
+);
+
+trait GradientStep<S: Scope, T: Data> {
+    fn gradient_step(
+        &self,
+        training_data: &Stream<S, SampleChunk<T>>,
+        batch_size: usize,
+        seed: u64,
+    ) -> Stream<S, GradientSum<T>>;
+}
+
+impl<S, Ts, T> GradientStep<S, T> for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope<Timestamp = Product<Ts, usize>>,
+    Ts: Timestamp,
+    T: Data + Float + ScalarOperand,
+{
+    fn gradient_step(
+        &self,
+        training_data: &Stream<S, SampleChunk<T>>,
+        batch_size: usize,
+        seed: u64,
+    ) -> Stream<S, GradientSum<T>> {
+        let worker = self.scope().index();
+        self.binary_frontier(
+            training_data,
+            Pipeline,
+            Pipeline,
+            "GradientStep",
+            move |_, _| {
+                let mut data_stash = FnvHashMap::default();
+                let mut weights_stash = Vec::new();
+
+                move |in_weights, in_data, out| {
+                    in_weights.for_each(|time, data| {
+                        weights_stash.push((time.retain(), data.drain(..).collect::<Vec<_>>()));
+                    });
+
+                    in_data.for_each(|time, data| {
+                        assert_eq!(time.inner, 0);
+                        data_stash
+                            .entry(time.time().outer.clone())
+                            .or_insert_with(Vec::new)
+                            .extend(data.drain(..));
+                    });
+
+                    let frontiers = [in_weights.frontier(), in_data.frontier()];
+                    for (cap, weights_list) in &mut weights_stash {
+                        // if neither input can produce data at `time`, compute the gradients
+                        if frontiers.iter().all(|f| !f.less_equal(cap.time())) {
+                            let mut session = out.session(&cap);
+                            let no_data = Vec::new();
+                            let data = data_stash.get(&cap.time().outer).unwrap_or(&no_data);
+                            for weights in weights_list.drain(..) {
+                                let rows = data
+                                    .iter()
+                                    .map(|&(ref x, _, _)| x.view().rows())
+                                    .sum::<usize>();
+
+                                // the rows of all local chunks that form the mini-batch
+                                let batch = if rows <= batch_size {
+                                    (0..rows).collect::<Vec<_>>()
+                                } else {
+                                    let mut rng = seeded_rng(seed, &(worker, cap.time()));
+                                    (0..batch_size).map(|_| rng.gen_range(0, rows)).collect()
+                                };
+
+                                let (gradient, sample_weight) =
+                                    batch_gradient(weights.view(), data, batch.iter().cloned());
+                                session.give(GradientSum {
+                                    weights: if worker == 0 { Some(weights) } else { None },
+                                    gradient: gradient.into(),
+                                    sample_weight,
+                                });
+                            }
+                        }
+                    }
+
+                    weights_stash.retain(|(_, list)| !list.is_empty());
+
+                    // the samples are needed until no more weights of their outer time can arrive
+                    data_stash.retain(|outer, _| {
+                        frontiers
+                            .iter()
+                            .any(|f| f.frontier().iter().any(|time| time.outer.less_equal(outer)))
+                    });
+                }
+            },
+        )
+    }
+}
+
+/// Sum of the gradients of the log-loss over the given rows of the chunks of samples and
+/// targets, weighted by the sample weights, and the total weight of the rows
+fn batch_gradient<T: Float + ScalarOperand>(
+    weights: ArrayView2<T>,
+    data: &[SampleChunk<T>],
+    batch: impl Iterator<Item = usize>,
+) -> (Array2<T>, T) {
+    let cols = weights.rows() - 1;
+    let mut gradient = Array2::zeros(weights.dim());
+    let mut total_weight = T::zero();
+    let offsets = data
+        .iter()
+        .scan(0, |offset, &(ref x, _, _)| {
+            let start = *offset;
+            *offset += x.view().rows();
+            Some(start)
+        })
+        .collect::<Vec<_>>();
+
+    for row in batch {
+        // the last chunk that starts before the row, skipping empty chunks
+        let chunk = offsets
+            .iter()
+            .rposition(|&start| start <= row)
+            .expect("Row in a chunk");
+        let (ref x, ref targets, ref sample_weights) = data[chunk];
+        let (x, targets) = (x.view(), targets.view());
+        let index = row - offsets[chunk];
+        let sample = x.slice(s![index..index + 1, ..]);
+        let weight = sample_weights
+            .as_ref()
+            .map_or(T::one(), |w| cast(w.view()[index]).unwrap());
+
+        // the gradient of the log-loss with respect to the outputs is `p - t`
+        let error = model_outputs(weights, sample).row(0).to_owned() - &targets.row(index);
+        for (i, &value) in sample.row(0).iter().enumerate() {
+            gradient.row_mut(i).scaled_add(weight * value, &error);
+        }
+        gradient.row_mut(cols).scaled_add(weight, &error);
+        total_weight = total_weight + weight;
+    }
+    (gradient, total_weight)
+}
+
+impl<S, L, T> Train<S, LogisticRegression<L, T>> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    L: ExchangeData + PartialEq,
+    T: ExchangeData + Float + ScalarOperand + Debug,
+{
+    fn train(&self, model: &LogisticRegression<L, T>) -> Stream<S, LogisticParams<L, T>> {
+        let max_iterations = model
+            .end_criteria
+            .max_iterations
+            .unwrap_or(<usize>::max_value());
+        let end_criteria = model.end_criteria.clone();
+        let (batch_size, seed) = (model.batch_size, model.seed);
+        let (learning_rate, l1, l2) = (model.learning_rate, model.l1, model.l2);
+        let classes = model.classes.clone();
+
+        let targets_model = model.clone();
+        let samples = self.map(move |data| targets_model.sample_chunk(data));
+
+        let initial_weights: AbomonableArray2<T> =
+            Array2::zeros((model.cols + 1, model.outputs())).into();
+        let initial_weights =
+            vec![initial_weights].init_each_time(&self.map(|_| ()).exchange(|_| 0u64));
+
+        self.scope()
+            .scoped(|inner_scope| {
+                let (loop_handle, loop_stream) = inner_scope.loop_variable(max_iterations, 1);
+
+                let (done, next_iteration) = initial_weights
+                    .enter(inner_scope)
+                    .concat(&loop_stream)
+                    // checks whether the convergence criteria are met and aborts the loop
+                    .stop_condition(end_criteria);
+
+                next_iteration
+                    .broadcast()
+                    .gradient_step(&samples.enter(inner_scope), batch_size, seed)
+                    .reduce_each_time(GradientSum::merge)
+                    .map(move |sum| {
+                        let mut weights: Array2<T> =
+                            sum.weights.expect("Weights of iteration").into();
+                        let cols = weights.rows() - 1;
+                        // the mean gradient of the samples, weighted by the sample weights
+                        let total_weight = if sum.sample_weight > T::zero() {
+                            sum.sample_weight
+                        } else {
+                            T::one()
+                        };
+
+                        weights.scaled_add(-learning_rate / total_weight, &sum.gradient.view());
+                        // the intercepts are not regularized
+                        weights.slice_mut(s![..cols, ..]).mapv_inplace(|w| {
+                            let w = w * (T::one() - learning_rate * l2);
+                            // soft thresholding, the proximal step of the L1 regularization
+                            w.signum() * (w.abs() - learning_rate * l1).max(T::zero())
+                        });
+                        weights.into()
+                    })
+                    .connect_loop(loop_handle);
+
+                done.leave()
+            })
+            .map(move |weights| LogisticParams {
+                classes: classes.clone(),
+                weights,
+            })
+    }
+}
+
+impl<S, L, T> Predict<S, LogisticRegression<L, T>, LinearModelError>
+    for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    L: ExchangeData + Copy,
+    T: ExchangeData + Float + ScalarOperand,
+{
+    fn predict(
+        &self,
+        _model: &LogisticRegression<L, T>,
+        train_results: Stream<S, LogisticParams<L, T>>,
+    ) -> Stream<S, Result<AbomonableArray1<L>, ModelError<LinearModelError>>> {
+        train_results.apply_latest(self, |_time, params, samples| {
+            params.predict_samples(&samples)
+        })
+    }
+}
+
+impl<A, L, T> PredictSamples<A, AbomonableArray1<L>, LinearModelError> for LogisticParams<L, T>
+where
+    for<'a> &'a A: AsArray<'a, T, Ix2>,
+    L: Data + Copy,
+    T: Float + ScalarOperand,
+{
+    fn predict_samples(
+        &self,
+        samples: &A,
+    ) -> Result<AbomonableArray1<L>, ModelError<LinearModelError>> {
+        let samples: ArrayView2<T> = samples.into();
+        self.check_features(&samples)?;
+
+        let labels = self
+            .class_probabilities(samples)
+            .outer_iter()
+            .map(|probabilities| {
+                let class = probabilities
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Less))
+                    .map(|(class, _)| class)
+                    .expect("At least two classes");
+                self.classes[class]
+            })
+            .collect::<Array1<_>>();
+        Ok(labels.into())
+    }
+}
+
+impl<A, L, T> PredictProbabilities<A, L, LinearModelError> for LogisticParams<L, T>
+where
+    for<'a> &'a A: AsArray<'a, T, Ix2>,
+    L: Data + PartialEq,
+    T: Float + ScalarOperand,
+{
+    /// Classes that the model was not trained on have probability 0.
+    fn predict_probabilities(
+        &self,
+        samples: &A,
+        classes: &[L],
+    ) -> Result<AbomonableArray2<f64>, ModelError<LinearModelError>> {
+        let samples: ArrayView2<T> = samples.into();
+        self.check_features(&samples)?;
+
+        let class_probabilities = self.class_probabilities(samples);
+        let mut probabilities = Array2::zeros((class_probabilities.rows(), classes.len()));
+        for (j, class) in classes.iter().enumerate() {
+            if let Some(i) = self.classes.iter().position(|c| c == class) {
+                probabilities
+                    .column_mut(j)
+                    .assign(&class_probabilities.column(i).mapv(|p| p.to_f64().unwrap()));
+            }
+        }
+        Ok(probabilities.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::stack;
+    use timely::dataflow::operators::capture::Extract;
+
+    fn separable() -> TrainingData<f64, u8> {
+        TrainingData {
+            x: arr2(&[
+                [-2., 1.],
+                [-1., -1.],
+                [-1.5, 0.],
+                [1., 1.],
+                [2., -1.],
+                [1.5, 0.],
+            ])
+            .into(),
+            y: arr1(&[0, 0, 0, 1, 1, 1]).into(),
+            weights: None,
+        }
+    }
+
+    fn train(
+        model: LogisticRegression<u8, f64>,
+        data: TrainingData<f64, u8>,
+    ) -> LogisticParams<u8, f64> {
+        let classes = model.classes.clone();
+        let shape = (model.cols + 1, model.outputs());
+        let result = ::timely::example(move |scope| {
+            vec![data.clone()]
+                .to_stream(scope)
+                .train(&model)
+                .map(|params| {
+                    params
+                        .weights
+                        .view()
+                        .iter()
+                        .map(|w| w.to_bits())
+                        .collect::<Vec<_>>()
+                })
+                .capture()
+        })
+        .extract();
+
+        let weights = result[0].1[0].iter().map(|&w| f64::from_bits(w)).collect();
+        LogisticParams {
+            classes,
+            weights: Array2::from_shape_vec(shape, weights).unwrap().into(),
+        }
+    }
+
+    #[test]
+    fn converges() {
+        let criteria = SgdConvergenceCriteria::default()
+            .limit_iterations(10)
+            .weight_change(0.5);
+        let old = arr2(&[[0., 0.], [1., 1.]]);
+        assert!(!criteria.converges(&old.view(), &arr2(&[[0., 1.], [1., 1.]]).view(), 1));
+        assert!(criteria.converges(&old.view(), &arr2(&[[0., 0.3], [1., 1.]]).view(), 1));
+        assert!(criteria.converges(&old.view(), &old.view(), 10));
+    }
+
+    #[test]
+    fn unknown_labels() {
+        let model =
+            LogisticRegression::<u8, f64>::new(vec![0, 1], 2, SgdConvergenceCriteria::default());
+        assert!(model.class_index(0, &1).is_ok());
+        assert!(model.class_index(0, &2).is_err());
+
+        let mut data = separable().with_weights(arr1(&[1., 2., 3., 4., 5., 6.]));
+        data.y_mut()[1] = 2;
+        data.y_mut()[4] = 3;
+        let (x, targets, weights) = model.sample_chunk(data);
+        assert_eq!(
+            x.view(),
+            arr2(&[[-2., 1.], [-1.5, 0.], [1., 1.], [1.5, 0.]])
+        );
+        assert_eq!(targets.view(), arr2(&[[0.], [0.], [1.], [1.]]));
+        assert_eq!(weights.unwrap().view(), arr1(&[1., 3., 4., 6.]));
+    }
+
+    #[test]
+    fn outputs_are_probabilities() {
+        let weights = arr2(&[[1., -1., 0.], [0., 2., 1.], [0.5, 0., -0.5]]);
+        let samples = arr2(&[[1., 2.], [-3., 0.5]]);
+        let outputs = model_outputs(weights.view(), samples.view());
+        for row in outputs.outer_iter() {
+            assert!(abs_diff_eq!(row.scalar_sum(), &1., epsilon = 1e-10));
+        }
+
+        let binary = model_outputs(weights.slice(s![.., ..1]), samples.view());
+        assert!(abs_diff_eq!(
+            binary[(0, 0)],
+            &(1. / (1. + (-1.5f64).exp())),
+            epsilon = 1e-10
+        ));
+    }
+
+    #[test]
+    fn binary_classification() {
+        let model = LogisticRegression::new(
+            vec![0, 1],
+            2,
+            SgdConvergenceCriteria::default().limit_iterations(200),
+        )
+        .learning_rate(1.);
+        let params = train(model, separable());
+
+        let data = separable();
+        let predictions = params.predict_samples(&data.x).unwrap();
+        assert_eq!(predictions, data.y);
+
+        let probabilities: Array2<f64> = params
+            .predict_probabilities(&data.x, &[1, 0, 5])
+            .unwrap()
+            .into();
+        assert!(probabilities[(0, 1)] > 0.9);
+        assert!(probabilities[(3, 0)] > 0.9);
+        assert_eq!(probabilities.column(2).scalar_sum(), 0.);
+    }
+
+    #[test]
+    fn weighted_samples() {
+        // a mislabeled sample without weight does not change the model
+        let data = separable();
+        let x = stack(Axis(0), &[data.x(), arr2(&[[5., 0.]]).view()]).unwrap();
+        let y = stack(Axis(0), &[data.y(), arr1(&[0]).view()]).unwrap();
+        let weights = arr1(&[1., 2., 1., 1., 0.5, 1., 0.]);
+        let weighted = TrainingData {
+            x: x.into(),
+            y: y.into(),
+            weights: None,
+        }
+        .with_weights(weights);
+
+        let model = LogisticRegression::new(
+            vec![0, 1],
+            2,
+            SgdConvergenceCriteria::default().limit_iterations(200),
+        )
+        .learning_rate(1.);
+        let params = train(model, weighted);
+        assert_eq!(params.predict_samples(&data.x).unwrap(), data.y);
+        assert_eq!(
+            params.predict_samples(&arr2(&[[5., 0.]])).unwrap().view()[0],
+            1
+        );
+    }
+
+    #[test]
+    fn multinomial_classification() {
+        let data = TrainingData {
+            x: arr2(&[
+                [0., 3.],
+                [0.5, 2.5],
+                [3., 0.],
+                [2.5, 0.5],
+                [-3., -3.],
+                [-2.5, -2.],
+            ])
+            .into(),
+            y: arr1(&[0, 0, 1, 1, 2, 2]).into(),
+            weights: None,
+        };
+        let model = LogisticRegression::new(
+            vec![0, 1, 2],
+            2,
+            SgdConvergenceCriteria::default().limit_iterations(200),
+        )
+        .learning_rate(0.5)
+        .batch_size(4)
+        .seed(3);
+        let params = train(model, data.clone());
+
+        let predictions = params.predict_samples(&data.x).unwrap();
+        assert_eq!(predictions, data.y);
+        assert!(params.predict_samples(&arr2(&[[1.]])).is_err());
+    }
+
+    #[test]
+    fn l1_regularization() {
+        // the second feature is irrelevant for the classification
+        let model = LogisticRegression::new(
+            vec![0, 1],
+            2,
+            SgdConvergenceCriteria::default().limit_iterations(200),
+        )
+        .learning_rate(1.)
+        .l1(0.1);
+        let params = train(model, separable());
+
+        let weights = params.weights.view();
+        assert!(weights[(0, 0)] > 0.);
+        assert_eq!(weights[(1, 0)], 0.);
+    }
+}
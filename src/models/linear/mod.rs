@@ -0,0 +1,43 @@
+//! Linear models, whose predictions depend on a weighted sum of the features.
+
+pub use self::logistic::*;
+pub use self::regression::*;
+use data::serialization::*;
+use ndarray::prelude::*;
+use ndarray::LinalgScalar;
+
+mod logistic;
+mod regression;
+
+#[derive(Fail, Debug, Abomonation, Clone)]
+pub enum LinearModelError {
+    #[fail(display = "Expected samples with {} features, got {}", _0, _1)]
+    FeatureMismatch(usize, usize),
+    #[fail(
+        display = "Label of sample {} is not one of the classes of the model",
+        _0
+    )]
+    UnknownLabel(usize),
+}
+
+/// Weights of the features and intercept of a linear model
+#[derive(Abomonation, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LinearParams<T> {
+    pub coefficients: AbomonableArray1<T>,
+    pub intercept: T,
+}
+
+impl<T: LinalgScalar> LinearParams<T> {
+    /// Weighted sum of the features of each sample, plus the intercept
+    pub fn decision_function(&self, samples: ArrayView2<T>) -> Result<Array1<T>, LinearModelError> {
+        let coefficients = self.coefficients.view();
+        if samples.cols() != coefficients.len() {
+            return Err(LinearModelError::FeatureMismatch(
+                coefficients.len(),
+                samples.cols(),
+            ));
+        }
+        let intercept = self.intercept;
+        Ok(samples.dot(&coefficients).mapv(|v| v + intercept))
+    }
+}
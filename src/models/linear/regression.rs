@@ -0,0 +1,345 @@
+//! Least squares linear regression, solved with the normal equations.
+//!
+//! Each worker sums up `XᵀWX` and `XᵀWy` of its samples, where `W` contains the sample
+//! weights on its diagonal. These sums are merged on the first worker, which solves
+//! `(XᵀWX + αI) w = XᵀWy` for the coefficients `w` and broadcasts them to all workers.
+//! Only a single pass over the data is necessary, and the amount of data sent between
+//! workers is quadratic in the number of features, but independent of the number of samples.
+
+use super::{LinearModelError, LinearParams};
+use data::dataflow::{ApplyLatest, ReduceEachTime};
+use data::serialization::*;
+use data::TrainingData;
+use models::*;
+use ndarray::prelude::*;
+use ndarray_linalg::{Eigh, Scalar, UPLO};
+use num_traits::cast::cast;
+use num_traits::Float;
+use timely::dataflow::operators::{Broadcast, Map};
+use timely::dataflow::{Scope, Stream};
+use timely::ExchangeData;
+
+/// Linear regression with optional L2 regularization of the coefficients (ridge regression)
+#[derive(Abomonation, Clone, Debug)]
+pub struct LinearRegression<T> {
+    l2: T,
+    fit_intercept: bool,
+}
+
+impl<T: Float> LinearRegression<T> {
+    /// Creates an ordinary least squares regression with an intercept
+    pub fn new() -> Self {
+        LinearRegression {
+            l2: T::zero(),
+            fit_intercept: true,
+        }
+    }
+
+    /// Sets the strength of the L2 regularization. The intercept is not regularized.
+    /// Without regularization, linearly dependent features result in the least squares
+    /// solution with the smallest coefficients.
+    pub fn ridge(mut self, l2: T) -> Self {
+        assert!(l2 >= T::zero(), "Regularization must not be negative");
+        self.l2 = l2;
+        self
+    }
+
+    /// Sets whether an intercept is fitted. Otherwise, the regression line passes
+    /// through the origin.
+    pub fn fit_intercept(mut self, fit_intercept: bool) -> Self {
+        self.fit_intercept = fit_intercept;
+        self
+    }
+}
+
+impl<T: Float> Default for LinearRegression<T> {
+    fn default() -> Self {
+        LinearRegression::new()
+    }
+}
+
+impl<T: ExchangeData> ModelAttributes for LinearRegression<T> {
+    type TrainingResult = LinearParams<T>;
+}
+
+impl<T: ExchangeData> LabelingModelAttributes for LinearRegression<T> {
+    type Predictions = AbomonableArray1<T>;
+    type PredictErr = LinearModelError;
+}
+
+/// Sums of the normal equations of some samples
+#[derive(Abomonation, Clone, Debug)]
+struct NormalEquations<T> {
+    gram: AbomonableArray2<T>,
+    moments: AbomonableArray1<T>,
+}
+
+impl<T: Scalar<Real = T> + Float> NormalEquations<T> {
+    fn new(
+        x: ArrayView2<T>,
+        y: ArrayView1<T>,
+        weights: Option<ArrayView1<f64>>,
+        fit_intercept: bool,
+    ) -> Self {
+        let x = if fit_intercept {
+            // the intercept is the coefficient of an additional feature that is always 1
+            let mut with_intercept = Array2::ones((x.rows(), x.cols() + 1));
+            with_intercept.slice_mut(s![.., ..-1]).assign(&x);
+            with_intercept
+        } else {
+            x.to_owned()
+        };
+
+        // the squared error of each sample is multiplied by its weight. The transpose is
+        // built in a new array, since a copy of `x.t()` with a single row keeps strides that
+        // BLAS rejects as the leading dimension.
+        let weighted = Array2::from_shape_fn((x.cols(), x.rows()), |(feature, sample)| {
+            let weight: T = weights.map_or(T::one(), |weights| cast(weights[sample]).unwrap());
+            x[(sample, feature)] * weight
+        });
+
+        NormalEquations {
+            gram: weighted.dot(&x).into(),
+            moments: weighted.dot(&y).into(),
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        let gram: Array2<T> = self.gram.into();
+        let moments: Array1<T> = self.moments.into();
+        NormalEquations {
+            gram: (gram + &other.gram.view()).into(),
+            moments: (moments + &other.moments.view()).into(),
+        }
+    }
+
+    fn solve(self, l2: T, fit_intercept: bool) -> LinearParams<T> {
+        let mut gram: Array2<T> = self.gram.into();
+        let n = gram.rows();
+        let regularized = if fit_intercept { n - 1 } else { n };
+        for i in 0..regularized {
+            gram[(i, i)] = gram[(i, i)] + l2;
+        }
+
+        // solving with the pseudo-inverse gives the minimum norm solution if the equations
+        // are singular, e.g. for constant or collinear features or too few samples
+        let moments: Array1<T> = self.moments.into();
+        let (eigenvalues, eigenvectors) = gram
+            .eigh(UPLO::Upper)
+            .expect("Eigendecomposition of the normal equations failed");
+        let largest = eigenvalues
+            .iter()
+            .fold(T::zero(), |largest, &v| Float::max(largest, Float::abs(v)));
+        let tolerance = largest * <T as Float>::epsilon() * cast::<usize, T>(n).unwrap();
+
+        let mut weights = Array1::zeros(n);
+        for (i, &eigenvalue) in eigenvalues.iter().enumerate() {
+            if eigenvalue > tolerance {
+                let vector = eigenvectors.column(i);
+                weights.scaled_add(vector.dot(&moments) / eigenvalue, &vector);
+            }
+        }
+
+        if fit_intercept {
+            LinearParams {
+                coefficients: weights.slice(s![..-1]).to_owned().into(),
+                intercept: weights[n - 1],
+            }
+        } else {
+            LinearParams {
+                coefficients: weights.into(),
+                intercept: T::zero(),
+            }
+        }
+    }
+}
+
+impl<S, T> Train<S, LinearRegression<T>> for Stream<S, TrainingData<T, T>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar<Real = T> + Float,
+{
+    fn train(&self, model: &LinearRegression<T>) -> Stream<S, LinearParams<T>> {
+        let l2 = model.l2;
+        let fit_intercept = model.fit_intercept;
+
+        self.map(move |data| {
+            NormalEquations::new(data.x(), data.y(), data.weights(), fit_intercept)
+        })
+        .reduce_each_time(NormalEquations::merge)
+        .map(move |equations| equations.solve(l2, fit_intercept))
+        .broadcast()
+    }
+}
+
+impl<S, T> Predict<S, LinearRegression<T>, LinearModelError> for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ExchangeData + Scalar + Float,
+{
+    fn predict(
+        &self,
+        _model: &LinearRegression<T>,
+        train_results: Stream<S, LinearParams<T>>,
+    ) -> Stream<S, Result<AbomonableArray1<T>, ModelError<LinearModelError>>> {
+        train_results.apply_latest(self, |_time, params, samples| {
+            params.predict_samples(&samples)
+        })
+    }
+}
+
+impl<A, T> PredictSamples<A, AbomonableArray1<T>, LinearModelError> for LinearParams<T>
+where
+    for<'a> &'a A: AsArray<'a, T, Ix2>,
+    T: ExchangeData + Scalar + Float,
+{
+    fn predict_samples(
+        &self,
+        samples: &A,
+    ) -> Result<AbomonableArray1<T>, ModelError<LinearModelError>> {
+        self.decision_function(samples.into())
+            .map(Into::into)
+            .map_err(ModelError::PredictionFailed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+    use timely::progress::timestamp::RootTimestamp;
+
+    fn example() -> (Array2<f64>, Array1<f64>) {
+        let x = arr2(&[[1., 2.], [2., 0.], [3., 1.], [4., 5.], [0., 1.]]);
+        let y = &x.column(0) * 2. - &x.column(1) + 3.;
+        (x, y)
+    }
+
+    fn fit(
+        model: &LinearRegression<f64>,
+        x: ArrayView2<f64>,
+        y: ArrayView1<f64>,
+    ) -> LinearParams<f64> {
+        // fit from two separate parts to check merging
+        let first = NormalEquations::new(
+            x.slice(s![..2, ..]),
+            y.slice(s![..2]),
+            None,
+            model.fit_intercept,
+        );
+        let second = NormalEquations::new(
+            x.slice(s![2.., ..]),
+            y.slice(s![2..]),
+            None,
+            model.fit_intercept,
+        );
+        first.merge(second).solve(model.l2, model.fit_intercept)
+    }
+
+    #[test]
+    fn exact_fit() {
+        let (x, y) = example();
+        let params = fit(&LinearRegression::new(), x.view(), y.view());
+        let coefficients = params.coefficients.view();
+        assert!(abs_diff_eq!(coefficients[0], &2., epsilon = 1e-8));
+        assert!(abs_diff_eq!(coefficients[1], &-1., epsilon = 1e-8));
+        assert!(abs_diff_eq!(params.intercept, &3., epsilon = 1e-8));
+
+        let predictions: Array1<f64> = params.predict_samples(&x).unwrap().into();
+        for (prediction, expected) in predictions.iter().zip(y.iter()) {
+            assert!(abs_diff_eq!(prediction, expected, epsilon = 1e-8));
+        }
+    }
+
+    #[test]
+    fn ridge() {
+        let x = arr2(&[[1.], [2.], [3.]]);
+        let y = arr1(&[2., 4., 6.]);
+        let model = LinearRegression::new().ridge(1.).fit_intercept(false);
+        let params = fit(&model, x.view(), y.view());
+
+        // w = Σxy / (Σx² + α)
+        assert!(abs_diff_eq!(
+            params.coefficients.view()[0],
+            &(28. / 15.),
+            epsilon = 1e-10
+        ));
+        assert_eq!(params.intercept, 0.);
+    }
+
+    #[test]
+    fn collinear_features() {
+        let x = arr2(&[[1., 1.], [2., 2.], [3., 3.]]);
+        let y = arr1(&[2., 4., 6.]);
+        let params = fit(&LinearRegression::new(), x.view(), y.view());
+
+        // the minimum norm solution spreads the weight evenly across the copies
+        let coefficients = params.coefficients.view();
+        assert!(abs_diff_eq!(coefficients[0], &1., epsilon = 1e-8));
+        assert!(abs_diff_eq!(coefficients[1], &1., epsilon = 1e-8));
+        assert!(abs_diff_eq!(params.intercept, &0., epsilon = 1e-8));
+    }
+
+    #[test]
+    fn weighted_samples() {
+        let x = arr2(&[[0.], [1.], [2.], [3.]]);
+        let y = arr1(&[0., 1., 2., 100.]);
+
+        // the outlier is ignored, the other samples are fitted exactly
+        let weights = arr1(&[1., 2., 0.5, 0.]);
+        let params =
+            NormalEquations::new(x.view(), y.view(), Some(weights.view()), true).solve(0., true);
+        assert!(abs_diff_eq!(
+            params.coefficients.view()[0],
+            &1.,
+            epsilon = 1e-8
+        ));
+        assert!(abs_diff_eq!(params.intercept, &0., epsilon = 1e-8));
+    }
+
+    #[test]
+    fn feature_mismatch() {
+        let (x, y) = example();
+        let params = fit(&LinearRegression::new(), x.view(), y.view());
+        assert!(params.predict_samples(&arr2(&[[1.]])).is_err());
+    }
+
+    #[test]
+    fn train_and_predict_streams() {
+        let result = ::timely::example(|scope| {
+            let (x, y) = example();
+            let training_data = vec![
+                TrainingData {
+                    x: x.slice(s![..3, ..]).to_owned().into(),
+                    y: y.slice(s![..3]).to_owned().into(),
+                    weights: None,
+                },
+                TrainingData {
+                    x: x.slice(s![3.., ..]).to_owned().into(),
+                    y: y.slice(s![3..]).to_owned().into(),
+                    weights: None,
+                },
+            ]
+            .to_stream(scope);
+
+            let model = LinearRegression::new();
+            let params = training_data.train(&model);
+            vec![AbomonableArray2::from(arr2(&[[10., 10.], [-1., 2.]]))]
+                .to_stream(scope)
+                .predict(&model, params)
+                .map(|predictions| {
+                    predictions
+                        .unwrap()
+                        .view()
+                        .iter()
+                        .map(|v| v.round() as i64)
+                        .collect::<Vec<_>>()
+                })
+                .capture()
+        })
+        .extract();
+
+        assert_eq!(result, vec![(RootTimestamp::new(0), vec![vec![13, -1]])]);
+    }
+}
@@ -1,16 +1,27 @@
+use data::serialization::AbomonableArray2;
 use timely::ExchangeData;
 use failure::Fail;
 use timely::dataflow::{Scope, Stream};
 use timely::Data;
 
 pub mod decision_tree;
+pub mod distance;
+pub mod gmm;
 pub mod gradient_boost;
+pub mod isolation_forest;
 pub mod kmeans;
+pub mod knn;
+pub mod linear;
+pub mod model_selection;
+pub mod naive_bayes;
+pub mod pipeline;
 
 #[derive(Fail, Debug, Abomonation, Clone)]
 pub enum ModelError<Inner: Data + Fail> {
     #[fail(display = "Prediction failed: {}", _0)]
     PredictionFailed(#[cause] Inner),
+    #[fail(display = "Training failed: {}", _0)]
+    TrainingFailed(#[cause] Inner),
 }
 
 pub trait ModelAttributes: ExchangeData {
@@ -41,3 +52,13 @@ pub trait Predict<S: Scope, M: LabelingModelAttributes, E: Data + Fail> {
 pub trait PredictSamples<Samples, Predictions, E: Data + Fail> {
     fn predict_samples(&self, input: &Samples) -> Result<Predictions, ModelError<E>>;
 }
+
+pub trait PredictProbabilities<Samples, L, E: Data + Fail> {
+    /// Predicts the probability of each of the given classes for each sample. Rows of the
+    /// result correspond to the samples, columns to the classes in the order they were given.
+    fn predict_probabilities(
+        &self,
+        input: &Samples,
+        classes: &[L],
+    ) -> Result<AbomonableArray2<f64>, ModelError<E>>;
+}
@@ -0,0 +1,226 @@
+//! K-Fold Cross-Validation of supervised models.
+
+use data::dataflow::error_measures::AggregateMeasure;
+use data::dataflow::{CombineEachTime, ReduceEachTime, SplitTrainingData};
+use data::serialization::*;
+use data::TrainingData;
+use fnv::FnvHashMap;
+use models::{LabelingModelAttributes, ModelError, PredictSamples, Train};
+use num_traits::{Float, NumCast};
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Broadcast, Concat, Filter, Map, Operator};
+use timely::dataflow::{Scope, Stream};
+use timely::{Data, ExchangeData};
+
+/// Errors of a model on each fold of a cross-validation, and their average
+#[derive(Abomonation, Clone, Debug, PartialEq)]
+pub struct CrossValidationResult<E> {
+    /// Error on each fold, or `None` if the fold had no test samples
+    pub fold_errors: Vec<Option<E>>,
+    /// Average error of the folds with test samples
+    pub mean_error: E,
+}
+
+pub trait CrossValidate<S: Scope, T: Data, L: Data> {
+    /// Splits the training data into `folds` folds. For each fold, the model is trained on the
+    /// samples of all other folds and then evaluated on the samples of the fold with the given
+    /// error measure. The result is emitted on the first worker once per timestamp, unless
+    /// none of the folds contain test samples at that timestamp.
+    ///
+    /// The error of a fold is computed from the merged statistics of all chunks of test data,
+    /// so it is exact regardless of how the test data is distributed across workers. If the
+    /// prediction of any test samples fails, the error is emitted instead.
+    fn cross_validate<M, Em, E>(
+        &self,
+        model: &M,
+        folds: usize,
+        seed: u64,
+        error_measure: Em,
+    ) -> Stream<S, Result<CrossValidationResult<E>, ModelError<M::PredictErr>>>
+    where
+        M: LabelingModelAttributes<Predictions = AbomonableArray1<L>>,
+        M::PredictErr: ExchangeData,
+        M::TrainingResult:
+            ExchangeData + PredictSamples<AbomonableArray2<T>, AbomonableArray1<L>, M::PredictErr>,
+        Stream<S, TrainingData<T, L>>: Train<S, M>,
+        Em: AggregateMeasure<L, E> + 'static,
+        Em::Statistics: ExchangeData,
+        E: ExchangeData + Float;
+}
+
+impl<S, T, L> CrossValidate<S, T, L> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: Data + Copy,
+    L: Data + Copy,
+{
+    fn cross_validate<M, Em, E>(
+        &self,
+        model: &M,
+        folds: usize,
+        seed: u64,
+        _error_measure: Em,
+    ) -> Stream<S, Result<CrossValidationResult<E>, ModelError<M::PredictErr>>>
+    where
+        M: LabelingModelAttributes<Predictions = AbomonableArray1<L>>,
+        M::PredictErr: ExchangeData,
+        M::TrainingResult:
+            ExchangeData + PredictSamples<AbomonableArray2<T>, AbomonableArray1<L>, M::PredictErr>,
+        Stream<S, TrainingData<T, L>>: Train<S, M>,
+        Em: AggregateMeasure<L, E> + 'static,
+        Em::Statistics: ExchangeData,
+        E: ExchangeData + Float,
+    {
+        let assigned = self.assign_folds(folds, seed);
+
+        let fold_errors = (0..folds)
+            .map(|fold| {
+                let train = assigned
+                    .filter(move |&(f, _)| f != fold)
+                    .map(|(_, data)| data);
+                let test = assigned
+                    .filter(move |&(f, _)| f == fold)
+                    .map(|(_, data)| data);
+
+                train
+                    .train(model)
+                    .broadcast()
+                    // the test data is evaluated with the model trained at the same time
+                    .combine_each_time(&test, |training_results, test_data| {
+                        let training_result = training_results.drain(..).last();
+                        test_data
+                            .drain(..)
+                            .filter_map(|data| {
+                                let statistics = training_result
+                                    .as_ref()?
+                                    .predict_samples(&data.x)
+                                    .map(|predictions| {
+                                        Em::statistics(&predictions.view(), &data.y())
+                                    });
+                                Some(statistics)
+                            })
+                            .collect()
+                    })
+                    .reduce_each_time(|first, second| Ok(Em::merge(first?, second?)))
+                    .map(move |statistics| (fold, statistics.map(|s| Em::finish(&s))))
+            })
+            .collect::<Vec<_>>();
+
+        let mut stash = FnvHashMap::default();
+        fold_errors
+            .iter()
+            .skip(1)
+            .fold(fold_errors[0].clone(), |all, errors| all.concat(errors))
+            .unary_notify(
+                Pipeline,
+                "CombineFoldErrors",
+                vec![],
+                move |input, output, notificator| {
+                    input.for_each(|time, data| {
+                        let errors = stash
+                            .entry(time.time().clone())
+                            .or_insert_with(|| vec![None; folds]);
+                        for (fold, error) in data.drain(..) {
+                            errors[fold] = Some(error);
+                        }
+                        notificator.notify_at(time.retain());
+                    });
+
+                    notificator.for_each(|time, _, _| {
+                        if let Some(result) = stash.remove(time.time()).and_then(combine_folds) {
+                            output.session(&time).give(result);
+                        }
+                    });
+                },
+            )
+    }
+}
+
+/// Combines the results of all folds, unless none of the folds have test samples. The first
+/// failed fold fails the whole cross-validation.
+fn combine_folds<E: Float, Err>(
+    results: Vec<Option<Result<E, Err>>>,
+) -> Option<Result<CrossValidationResult<E>, Err>> {
+    let mut fold_errors = Vec::with_capacity(results.len());
+    for result in results {
+        match result {
+            Some(Ok(error)) => fold_errors.push(Some(error)),
+            Some(Err(err)) => return Some(Err(err)),
+            None => fold_errors.push(None),
+        }
+    }
+
+    let errors = fold_errors
+        .iter()
+        .filter_map(|&error| error)
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        return None;
+    }
+    let mean_error = errors.iter().fold(E::zero(), |acc, &error| acc + error)
+        / NumCast::from(errors.len()).unwrap();
+
+    Some(Ok(CrossValidationResult {
+        fold_errors,
+        mean_error,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::dataflow::error_measures::Rmse;
+    use models::linear::LinearRegression;
+    use ndarray::prelude::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::{Capture, ToStream};
+    use timely::progress::timestamp::RootTimestamp;
+
+    #[test]
+    fn exact_model_on_every_fold() {
+        let result = ::timely::example(|scope| {
+            // y = 2x + 1 is fitted exactly from the training samples of any fold
+            let x = Array2::from_shape_fn((40, 1), |(row, _)| row as f64);
+            let y = x.column(0).mapv(|x| 2. * x + 1.);
+            vec![TrainingData {
+                x: x.into(),
+                y: y.into(),
+                weights: None,
+            }]
+            .to_stream(scope)
+            .cross_validate(&LinearRegression::new(), 4, 1, Rmse)
+            .map(|result| {
+                let result = result.unwrap();
+                let exact = result
+                    .fold_errors
+                    .iter()
+                    .all(|error| error.map_or(false, |error| error < 1e-6));
+                (result.fold_errors.len(), exact, result.mean_error < 1e-6)
+            })
+            .capture()
+        })
+        .extract();
+
+        assert_eq!(result, vec![(RootTimestamp::new(0), vec![(4, true, true)])]);
+    }
+
+    #[test]
+    fn skip_folds_without_test_samples() {
+        let result = combine_folds::<f64, ()>(vec![Some(Ok(1.)), None, Some(Ok(3.))]).unwrap();
+        assert_eq!(
+            result,
+            Ok(CrossValidationResult {
+                fold_errors: vec![Some(1.), None, Some(3.)],
+                mean_error: 2.,
+            })
+        );
+
+        assert_eq!(combine_folds::<f64, ()>(vec![None, None]), None);
+    }
+
+    #[test]
+    fn fail_with_any_fold() {
+        let result = combine_folds(vec![Some(Ok(1.)), None, Some(Err("Prediction failed"))]);
+        assert_eq!(result, Some(Err("Prediction failed")));
+    }
+}
@@ -0,0 +1,7 @@
+//! Tools for evaluating models and selecting their parameters.
+
+pub mod cross_validation;
+pub mod search;
+
+pub use self::cross_validation::{CrossValidate, CrossValidationResult};
+pub use self::search::{ParameterGrid, ParameterSet, RandomSearch, SearchParameters, SearchResult};
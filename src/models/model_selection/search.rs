@@ -0,0 +1,389 @@
+//! Grid and random search over the parameters of a model, scored by cross-validation.
+
+use data::dataflow::error_measures::AggregateMeasure;
+use data::dataflow::random::seeded_rng;
+use data::serialization::*;
+use data::TrainingData;
+use fnv::FnvHashMap;
+use models::model_selection::cross_validation::{CrossValidate, CrossValidationResult};
+use models::{LabelingModelAttributes, ModelError, PredictSamples, Train};
+use num_traits::Float;
+use rand::Rng;
+use std::cmp::Ordering;
+use std::fmt;
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::{Concat, Map, Operator};
+use timely::dataflow::{Scope, Stream};
+use timely::{Data, ExchangeData};
+
+/// A set of named parameter values, from which a model instance is built
+#[derive(Abomonation, Clone, Debug, Default, PartialEq)]
+pub struct ParameterSet {
+    values: Vec<(String, f64)>,
+}
+
+impl ParameterSet {
+    /// Sets the value of a parameter
+    pub fn set<N: Into<String>>(mut self, name: N, value: f64) -> Self {
+        let name = name.into();
+        self.values.retain(|(existing, _)| *existing != name);
+        self.values.push((name, value));
+        self
+    }
+
+    /// Value of the parameter with the given name
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.values
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|&(_, value)| value)
+    }
+}
+
+impl fmt::Display for ParameterSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (name, value)) in self.values.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}={}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parameter space that contains every combination of the given values for each parameter
+#[derive(Clone, Debug, Default)]
+pub struct ParameterGrid {
+    axes: Vec<(String, Vec<f64>)>,
+}
+
+impl ParameterGrid {
+    pub fn new() -> Self {
+        ParameterGrid::default()
+    }
+
+    /// Adds a parameter with the values to try
+    pub fn axis<N: Into<String>>(mut self, name: N, values: Vec<f64>) -> Self {
+        self.axes.push((name.into(), values));
+        self
+    }
+
+    /// All combinations of parameter values
+    pub fn candidates(&self) -> Vec<ParameterSet> {
+        self.axes
+            .iter()
+            .fold(vec![ParameterSet::default()], |candidates, (name, values)| {
+                candidates
+                    .iter()
+                    .flat_map(|candidate| {
+                        values
+                            .iter()
+                            .map(move |&value| candidate.clone().set(name.clone(), value))
+                    })
+                    .collect()
+            })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Range {
+    Uniform(f64, f64),
+    LogUniform(f64, f64),
+    Choice(Vec<f64>),
+}
+
+/// Parameter space from which a number of random parameter sets are drawn
+#[derive(Clone, Debug, Default)]
+pub struct RandomSearch {
+    axes: Vec<(String, Range)>,
+}
+
+impl RandomSearch {
+    pub fn new() -> Self {
+        RandomSearch::default()
+    }
+
+    /// Adds a parameter that is drawn uniformly from `[low, high)`
+    pub fn uniform<N: Into<String>>(mut self, name: N, low: f64, high: f64) -> Self {
+        assert!(low < high, "The range must not be empty");
+        self.axes.push((name.into(), Range::Uniform(low, high)));
+        self
+    }
+
+    /// Adds a parameter whose logarithm is drawn uniformly from `[ln(low), ln(high))`.
+    /// Useful for parameters that vary across orders of magnitude, like learning rates.
+    pub fn log_uniform<N: Into<String>>(mut self, name: N, low: f64, high: f64) -> Self {
+        assert!(low > 0., "The range must only contain positive values");
+        assert!(low < high, "The range must not be empty");
+        self.axes.push((name.into(), Range::LogUniform(low, high)));
+        self
+    }
+
+    /// Adds a parameter that is drawn from a fixed set of values
+    pub fn choice<N: Into<String>>(mut self, name: N, values: Vec<f64>) -> Self {
+        assert!(!values.is_empty(), "At least one value is required");
+        self.axes.push((name.into(), Range::Choice(values)));
+        self
+    }
+
+    /// Draws `n` parameter sets. The same seed always results in the same parameter sets.
+    pub fn candidates(&self, n: usize, seed: u64) -> Vec<ParameterSet> {
+        (0..n)
+            .map(|i| {
+                let mut rng = seeded_rng(seed, &i);
+                self.axes
+                    .iter()
+                    .fold(ParameterSet::default(), |candidate, (name, range)| {
+                        let value = match range {
+                            Range::Uniform(low, high) => rng.gen_range(*low, *high),
+                            Range::LogUniform(low, high) => {
+                                rng.gen_range(low.ln(), high.ln()).exp()
+                            }
+                            Range::Choice(values) => values[rng.gen_range(0, values.len())],
+                        };
+                        candidate.set(name.clone(), value)
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Cross-validation results of all candidates of a parameter search, ordered from the
+/// lowest to the highest mean error, and the model built from the best candidate
+#[derive(Abomonation, Clone, Debug)]
+pub struct SearchResult<M, E> {
+    pub ranking: Vec<(ParameterSet, CrossValidationResult<E>)>,
+    pub best_model: M,
+}
+
+pub trait SearchParameters<S: Scope, T: Data, L: Data> {
+    /// Builds a model for each candidate parameter set using `build_model`, and scores
+    /// it with a k-fold cross-validation on the training data. The result is emitted on
+    /// the first worker once per timestamp. If the cross-validation of any candidate fails,
+    /// its error is emitted instead.
+    fn search_parameters<M, F, Em, E>(
+        &self,
+        candidates: &[ParameterSet],
+        build_model: F,
+        folds: usize,
+        seed: u64,
+        error_measure: Em,
+    ) -> Stream<S, Result<SearchResult<M, E>, ModelError<M::PredictErr>>>
+    where
+        M: LabelingModelAttributes<Predictions = AbomonableArray1<L>>,
+        M::PredictErr: ExchangeData,
+        M::TrainingResult:
+            ExchangeData + PredictSamples<AbomonableArray2<T>, AbomonableArray1<L>, M::PredictErr>,
+        Stream<S, TrainingData<T, L>>: Train<S, M>,
+        F: Fn(&ParameterSet) -> M,
+        Em: AggregateMeasure<L, E> + Clone + 'static,
+        Em::Statistics: ExchangeData,
+        E: ExchangeData + Float;
+}
+
+impl<S, T, L> SearchParameters<S, T, L> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: Data + Copy,
+    L: Data + Copy,
+{
+    fn search_parameters<M, F, Em, E>(
+        &self,
+        candidates: &[ParameterSet],
+        build_model: F,
+        folds: usize,
+        seed: u64,
+        error_measure: Em,
+    ) -> Stream<S, Result<SearchResult<M, E>, ModelError<M::PredictErr>>>
+    where
+        M: LabelingModelAttributes<Predictions = AbomonableArray1<L>>,
+        M::PredictErr: ExchangeData,
+        M::TrainingResult:
+            ExchangeData + PredictSamples<AbomonableArray2<T>, AbomonableArray1<L>, M::PredictErr>,
+        Stream<S, TrainingData<T, L>>: Train<S, M>,
+        F: Fn(&ParameterSet) -> M,
+        Em: AggregateMeasure<L, E> + Clone + 'static,
+        Em::Statistics: ExchangeData,
+        E: ExchangeData + Float,
+    {
+        assert!(!candidates.is_empty(), "At least one candidate is required");
+        let candidates = candidates.to_vec();
+        let models = candidates.iter().map(&build_model).collect::<Vec<_>>();
+
+        let scores = models
+            .iter()
+            .enumerate()
+            .map(|(i, model)| {
+                // every candidate is evaluated on the same folds
+                self.cross_validate(model, folds, seed, error_measure.clone())
+                    .map(move |result| (i, result))
+            })
+            .collect::<Vec<_>>();
+
+        let mut stash = FnvHashMap::default();
+        scores
+            .iter()
+            .skip(1)
+            .fold(scores[0].clone(), |all, candidate_scores| {
+                all.concat(candidate_scores)
+            })
+            .unary_notify(
+                Pipeline,
+                "RankCandidates",
+                vec![],
+                move |input, output, notificator| {
+                    input.for_each(|time, data| {
+                        stash
+                            .entry(time.time().clone())
+                            .or_insert_with(Vec::new)
+                            .extend(data.drain(..));
+                        notificator.notify_at(time.retain());
+                    });
+
+                    notificator.for_each(|time, _, _| {
+                        if let Some(scores) = stash.remove(time.time()) {
+                            let scores = scores
+                                .into_iter()
+                                .map(|(i, result)| result.map(|result| (i, result)))
+                                .collect::<Result<Vec<_>, _>>();
+                            let mut scores = match scores {
+                                Ok(scores) => scores,
+                                Err(err) => {
+                                    output.session(&time).give(Err(err));
+                                    return;
+                                }
+                            };
+                            scores.sort_by(|(_, a), (_, b)| {
+                                compare_errors(a.mean_error, b.mean_error)
+                            });
+
+                            let best_model = models[scores[0].0].clone();
+                            let ranking = scores
+                                .into_iter()
+                                .map(|(i, result)| (candidates[i].clone(), result))
+                                .collect();
+
+                            output.session(&time).give(Ok(SearchResult {
+                                ranking,
+                                best_model,
+                            }));
+                        }
+                    });
+                },
+            )
+    }
+}
+
+/// Orders errors from lowest to highest, with NaN errors last
+fn compare_errors<E: Float>(a: E, b: E) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => a.partial_cmp(&b).unwrap(),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::dataflow::error_measures::Rmse;
+    use models::linear::LinearRegression;
+    use ndarray::prelude::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::{Capture, ToStream};
+
+    #[test]
+    fn grid_candidates() {
+        let candidates = ParameterGrid::new()
+            .axis("levels", vec![2., 3.])
+            .axis("learning_rate", vec![0.1, 0.2, 0.3])
+            .candidates();
+
+        assert_eq!(candidates.len(), 6);
+        assert_eq!(candidates[0].get("levels"), Some(2.));
+        assert_eq!(candidates[0].get("learning_rate"), Some(0.1));
+        assert_eq!(candidates[5].get("levels"), Some(3.));
+        assert_eq!(candidates[5].get("learning_rate"), Some(0.3));
+        assert_eq!(candidates[5].get("bins"), None);
+    }
+
+    #[test]
+    fn random_candidates() {
+        let search = RandomSearch::new()
+            .uniform("trim_ratio", 0.5, 1.)
+            .log_uniform("learning_rate", 0.001, 1.)
+            .choice("bins", vec![8., 16., 32.]);
+
+        let candidates = search.candidates(20, 5);
+        assert_eq!(candidates, search.candidates(20, 5));
+        assert_ne!(candidates, search.candidates(20, 6));
+
+        for candidate in &candidates {
+            let trim_ratio = candidate.get("trim_ratio").unwrap();
+            let learning_rate = candidate.get("learning_rate").unwrap();
+            assert!(trim_ratio >= 0.5 && trim_ratio < 1.);
+            assert!(learning_rate >= 0.001 && learning_rate < 1.);
+            assert!([8., 16., 32.].contains(&candidate.get("bins").unwrap()));
+        }
+    }
+
+    #[test]
+    fn rank_by_cross_validation() {
+        let result = ::timely::example(|scope| {
+            // y = 2x + 1 is fitted exactly without regularization, stronger
+            // regularization shrinks the slope further
+            let x = Array2::from_shape_fn((40, 1), |(row, _)| row as f64);
+            let y = x.column(0).mapv(|x| 2. * x + 1.);
+            let candidates = ParameterGrid::new()
+                .axis("l2", vec![1000., 0., 10.])
+                .candidates();
+
+            vec![TrainingData {
+                x: x.into(),
+                y: y.into(),
+                weights: None,
+            }]
+            .to_stream(scope)
+            .search_parameters(
+                &candidates,
+                |parameters| LinearRegression::new().ridge(parameters.get("l2").unwrap()),
+                4,
+                1,
+                Rmse,
+            )
+            .map(|result| {
+                let result = result.unwrap();
+                let errors = result
+                    .ranking
+                    .iter()
+                    .map(|(_, result)| result.mean_error)
+                    .collect::<Vec<_>>();
+                let ranked = result
+                    .ranking
+                    .iter()
+                    .map(|(parameters, _)| parameters.get("l2").unwrap() as i64)
+                    .collect::<Vec<_>>();
+                let ascending = errors[0] < 1e-6 && errors[0] < errors[1] && errors[1] < errors[2];
+                (ranked, ascending)
+            })
+            .capture()
+        })
+        .extract();
+
+        assert_eq!(result[0].1, vec![(vec![0, 10, 1000], true)]);
+    }
+
+    #[test]
+    fn nan_errors_last() {
+        let mut errors = vec![2., ::std::f64::NAN, 1.];
+        errors.sort_by(|&a, &b| compare_errors(a, b));
+        assert_eq!(&errors[..2], &[1., 2.]);
+        assert!(errors[2].is_nan());
+    }
+
+    #[test]
+    #[should_panic]
+    fn log_uniform_positive() {
+        RandomSearch::new().log_uniform("learning_rate", 0., 1.);
+    }
+}
@@ -0,0 +1,417 @@
+use super::{ClassDensities, NaiveBayesError, NaiveBayesParams};
+use data::dataflow::{ApplyLatest, ReduceEachTime};
+use data::serialization::*;
+use data::TrainingData;
+use models::decision_tree::histogram_generics::{ContinuousValue, DiscreteValue};
+use models::*;
+use ndarray::prelude::*;
+use num_traits::cast::cast;
+use std::f64::consts::PI;
+use std::marker::PhantomData;
+use timely::dataflow::operators::{Broadcast, Map};
+use timely::dataflow::{Scope, Stream};
+
+/// Naive Bayes classifier that assumes normally distributed features within each class
+#[derive(Abomonation, Clone, Debug)]
+pub struct GaussianNaiveBayes<T, L> {
+    var_smoothing: T,
+    _l: PhantomData<L>,
+}
+
+impl<T: ContinuousValue, L> GaussianNaiveBayes<T, L> {
+    pub fn new() -> Self {
+        GaussianNaiveBayes {
+            var_smoothing: cast(1e-9).unwrap(),
+            _l: PhantomData,
+        }
+    }
+
+    /// Sets the portion of the largest variance of all features that is added to the
+    /// variances, which keeps constant features from producing infinite densities
+    pub fn var_smoothing(mut self, var_smoothing: T) -> Self {
+        assert!(
+            var_smoothing >= T::zero(),
+            "Variance smoothing must not be negative"
+        );
+        self.var_smoothing = var_smoothing;
+        self
+    }
+}
+
+impl<T: ContinuousValue, L> Default for GaussianNaiveBayes<T, L> {
+    fn default() -> Self {
+        GaussianNaiveBayes::new()
+    }
+}
+
+impl<T: ContinuousValue, L: DiscreteValue> ModelAttributes for GaussianNaiveBayes<T, L> {
+    type TrainingResult = NaiveBayesParams<L, GaussianDensities<T>>;
+}
+
+impl<T: ContinuousValue, L: DiscreteValue> LabelingModelAttributes for GaussianNaiveBayes<T, L> {
+    type Predictions = AbomonableArray1<L>;
+    type PredictErr = NaiveBayesError;
+}
+
+/// Mean and variance of each feature (inner) for each class (outer)
+#[derive(Abomonation, Clone, Debug, PartialEq)]
+pub struct GaussianDensities<T> {
+    pub mean: Vec<Vec<T>>,
+    pub variance: Vec<Vec<T>>,
+}
+
+impl<T: ContinuousValue> ClassDensities for GaussianDensities<T> {
+    type Value = T;
+
+    fn features(&self) -> usize {
+        self.mean.first().map_or(0, |mean| mean.len())
+    }
+
+    fn log_density(&self, class: usize, sample: ArrayView1<T>) -> T {
+        let two_pi: T = cast(2. * PI).unwrap();
+        let half: T = cast(0.5).unwrap();
+        sample
+            .iter()
+            .zip(&self.mean[class])
+            .zip(&self.variance[class])
+            .map(|((&x, &mean), &variance)| {
+                -half * ((two_pi * variance).ln() + (x - mean) * (x - mean) / variance)
+            })
+            .sum()
+    }
+}
+
+/// Total weight, weighted mean and weighted sum of squared differences from the mean of
+/// each feature of some samples
+#[derive(Abomonation, Clone, Debug, PartialEq)]
+struct WeightedMoments<T> {
+    weight: T,
+    mean: Vec<T>,
+    squared_deviations: Vec<T>,
+}
+
+impl<T: ContinuousValue> WeightedMoments<T> {
+    /// Moments of the samples, which must have a positive total weight
+    fn new(x: ArrayView2<T>, weights: &[T]) -> Self {
+        let weight = weights.iter().cloned().sum::<T>();
+        let mean = x
+            .gencolumns()
+            .into_iter()
+            .map(|column| column.iter().zip(weights).map(|(&v, &w)| v * w).sum::<T>() / weight)
+            .collect::<Vec<_>>();
+        let squared_deviations = x
+            .gencolumns()
+            .into_iter()
+            .zip(&mean)
+            .map(|(column, &mean)| {
+                column
+                    .iter()
+                    .zip(weights)
+                    .map(|(&v, &w)| w * (v - mean) * (v - mean))
+                    .sum()
+            })
+            .collect();
+        WeightedMoments {
+            weight,
+            mean,
+            squared_deviations,
+        }
+    }
+
+    /// Combines the moments of two parts of the samples (Chan et al.)
+    fn merge(self, other: Self) -> Self {
+        let weight = self.weight + other.weight;
+        let mean = self
+            .mean
+            .iter()
+            .zip(&other.mean)
+            .map(|(&m1, &m2)| m1 + (m2 - m1) * other.weight / weight)
+            .collect();
+        let squared_deviations = self
+            .squared_deviations
+            .iter()
+            .zip(&other.squared_deviations)
+            .zip(self.mean.iter().zip(&other.mean))
+            .map(|((&s1, &s2), (&m1, &m2))| {
+                s1 + s2 + (m2 - m1) * (m2 - m1) * self.weight * other.weight / weight
+            })
+            .collect();
+        WeightedMoments {
+            weight,
+            mean,
+            squared_deviations,
+        }
+    }
+
+    /// Weighted population variance of each feature
+    fn variance(&self) -> Vec<T> {
+        self.squared_deviations
+            .iter()
+            .map(|&s| s / self.weight)
+            .collect()
+    }
+}
+
+/// Moments of the features for each label, sorted by label
+type ClassStatistics<T, L> = Vec<(L, WeightedMoments<T>)>;
+
+fn class_statistics<T: ContinuousValue, L: DiscreteValue>(
+    data: &TrainingData<T, L>,
+) -> ClassStatistics<T, L> {
+    let mut rows: Vec<(L, Vec<usize>, Vec<T>)> = Vec::new();
+    for (row, label) in data.y().iter().enumerate() {
+        // samples without weight do not contribute to the statistics
+        let weight = data.weight(row);
+        if weight == 0. {
+            continue;
+        }
+        let weight = cast(weight).unwrap();
+        match rows.binary_search_by(|(l, _, _)| l.cmp(label)) {
+            Ok(i) => {
+                rows[i].1.push(row);
+                rows[i].2.push(weight);
+            }
+            Err(i) => rows.insert(i, (*label, vec![row], vec![weight])),
+        }
+    }
+
+    let x = data.x();
+    rows.into_iter()
+        .map(|(label, rows, weights)| {
+            let statistics = WeightedMoments::new(x.select(Axis(0), &rows).view(), &weights);
+            (label, statistics)
+        })
+        .collect()
+}
+
+fn merge_class_statistics<T: ContinuousValue, L: DiscreteValue>(
+    mut first: ClassStatistics<T, L>,
+    second: ClassStatistics<T, L>,
+) -> ClassStatistics<T, L> {
+    for (label, statistics) in second {
+        match first.binary_search_by(|(l, _)| l.cmp(&label)) {
+            Ok(i) => {
+                let merged = first[i].1.clone().merge(statistics);
+                first[i].1 = merged;
+            }
+            Err(i) => first.insert(i, (label, statistics)),
+        }
+    }
+    first
+}
+
+fn fit<T: ContinuousValue, L: DiscreteValue>(
+    statistics: ClassStatistics<T, L>,
+    var_smoothing: T,
+) -> NaiveBayesParams<L, GaussianDensities<T>> {
+    // like the variances, the smoothing is relative to the scale of the features
+    let largest_variance = statistics
+        .iter()
+        .map(|(_, s)| s.clone())
+        .fold(None, |total, s| match total {
+            None => Some(s),
+            Some(total) => Some(total.merge(s)),
+        })
+        .map_or(T::zero(), |total| {
+            total.variance().into_iter().fold(T::zero(), T::max)
+        });
+    let epsilon = var_smoothing * largest_variance;
+
+    let classes = statistics
+        .iter()
+        .map(|(label, s)| (*label, s.weight.to_f64().unwrap()))
+        .collect();
+    let densities = GaussianDensities {
+        mean: statistics.iter().map(|(_, s)| s.mean.clone()).collect(),
+        variance: statistics
+            .iter()
+            .map(|(_, s)| s.variance().into_iter().map(|v| v + epsilon).collect())
+            .collect(),
+    };
+    NaiveBayesParams { classes, densities }
+}
+
+impl<S, T, L> Train<S, GaussianNaiveBayes<T, L>> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: ContinuousValue,
+    L: DiscreteValue,
+{
+    fn train(
+        &self,
+        model: &GaussianNaiveBayes<T, L>,
+    ) -> Stream<S, NaiveBayesParams<L, GaussianDensities<T>>> {
+        let var_smoothing = model.var_smoothing;
+
+        self.map(|data| class_statistics(&data))
+            .reduce_each_time(merge_class_statistics)
+            .map(move |statistics| fit(statistics, var_smoothing))
+            .broadcast()
+    }
+}
+
+impl<S, T, L> Predict<S, GaussianNaiveBayes<T, L>, NaiveBayesError>
+    for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ContinuousValue,
+    L: DiscreteValue,
+{
+    fn predict(
+        &self,
+        _model: &GaussianNaiveBayes<T, L>,
+        train_results: Stream<S, NaiveBayesParams<L, GaussianDensities<T>>>,
+    ) -> Stream<S, Result<AbomonableArray1<L>, ModelError<NaiveBayesError>>> {
+        train_results.apply_latest(self, |_time, params, samples| {
+            params.predict_samples(&samples)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+    use timely::progress::timestamp::RootTimestamp;
+
+    fn example() -> TrainingData<f64, i64> {
+        TrainingData {
+            x: arr2(&[
+                [1., 0.],
+                [2., 1.],
+                [3., 0.],
+                [10., 1.],
+                [11., 0.],
+                [12., 1.],
+            ])
+            .into(),
+            y: arr1(&[0, 0, 0, 1, 1, 1]).into(),
+            weights: None,
+        }
+    }
+
+    fn split(data: &TrainingData<f64, i64>, at: usize) -> Vec<TrainingData<f64, i64>> {
+        vec![
+            TrainingData {
+                x: data.x().slice(s![..at, ..]).to_owned().into(),
+                y: data.y().slice(s![..at]).to_owned().into(),
+                weights: None,
+            },
+            TrainingData {
+                x: data.x().slice(s![at.., ..]).to_owned().into(),
+                y: data.y().slice(s![at..]).to_owned().into(),
+                weights: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn merged_statistics() {
+        let data = example();
+        let parts = split(&data, 4);
+        let merged =
+            merge_class_statistics(class_statistics(&parts[0]), class_statistics(&parts[1]));
+        let params = fit(merged, 0.);
+
+        assert_eq!(params.classes, vec![(0, 3.), (1, 3.)]);
+        let expected = GaussianDensities {
+            mean: vec![vec![2., 1. / 3.], vec![11., 2. / 3.]],
+            variance: vec![vec![2. / 3., 2. / 9.], vec![2. / 3., 2. / 9.]],
+        };
+        for (class, expected_class) in params.densities.mean.iter().zip(&expected.mean) {
+            for (value, expected) in class.iter().zip(expected_class) {
+                assert!(abs_diff_eq!(value, expected, epsilon = 1e-10));
+            }
+        }
+        for (class, expected_class) in params.densities.variance.iter().zip(&expected.variance) {
+            for (value, expected) in class.iter().zip(expected_class) {
+                assert!(abs_diff_eq!(value, expected, epsilon = 1e-10));
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_samples() {
+        // doubling the weight of a sample is the same as adding it twice
+        let weighted = TrainingData {
+            x: arr2(&[[1., 0.], [2., 2.], [9., 1.]]).into(),
+            y: arr1(&[0, 0, 1]).into(),
+            weights: None,
+        }
+        .with_weights(arr1(&[2., 1., 0.5]));
+        let repeated = TrainingData {
+            x: arr2(&[[1., 0.], [1., 0.], [2., 2.], [9., 1.]]).into(),
+            y: arr1(&[0, 0, 0, 1]).into(),
+            weights: None,
+        };
+
+        let weighted = fit(class_statistics(&weighted), 0.);
+        let repeated = fit(class_statistics(&repeated), 0.);
+        assert_eq!(weighted.classes, vec![(0, 3.), (1, 0.5)]);
+        for (a, b) in weighted.densities.mean[0]
+            .iter()
+            .chain(&weighted.densities.variance[0])
+            .zip(
+                repeated.densities.mean[0]
+                    .iter()
+                    .chain(&repeated.densities.variance[0]),
+            )
+        {
+            assert!(abs_diff_eq!(a, b, epsilon = 1e-10));
+        }
+    }
+
+    #[test]
+    fn predict_and_probabilities() {
+        let params = fit(class_statistics(&example()), 1e-9);
+        let samples = arr2(&[[0., 0.], [7., 1.], [20., 0.]]);
+
+        let predictions: Array1<i64> = params.predict_samples(&samples).unwrap().into();
+        assert_eq!(predictions, arr1(&[0, 1, 1]));
+
+        let probabilities: Array2<f64> = params
+            .predict_probabilities(&samples, &[1, 0, 2])
+            .unwrap()
+            .into();
+        for row in probabilities.outer_iter() {
+            assert!(abs_diff_eq!(&row.scalar_sum(), &1., epsilon = 1e-10));
+            assert_eq!(row[2], 0.);
+        }
+        assert!(probabilities[(0, 1)] > 0.99);
+        assert!(probabilities[(2, 0)] > 0.99);
+
+        assert!(params.predict_samples(&arr2(&[[1.]])).is_err());
+    }
+
+    #[test]
+    fn constant_features() {
+        let data = TrainingData {
+            x: arr2(&[[1., 5.], [2., 5.], [8., 5.], [9., 5.]]).into(),
+            y: arr1(&[0, 0, 1, 1]).into(),
+            weights: None,
+        };
+        let params = fit(class_statistics(&data), 1e-9);
+        let predictions: Array1<i64> = params
+            .predict_samples(&arr2(&[[1.5, 5.], [8.5, 6.]]))
+            .unwrap()
+            .into();
+        assert_eq!(predictions, arr1(&[0, 1]));
+    }
+
+    #[test]
+    fn train_and_predict_streams() {
+        let result = ::timely::example(|scope| {
+            let model = GaussianNaiveBayes::new();
+            let params = split(&example(), 2).to_stream(scope).train(&model);
+            vec![AbomonableArray2::from(arr2(&[[2.5, 1.], [9., 0.]]))]
+                .to_stream(scope)
+                .predict(&model, params)
+                .map(|predictions| predictions.unwrap().view().to_vec())
+                .capture()
+        })
+        .extract();
+
+        assert_eq!(result, vec![(RootTimestamp::new(0), vec![vec![0, 1]])]);
+    }
+}
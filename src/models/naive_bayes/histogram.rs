@@ -0,0 +1,284 @@
+use super::{ClassDensities, NaiveBayesError, NaiveBayesParams};
+use data::dataflow::{ApplyLatest, ReduceEachTime};
+use data::serialization::*;
+use data::TrainingData;
+use models::decision_tree::classification::histogram::{
+    FeatureValueHistogramSet, Histogram, SerializableFeatureValueHistogramSet,
+};
+use models::decision_tree::histogram_generics::*;
+use models::decision_tree::tree::DecisionTree;
+use models::*;
+use ndarray::prelude::*;
+use num_traits::cast::cast;
+use std::marker::PhantomData;
+use timely::dataflow::operators::{Broadcast, Map};
+use timely::dataflow::{Scope, Stream};
+
+/// Naive Bayes classifier that estimates the density of each feature within each class
+/// from a streaming histogram, without assuming a particular distribution
+#[derive(Abomonation, Clone, Debug)]
+pub struct HistogramNaiveBayes<T, L> {
+    bins: usize,
+    min_density: T,
+    _l: PhantomData<L>,
+}
+
+impl<T: ContinuousValue, L> HistogramNaiveBayes<T, L> {
+    /// Creates a classifier with histograms of at most `bins` bins per feature and class
+    pub fn new(bins: usize) -> Self {
+        assert!(bins > 1, "Histograms need at least two bins");
+        HistogramNaiveBayes {
+            bins,
+            min_density: cast(1e-9).unwrap(),
+            _l: PhantomData,
+        }
+    }
+
+    /// Sets the density that is assumed for feature values outside of the range seen
+    /// for a class, so that a single unseen value does not rule out the class
+    pub fn min_density(mut self, min_density: T) -> Self {
+        assert!(min_density > T::zero(), "Minimum density must be positive");
+        self.min_density = min_density;
+        self
+    }
+}
+
+impl<T: ContinuousValue, L: DiscreteValue> ModelAttributes for HistogramNaiveBayes<T, L> {
+    type TrainingResult = NaiveBayesParams<L, HistogramDensities<T>>;
+}
+
+impl<T: ContinuousValue, L: DiscreteValue> LabelingModelAttributes for HistogramNaiveBayes<T, L> {
+    type Predictions = AbomonableArray1<L>;
+    type PredictErr = NaiveBayesError;
+}
+
+/// Histogram of each feature (inner) for each class (outer)
+#[derive(Abomonation, Clone, Debug)]
+pub struct HistogramDensities<T: ContinuousValue> {
+    pub histograms: Vec<Vec<Histogram<T>>>,
+    pub min_density: T,
+}
+
+impl<T: ContinuousValue> ClassDensities for HistogramDensities<T> {
+    type Value = T;
+
+    fn features(&self) -> usize {
+        self.histograms.first().map_or(0, |h| h.len())
+    }
+
+    fn log_density(&self, class: usize, sample: ArrayView1<T>) -> T {
+        sample
+            .iter()
+            .zip(&self.histograms[class])
+            .map(|(&x, histogram)| histogram.density(x).max(self.min_density).ln())
+            .sum()
+    }
+}
+
+fn fit<T: ContinuousValue, L: DiscreteValue>(
+    histograms: FeatureValueHistogramSet<T, L>,
+    bins: usize,
+    min_density: T,
+) -> NaiveBayesParams<L, HistogramDensities<T>> {
+    // all samples were collected in the root of an empty tree
+    let root = DecisionTree::<T, L>::default().root();
+    let classes = histograms
+        .find_node_label_weights(&root)
+        .unwrap_or_default();
+    let features = histograms
+        .get(&root)
+        .map_or(0, |features| features.iter().count());
+
+    let histograms = classes
+        .iter()
+        .map(|(label, _)| {
+            (0..features)
+                .map(|feature| {
+                    histograms
+                        .get(&root)
+                        .and_then(|features| features.get(&feature))
+                        .and_then(|labels| labels.get(label))
+                        .cloned()
+                        .unwrap_or_else(|| Histogram::new(bins))
+                })
+                .collect()
+        })
+        .collect();
+
+    NaiveBayesParams {
+        classes,
+        densities: HistogramDensities {
+            histograms,
+            min_density,
+        },
+    }
+}
+
+impl<S, T, L> Train<S, HistogramNaiveBayes<T, L>> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    T: ContinuousValue,
+    L: DiscreteValue,
+{
+    fn train(
+        &self,
+        model: &HistogramNaiveBayes<T, L>,
+    ) -> Stream<S, NaiveBayesParams<L, HistogramDensities<T>>> {
+        let bins = model.bins;
+        let min_density = model.min_density;
+
+        self.map(move |data| {
+            let histograms =
+                FeatureValueHistogramSet::from_data(&DecisionTree::default(), &[data], bins);
+            SerializableFeatureValueHistogramSet::from(histograms)
+        })
+        .reduce_each_time(|first, second| {
+            let mut first: FeatureValueHistogramSet<T, L> = first.into();
+            first.merge(second.into());
+            first.into()
+        })
+        .map(move |histograms| fit(histograms.into(), bins, min_density))
+        .broadcast()
+    }
+}
+
+impl<S, T, L> Predict<S, HistogramNaiveBayes<T, L>, NaiveBayesError>
+    for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    T: ContinuousValue,
+    L: DiscreteValue,
+{
+    fn predict(
+        &self,
+        _model: &HistogramNaiveBayes<T, L>,
+        train_results: Stream<S, NaiveBayesParams<L, HistogramDensities<T>>>,
+    ) -> Stream<S, Result<AbomonableArray1<L>, ModelError<NaiveBayesError>>> {
+        train_results.apply_latest(self, |_time, params, samples| {
+            params.predict_samples(&samples)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+    use timely::progress::timestamp::RootTimestamp;
+
+    fn example() -> Vec<TrainingData<f64, i64>> {
+        // the first feature separates the classes, the second is noise
+        let x = (0..40)
+            .map(|i| {
+                let class = i % 2;
+                vec![(class * 10 + i % 7) as f64, (i % 5) as f64]
+            })
+            .collect::<Vec<_>>();
+        let y = (0..40).map(|i| i % 2).collect::<Vec<_>>();
+
+        (0..2)
+            .map(|part| TrainingData {
+                x: Array2::from_shape_vec(
+                    (20, 2),
+                    x[part * 20..(part + 1) * 20]
+                        .iter()
+                        .flat_map(|row| row.clone())
+                        .collect(),
+                )
+                .unwrap()
+                .into(),
+                y: Array1::from_vec(y[part * 20..(part + 1) * 20].to_vec()).into(),
+                weights: None,
+            })
+            .collect()
+    }
+
+    fn fit_parts(
+        data: Vec<TrainingData<f64, i64>>,
+    ) -> NaiveBayesParams<i64, HistogramDensities<f64>> {
+        let mut histograms = FeatureValueHistogramSet::default();
+        for part in data {
+            histograms.merge(FeatureValueHistogramSet::from_data(
+                &DecisionTree::default(),
+                &[part],
+                10,
+            ));
+        }
+        fit(histograms, 10, 1e-9)
+    }
+
+    #[test]
+    fn predict_and_probabilities() {
+        let params = fit_parts(example());
+        assert_eq!(params.classes, vec![(0, 20.), (1, 20.)]);
+        assert_eq!(params.densities.features(), 2);
+
+        let samples = arr2(&[[3., 2.], [13., 2.], [-5., 1.], [100., 1.]]);
+        let predictions: Array1<i64> = params.predict_samples(&samples).unwrap().into();
+        assert_eq!(&predictions.slice(s![..2]), &arr1(&[0, 1]));
+
+        let probabilities: Array2<f64> = params
+            .predict_probabilities(&samples, &[0, 1])
+            .unwrap()
+            .into();
+        for row in probabilities.outer_iter() {
+            assert!(abs_diff_eq!(&row.scalar_sum(), &1., epsilon = 1e-10));
+        }
+        assert!(probabilities[(0, 0)] > 0.99);
+        assert!(probabilities[(1, 1)] > 0.99);
+
+        assert!(params.predict_samples(&arr2(&[[1.]])).is_err());
+    }
+
+    #[test]
+    fn constant_feature_per_class() {
+        // the first feature has a single value within each class
+        let data = TrainingData {
+            x: arr2(&[[1., 0.], [1., 1.], [1., 2.], [3., 0.], [3., 1.], [3., 2.]]).into(),
+            y: arr1(&[0, 0, 0, 1, 1, 1]).into(),
+            weights: None,
+        };
+        let params = fit_parts(vec![data]);
+
+        let samples = arr2(&[[1., 1.], [3., 1.]]);
+        let predictions: Array1<i64> = params.predict_samples(&samples).unwrap().into();
+        assert_eq!(predictions, arr1(&[0, 1]));
+
+        let probabilities: Array2<f64> = params
+            .predict_probabilities(&samples, &[0, 1])
+            .unwrap()
+            .into();
+        assert!(probabilities[(0, 0)] > 0.99);
+        assert!(probabilities[(1, 1)] > 0.99);
+    }
+
+    #[test]
+    fn weighted_samples() {
+        let data = example()
+            .into_iter()
+            .map(|part| {
+                let weights = part.y().mapv(|label| if label == 0 { 0.5 } else { 2. });
+                part.with_weights(weights)
+            })
+            .collect();
+        let params = fit_parts(data);
+        assert_eq!(params.classes, vec![(0, 10.), (1, 40.)]);
+    }
+
+    #[test]
+    fn train_and_predict_streams() {
+        let result = ::timely::example(|scope| {
+            let model = HistogramNaiveBayes::new(10);
+            let params = example().to_stream(scope).train(&model);
+            vec![AbomonableArray2::from(arr2(&[[4., 0.], [12., 4.]]))]
+                .to_stream(scope)
+                .predict(&model, params)
+                .map(|predictions| predictions.unwrap().view().to_vec())
+                .capture()
+        })
+        .extract();
+
+        assert_eq!(result, vec![(RootTimestamp::new(0), vec![vec![0, 1]])]);
+    }
+}
@@ -0,0 +1,136 @@
+//! Naive Bayes classifiers, which assume that the features are independent given the class.
+//!
+//! Both models are trained in a single pass: each worker summarizes the features of its
+//! samples per class, weighted by the sample weights. The summaries are merged on the first
+//! worker and turned into an estimate of the density of each feature for each class, which
+//! is broadcast to all workers. `GaussianNaiveBayes` assumes normally distributed features,
+//! `HistogramNaiveBayes` estimates the densities from streaming histograms and makes no
+//! assumption about their shape.
+
+pub use self::gaussian::*;
+pub use self::histogram::*;
+use data::serialization::*;
+use models::decision_tree::histogram_generics::ContinuousValue;
+use models::*;
+use ndarray::prelude::*;
+use num_traits::cast::cast;
+use std::cmp::Ordering;
+
+mod gaussian;
+mod histogram;
+
+#[derive(Fail, Debug, Abomonation, Clone)]
+pub enum NaiveBayesError {
+    #[fail(display = "Expected samples with {} features, got {}", _0, _1)]
+    FeatureMismatch(usize, usize),
+}
+
+/// Estimated densities of the features for each class
+pub trait ClassDensities {
+    /// Type of the feature values
+    type Value;
+
+    /// Number of features
+    fn features(&self) -> usize;
+
+    /// Logarithm of the density of the sample's features, given the class at index `class`
+    fn log_density(&self, class: usize, sample: ArrayView1<Self::Value>) -> Self::Value;
+}
+
+/// Classes, the total weight of their training samples and the densities of the features
+/// of a trained Naive Bayes classifier
+#[derive(Abomonation, Clone, Debug)]
+pub struct NaiveBayesParams<L, D> {
+    pub classes: Vec<(L, f64)>,
+    pub densities: D,
+}
+
+impl<L, D> NaiveBayesParams<L, D> {
+    /// Logarithm of the joint probability of each class (columns) and each sample (rows),
+    /// up to a constant
+    fn joint_log_likelihood<T>(
+        &self,
+        samples: ArrayView2<T>,
+    ) -> Result<Array2<T>, ModelError<NaiveBayesError>>
+    where
+        T: ContinuousValue,
+        D: ClassDensities<Value = T>,
+    {
+        if samples.cols() != self.densities.features() {
+            return Err(ModelError::PredictionFailed(
+                NaiveBayesError::FeatureMismatch(self.densities.features(), samples.cols()),
+            ));
+        }
+
+        let total: T = cast(self.classes.iter().map(|&(_, weight)| weight).sum::<f64>()).unwrap();
+        let mut likelihood = Array2::zeros((samples.rows(), self.classes.len()));
+        for (mut row, sample) in likelihood.outer_iter_mut().zip(samples.outer_iter()) {
+            for (class, (value, &(_, weight))) in row.iter_mut().zip(&self.classes).enumerate() {
+                let prior = cast::<_, T>(weight).unwrap() / total;
+                *value = prior.ln() + self.densities.log_density(class, sample);
+            }
+        }
+        Ok(likelihood)
+    }
+}
+
+impl<A, L, T, D> PredictSamples<A, AbomonableArray1<L>, NaiveBayesError> for NaiveBayesParams<L, D>
+where
+    for<'a> &'a A: AsArray<'a, T, Ix2>,
+    L: Copy,
+    T: ContinuousValue,
+    D: ClassDensities<Value = T>,
+{
+    fn predict_samples(
+        &self,
+        samples: &A,
+    ) -> Result<AbomonableArray1<L>, ModelError<NaiveBayesError>> {
+        let samples: ArrayView2<T> = samples.into();
+        let labels = self
+            .joint_log_likelihood(samples)?
+            .outer_iter()
+            .map(|row| {
+                let class = row
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Less))
+                    .map(|(class, _)| class)
+                    .expect("At least one class");
+                self.classes[class].0
+            })
+            .collect::<Array1<_>>();
+        Ok(labels.into())
+    }
+}
+
+impl<A, L, T, D> PredictProbabilities<A, L, NaiveBayesError> for NaiveBayesParams<L, D>
+where
+    for<'a> &'a A: AsArray<'a, T, Ix2>,
+    L: PartialEq,
+    T: ContinuousValue,
+    D: ClassDensities<Value = T>,
+{
+    /// Classes that did not occur in the training data have probability 0.
+    fn predict_probabilities(
+        &self,
+        samples: &A,
+        classes: &[L],
+    ) -> Result<AbomonableArray2<f64>, ModelError<NaiveBayesError>> {
+        let samples: ArrayView2<T> = samples.into();
+        let likelihood = self.joint_log_likelihood(samples)?;
+
+        let mut probabilities = Array2::zeros((likelihood.rows(), classes.len()));
+        for (mut row, likelihood) in probabilities.outer_iter_mut().zip(likelihood.outer_iter()) {
+            // normalize the joint probabilities, shifted by the maximum for numerical stability
+            let max = likelihood.fold(T::neg_infinity(), |max, &l| max.max(l));
+            let joint = likelihood.mapv(|l| (l - max).exp());
+            let sum = joint.scalar_sum();
+            for (j, class) in classes.iter().enumerate() {
+                if let Some(i) = self.classes.iter().position(|(c, _)| c == class) {
+                    row[j] = (joint[i] / sum).to_f64().unwrap();
+                }
+            }
+        }
+        Ok(probabilities.into())
+    }
+}
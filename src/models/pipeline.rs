@@ -0,0 +1,256 @@
+//! Models that transform the features of the samples before passing them to another model.
+//!
+//! A `Pipeline` fits its transformer and trains its inner model in one `train` call, and
+//! applies the same fitted transformation to the samples before each prediction. Since a
+//! pipeline is a model itself, several transformers are chained by nesting pipelines.
+//! Its training result can be persisted as JSON if the fitted transformation and the
+//! training result of the inner model implement the serde traits.
+
+use data::dataflow::{ApplyLatest, CombineEachTime};
+use data::preprocessing::{MapFeatures, TransformFeatures, Transformer};
+use data::serialization::*;
+use data::TrainingData;
+use failure::Fail;
+use models::*;
+use ndarray::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use timely::dataflow::{Scope, Stream};
+use timely::{Data, ExchangeData};
+
+/// Model that fits a transformer to the features of type `T` of the training data
+/// and trains the inner model on the transformed features
+#[derive(Clone, Abomonation)]
+pub struct Pipeline<Tr, M, T> {
+    transformer: Tr,
+    model: M,
+    _t: PhantomData<T>,
+}
+
+impl<Tr, M, T> Pipeline<Tr, M, T> {
+    pub fn new(transformer: Tr, model: M) -> Self {
+        Pipeline {
+            transformer,
+            model,
+            _t: PhantomData,
+        }
+    }
+}
+
+/// The fitted transformation and the training result of the inner model
+#[derive(Clone, Abomonation, Debug, Serialize, Deserialize)]
+pub struct PipelineResult<F, R> {
+    pub fitted: F,
+    pub model: R,
+}
+
+impl<F: Serialize, R: Serialize> PipelineResult<F, R> {
+    /// Writes the result as JSON
+    pub fn save<W: Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer(writer, self).map_err(io::Error::from)
+    }
+}
+
+impl<F: DeserializeOwned, R: DeserializeOwned> PipelineResult<F, R> {
+    /// Reads a result that was written by `save`. Input that does not describe a result
+    /// of these types is rejected with an `InvalidData` error.
+    pub fn load<Rd: Read>(reader: Rd) -> io::Result<Self> {
+        serde_json::from_reader(reader).map_err(io::Error::from)
+    }
+}
+
+impl<Tr, M, T> ModelAttributes for Pipeline<Tr, M, T>
+where
+    Tr: Transformer<T> + ExchangeData,
+    M: ModelAttributes,
+    M::TrainingResult: ExchangeData,
+    T: ExchangeData,
+{
+    type TrainingResult = PipelineResult<Tr::Fitted, M::TrainingResult>;
+}
+
+impl<Tr, M, T> LabelingModelAttributes for Pipeline<Tr, M, T>
+where
+    Tr: Transformer<T> + ExchangeData,
+    M: LabelingModelAttributes,
+    M::TrainingResult: ExchangeData,
+    T: ExchangeData,
+{
+    type Predictions = M::Predictions;
+    type PredictErr = M::PredictErr;
+}
+
+impl<S, Tr, M, T, L> Train<S, Pipeline<Tr, M, T>> for Stream<S, TrainingData<T, L>>
+where
+    S: Scope,
+    Tr: Transformer<T> + ExchangeData,
+    Tr::Fitted: MapFeatures<Input = T>,
+    <Tr::Fitted as MapFeatures>::Output: Data,
+    M: ModelAttributes,
+    M::TrainingResult: ExchangeData,
+    T: ExchangeData,
+    L: Data,
+    Stream<S, TrainingData<<Tr::Fitted as MapFeatures>::Output, L>>: Train<S, M>,
+{
+    fn train(
+        &self,
+        pipeline: &Pipeline<Tr, M, T>,
+    ) -> Stream<S, PipelineResult<Tr::Fitted, M::TrainingResult>> {
+        let fitted = self.fit_transformer(&pipeline.transformer);
+
+        // the data and the model of each time are paired with the transformer fitted at that time
+        let model_results = fitted
+            .combine_each_time(self, |fitted, data| {
+                let fitted = fitted.drain(..).last();
+                data.drain(..)
+                    .filter_map(|data| {
+                        Some(TrainingData {
+                            x: fitted.as_ref()?.map_features(data.x()).into(),
+                            y: data.y,
+                            weights: data.weights,
+                        })
+                    })
+                    .collect()
+            })
+            .train(&pipeline.model);
+
+        fitted.combine_each_time(&model_results, |fitted, models| {
+            let fitted = fitted.drain(..).last();
+            models
+                .drain(..)
+                .filter_map(|model| {
+                    Some(PipelineResult {
+                        fitted: fitted.clone()?,
+                        model,
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+impl<S, Tr, M, T, E> Predict<S, Pipeline<Tr, M, T>, E> for Stream<S, AbomonableArray2<T>>
+where
+    S: Scope,
+    Tr: Transformer<T> + ExchangeData,
+    M: LabelingModelAttributes,
+    M::TrainingResult: ExchangeData,
+    T: ExchangeData,
+    E: Data + Fail,
+    PipelineResult<Tr::Fitted, M::TrainingResult>:
+        PredictSamples<AbomonableArray2<T>, M::Predictions, E>,
+{
+    fn predict(
+        &self,
+        _pipeline: &Pipeline<Tr, M, T>,
+        train_results: Stream<S, PipelineResult<Tr::Fitted, M::TrainingResult>>,
+    ) -> Stream<S, Result<M::Predictions, ModelError<E>>> {
+        train_results.apply_latest(self, |_time, result, samples| {
+            result.predict_samples(&samples)
+        })
+    }
+}
+
+impl<A, F, R, P, E> PredictSamples<A, P, E> for PipelineResult<F, R>
+where
+    for<'a> &'a A: AsArray<'a, F::Input, Ix2>,
+    F: MapFeatures,
+    R: PredictSamples<AbomonableArray2<F::Output>, P, E>,
+    E: Data + Fail,
+{
+    fn predict_samples(&self, samples: &A) -> Result<P, ModelError<E>> {
+        let transformed: AbomonableArray2<F::Output> =
+            self.fitted.map_features(samples.into()).into();
+        self.model.predict_samples(&transformed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data::preprocessing::StandardScaler;
+    use data::quantize::QuantileQuantizer;
+    use models::decision_tree::tree::{DecisionTree, Rule};
+    use models::linear::LinearRegression;
+    use timely::dataflow::operators::capture::Extract;
+    use timely::dataflow::operators::*;
+    use timely::progress::timestamp::RootTimestamp;
+
+    fn example() -> PipelineResult<Vec<QuantileQuantizer>, DecisionTree<i64, f64>> {
+        let mut tree = DecisionTree::default();
+        let root = tree.root();
+        let (left, right) = tree.split(root, Rule::threshold(0, 1), None);
+        tree.label(left, -1.);
+        tree.label(right, 1.);
+
+        PipelineResult {
+            fitted: vec![QuantileQuantizer::new(vec![0.5])],
+            model: tree,
+        }
+    }
+
+    #[test]
+    fn predict_transformed_samples() {
+        let samples = arr2(&[[0.2], [0.5], [0.8]]);
+        let predictions: Array1<f64> = example().predict_samples(&samples).unwrap().into();
+        assert_eq!(predictions, arr1(&[-1., -1., 1.]));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut bytes = Vec::new();
+        example().save(&mut bytes).unwrap();
+
+        let loaded =
+            PipelineResult::<Vec<QuantileQuantizer>, DecisionTree<i64, f64>>::load(&bytes[..])
+                .unwrap();
+        assert_eq!(loaded.fitted, example().fitted);
+        assert_eq!(loaded.model.nodes(), example().model.nodes());
+
+        let truncated = &bytes[..bytes.len() / 2];
+        let error =
+            PipelineResult::<Vec<QuantileQuantizer>, DecisionTree<i64, f64>>::load(truncated)
+                .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn train_and_predict_streams() {
+        let result = ::timely::example(|scope| {
+            // y = 3x + 2, with features far from zero and of different scale
+            let parts = (0..2)
+                .map(|part| {
+                    let x = (0..5)
+                        .map(|i| 100. + (part * 5 + i) as f64)
+                        .collect::<Vec<_>>();
+                    TrainingData {
+                        y: x.iter().map(|x| 3. * x + 2.).collect::<Array1<_>>().into(),
+                        x: Array2::from_shape_vec((5, 1), x).unwrap().into(),
+                        weights: None,
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let pipeline = Pipeline::new(StandardScaler, LinearRegression::new());
+            let results = parts.to_stream(scope).train(&pipeline);
+            vec![AbomonableArray2::from(arr2(&[[0.], [110.]]))]
+                .to_stream(scope)
+                .predict(&pipeline, results)
+                .map(|predictions| {
+                    predictions
+                        .unwrap()
+                        .view()
+                        .iter()
+                        .map(|p| p.round() as i64)
+                        .collect::<Vec<_>>()
+                })
+                .capture()
+        })
+        .extract();
+
+        assert_eq!(result, vec![(RootTimestamp::new(0), vec![vec![2, 332]])]);
+    }
+}